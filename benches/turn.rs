@@ -0,0 +1,54 @@
+//! Turn time on a large region. Run with `cargo bench`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use wonderfill::world::{
+    data::DataManager, entity::friendly::MoveLeft, space::TilePos, tile::core::BaseGround, World,
+};
+
+/// Side lengths of the benchmark region. 400 * 250 = 100k tiles
+const REGION_SIZE: (i32, i32) = (400, 250);
+
+/// One entity every this many tiles in each direction
+const ENTITY_SPACING: i32 = 10;
+
+fn big_region() -> DataManager {
+    let mut mgr = DataManager::new(String::from("bench"));
+    for x in 0..REGION_SIZE.0 {
+        for y in 0..REGION_SIZE.1 {
+            mgr.push_tile_override(Box::new(BaseGround::default((x, y).into())));
+        }
+    }
+    for x in (1..REGION_SIZE.0).step_by(ENTITY_SPACING as usize) {
+        for y in (1..REGION_SIZE.1).step_by(ENTITY_SPACING as usize) {
+            mgr.push_entity(Box::new(MoveLeft::new((x, y).into())));
+        }
+    }
+    mgr
+}
+
+fn turn(c: &mut Criterion) {
+    let mut world = World::new();
+    world.mgr = big_region();
+    assert_eq!(world.mgr.tile_count(), 100_000);
+
+    // Waiting a turn, which goes through everything a move does, keeping the turn for undo included
+    c.bench_function("turn 100k tiles", |b| {
+        b.iter(|| world.take_turn(TilePos::origin()))
+    });
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut mgr = big_region();
+    let far_corner: TilePos = (REGION_SIZE.0 - 1, REGION_SIZE.1 - 1).into();
+
+    c.bench_function("tile lookup 100k tiles", |b| {
+        b.iter(|| mgr.get_tile_at_pos(black_box(far_corner)).is_some())
+    });
+    c.bench_function("entity lookup 100k tiles", |b| {
+        b.iter(|| mgr.get_entity_at_pos(black_box(far_corner)).is_none())
+    });
+}
+
+criterion_group!(benches, turn, lookup);
+criterion_main!(benches);
@@ -1,13 +1,14 @@
 use std::collections::HashMap;
 
-use speedy2d::{color::Color, shape::Rectangle, Graphics2D};
-
-use serde::{Deserialize, Serialize};
+use speedy2d::{color::Color, shape::Rectangle};
 
 use crate::{
-    draw::ui::img::{Img, ImgManager},
-    world::{time::Clock, space::Direction},
+    draw::{
+        atlas::{self, AtlasError},
+        render::Renderer,
+    },
     utility::time::NInstant,
+    world::{space::Direction, time::Clock},
 };
 
 #[derive(Debug)]
@@ -15,19 +16,38 @@ pub enum AnimationSelectError {
     NotFound,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug)]
+pub enum AnimationLoadError {
+    /// No atlas describes the sprite sheet
+    NoAtlas(String),
+    /// The atlas doesn't have the sprite
+    NotFound(String),
+    Atlas(AtlasError),
+}
+
+#[derive(Debug, Clone)]
 pub struct Animation {
-    src: Img,
+    /// Path of the sprite sheet
+    src: String,
     frame_size: (u16, u16),
+    /// Empty pixels around each frame in the sheet
+    gutter: u16,
     frames: HashMap<String, (bool, Vec<(u16, u16)>)>,
     default: (u16, u16),
     pub frame_loop: Option<(bool, Vec<(u16, u16)>)>,
     start: NInstant,
     iter_speed_ms: u16,
 }
+/// Placeholder for skipped fields while loading. Gets replaced by the real sprite before it's drawn
+impl Default for Animation {
+    fn default() -> Self {
+        Animation::still(String::new(), (0, 0), (0, 0))
+    }
+}
+
 impl Animation {
     pub fn new(
-        src: Img,
+        src: String,
         frame_size: (u16, u16),
         frames: HashMap<String, (bool, Vec<(u16, u16)>)>,
         default: (u16, u16),
@@ -36,6 +56,7 @@ impl Animation {
         Animation {
             src,
             frame_size,
+            gutter: 1,
             frames,
             default,
             frame_loop: None,
@@ -44,15 +65,38 @@ impl Animation {
         }
     }
 
-    pub fn still(src: Img, frame_size: (u16, u16), frame_pos: (u16, u16)) -> Animation {
+    pub fn still(src: String, frame_size: (u16, u16), frame_pos: (u16, u16)) -> Animation {
         Self::new(src, frame_size, HashMap::new(), frame_pos, 0)
     }
 
+    /// Builds the animations of `sprite` from the atlas of the sheet at `src`
+    pub fn from_atlas(
+        src: &str,
+        sprite: &str,
+        variant: Direction,
+    ) -> Result<Animation, AnimationLoadError> {
+        let atlas = atlas::get(src).ok_or_else(|| AnimationLoadError::NoAtlas(src.to_string()))?;
+        let frames = atlas
+            .animations(sprite, variant)
+            .ok_or_else(|| AnimationLoadError::NotFound(sprite.to_string()))?
+            .map_err(AnimationLoadError::Atlas)?;
+
+        let mut result = Self::new(
+            src.to_string(),
+            atlas.frame_size,
+            frames,
+            atlas.default,
+            atlas.iter_speed_ms,
+        );
+        result.gutter = atlas.gutter;
+        Ok(result)
+    }
+
     pub fn select(&mut self, anim_name: &str) -> Result<(), AnimationSelectError> {
         self.start = NInstant::now();
         self.intercept(anim_name)
     }
-    
+
     pub fn intercept(&mut self, anim_name: &str) -> Result<(), AnimationSelectError> {
         match self.frames.get(anim_name) {
             Some(frames) => {
@@ -68,15 +112,11 @@ impl Animation {
 
     fn draw_helper(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         window_rect: Rectangle<f32>,
         do_offset: bool,
         color: Color,
     ) {
-        if self.src.state.is_none() {
-            self.src.init(graphics, manager);
-        }
         let frame_pos = match &self.frame_loop {
             Some((do_loop, frame_loop)) => {
                 let duration_ms = self.start.get_instant().elapsed().as_millis();
@@ -97,45 +137,52 @@ impl Animation {
             None => self.default,
         };
 
-        if let Some(img) = &self.src.state {
-            graphics.draw_rectangle_image_subset_tinted(
-                window_rect,
-                color,
-                self.get_bounds_rect_from_pos(frame_pos),
-                img,
-            );
-        }
+        renderer.draw_image_subset_tinted(
+            &self.src,
+            window_rect,
+            color,
+            self.get_bounds_rect_from_pos(frame_pos),
+        );
     }
 
     pub fn draw_overworld(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         clock: &Clock,
         window_rect: Rectangle<f32>,
         color: Color,
     ) {
-        self.draw_helper(graphics, manager, window_rect, !clock.is_day(), color);
+        self.draw_helper(renderer, window_rect, !clock.is_day(), color);
     }
 
-    pub fn draw(
+    pub fn draw(&mut self, renderer: &mut dyn Renderer, window_rect: Rectangle<f32>) {
+        self.draw_helper(renderer, window_rect, false, Color::WHITE);
+    }
+
+    /// Draws the day frame multiplied by `color`, e.g. for previews outside the world
+    pub fn draw_tinted(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         window_rect: Rectangle<f32>,
+        color: Color,
     ) {
-        self.draw_helper(graphics, manager, window_rect, false, Color::WHITE);
+        self.draw_helper(renderer, window_rect, false, color);
     }
 
+    /// Where the frame at `pos` is in the sprite sheet, in pixels
     fn get_bounds_rect_from_pos(&self, pos: (u16, u16)) -> Rectangle {
-        let img_bounds = self.src.state.as_ref().unwrap().size();
+        let gutter = self.gutter as f32;
+        let cell = (
+            self.frame_size.0 as f32 + 2.0 * gutter,
+            self.frame_size.1 as f32 + 2.0 * gutter,
+        );
         let top_left = (
-            ((pos.0 as f32) * (self.frame_size.0 as f32 + 2.0) + 1.0) / (img_bounds.x as f32),
-            ((pos.1 as f32) * (self.frame_size.1 as f32 + 2.0) + 1.0) / (img_bounds.y as f32),
+            (pos.0 as f32) * cell.0 + gutter,
+            (pos.1 as f32) * cell.1 + gutter,
         );
         let bottom_right = (
-            ((pos.0 as f32 + 1.0) * (self.frame_size.0 as f32 + 2.0) - 1.0) / (img_bounds.x as f32),
-            ((pos.1 as f32 + 1.0) * (self.frame_size.1 as f32 + 2.0) - 1.0) / (img_bounds.y as f32),
+            (pos.0 as f32 + 1.0) * cell.0 - gutter,
+            (pos.1 as f32 + 1.0) * cell.1 - gutter,
         );
         return Rectangle::from_tuples(top_left, bottom_right);
     }
@@ -153,4 +200,4 @@ pub fn match_directions(direction: Direction, top_left: (u16, u16)) -> (u16, u16
         Direction::CornerTL => top_left,
         Direction::Center => (top_left.0 + 2, top_left.1 + 1),
     }
-}
\ No newline at end of file
+}
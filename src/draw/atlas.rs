@@ -0,0 +1,189 @@
+//! Sprite sheet layouts. Every sheet `name.png` has a `name.atlas.json` next to it
+//! giving the size of its frames and naming the frames and animations of each sprite
+
+use std::{collections::HashMap, fs, io, path::Path, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::{draw::animation, world::space::Direction};
+
+/// Folder searched for atlas files
+const ATLAS_DIR: &str = "assets/img";
+const ATLAS_EXTENSION: &str = ".atlas.json";
+
+/// Frames of each named animation, and whether the animation loops
+pub type Frames = HashMap<String, (bool, Vec<(u16, u16)>)>;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// A sprite refers to a frame name the atlas doesn't define
+    UnknownFrame(String),
+}
+
+impl From<io::Error> for AtlasError {
+    fn from(e: io::Error) -> Self {
+        AtlasError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AtlasError {
+    fn from(e: serde_json::Error) -> Self {
+        AtlasError::Parse(e)
+    }
+}
+
+/// Layout of one sprite sheet. Frame coordinates count frames, not pixels
+#[derive(Debug, Deserialize)]
+pub struct Atlas {
+    /// Size of a frame in pixels, not counting the gutter
+    pub frame_size: (u16, u16),
+    /// Empty pixels on each side of every frame
+    #[serde(default = "default_gutter")]
+    pub gutter: u16,
+    /// Frame drawn when no animation is selected
+    pub default: (u16, u16),
+    #[serde(default = "default_iter_speed")]
+    pub iter_speed_ms: u16,
+    /// Names for frames so sprites don't have to repeat coordinates
+    #[serde(default)]
+    frames: HashMap<String, (u16, u16)>,
+    sprites: HashMap<String, SpriteDef>,
+}
+
+fn default_gutter() -> u16 {
+    1
+}
+
+fn default_iter_speed() -> u16 {
+    100
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FrameRef {
+    Pos((u16, u16)),
+    Name(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AnimDef {
+    /// Shorthand for a looping animation
+    Frames(Vec<FrameRef>),
+    Full {
+        frames: Vec<FrameRef>,
+        #[serde(default = "default_looping")]
+        looping: bool,
+    },
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SpriteDef {
+    /// A 5 by 3 block with a frame for each direction, given by its top left frame.
+    /// See [`animation::match_directions`] for the layout
+    Directional { directional: FrameRef },
+    /// A frame for each direction, for sprites that don't follow the usual layout
+    Variants {
+        variants: HashMap<Direction, FrameRef>,
+    },
+    /// Named animations. Sprites with a single look just have a "base" animation
+    Animations(HashMap<String, AnimDef>),
+}
+
+impl Atlas {
+    pub fn load(path: &Path) -> Result<Atlas, AtlasError> {
+        let atlas: Atlas = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        // Catch typos in frame names now rather than the first time the sprite is drawn
+        for name in atlas.sprites.keys() {
+            for variant in Direction::ALL {
+                atlas.animations(name, variant).transpose()?;
+            }
+        }
+        Ok(atlas)
+    }
+
+    pub fn contains(&self, sprite: &str) -> bool {
+        self.sprites.contains_key(sprite)
+    }
+
+    /// Every animation of `sprite` as seen from `variant`. None if the atlas doesn't have the sprite
+    pub fn animations(
+        &self,
+        sprite: &str,
+        variant: Direction,
+    ) -> Option<Result<Frames, AtlasError>> {
+        let result = match self.sprites.get(sprite)? {
+            SpriteDef::Directional { directional } => self
+                .frame(directional)
+                .map(|top_left| base(animation::match_directions(variant, top_left))),
+            SpriteDef::Variants { variants } => match variants.get(&variant) {
+                Some(frame) => self.frame(frame).map(base),
+                None => Ok(base(self.default)),
+            },
+            SpriteDef::Animations(anims) => anims
+                .iter()
+                .map(|(name, anim)| {
+                    let (frames, looping) = match anim {
+                        AnimDef::Frames(frames) => (frames, true),
+                        AnimDef::Full { frames, looping } => (frames, *looping),
+                    };
+                    let frames = frames
+                        .iter()
+                        .map(|frame| self.frame(frame))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok((name.clone(), (looping, frames)))
+                })
+                .collect(),
+        };
+        Some(result)
+    }
+
+    fn frame(&self, frame: &FrameRef) -> Result<(u16, u16), AtlasError> {
+        match frame {
+            FrameRef::Pos(pos) => Ok(*pos),
+            FrameRef::Name(name) => self
+                .frames
+                .get(name)
+                .copied()
+                .ok_or_else(|| AtlasError::UnknownFrame(name.clone())),
+        }
+    }
+}
+
+fn base(frame: (u16, u16)) -> Frames {
+    let mut frames = Frames::new();
+    frames.insert(String::from("base"), (true, vec![frame]));
+    frames
+}
+
+/// The atlas of the sprite sheet at `src`. Every atlas is read the first time any is asked for
+pub fn get(src: &str) -> Option<&'static Atlas> {
+    static ATLASES: OnceLock<HashMap<String, Atlas>> = OnceLock::new();
+    ATLASES.get_or_init(load_all).get(src)
+}
+
+/// Reads every atlas file, keyed by the path of the sheet it describes
+fn load_all() -> HashMap<String, Atlas> {
+    let mut result = HashMap::new();
+    for entry in fs::read_dir(ATLAS_DIR).expect("Couldn't read sprite folder") {
+        let path = entry.unwrap().path();
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if let Some(sheet) = file_name.strip_suffix(ATLAS_EXTENSION) {
+            let atlas = Atlas::load(&path)
+                .unwrap_or_else(|e| panic!("Couldn't load atlas {:?}: {:?}", path, e));
+            result.insert(format!("{}/{}.png", ATLAS_DIR, sheet), atlas);
+        }
+    }
+    result
+}
@@ -1,4 +1,8 @@
 pub mod animation;
-pub mod ui;
+pub mod atlas;
+/// drawing without depending on a particular backend
+pub mod render;
 /// anything related to the screen or window
 pub mod screen;
+pub mod sprite;
+pub mod ui;
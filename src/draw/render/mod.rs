@@ -0,0 +1,33 @@
+//! Everything in the game is drawn through a [`Renderer`], so the same drawing code
+//! can go to the window or to an image in memory
+
+use speedy2d::{color::Color, shape::Rectangle};
+
+/// draws to the window with speedy2d
+pub mod window;
+
+/// draws to an RGBA image without a window or GPU
+pub mod software;
+
+pub trait Renderer {
+    /// Size of what's being drawn to, in pixels
+    fn resolution(&self) -> (u32, u32);
+
+    /// Fills everything with `color`
+    fn clear(&mut self, color: Color);
+
+    /// Draws the part of the image at `src` inside `subset` stretched over `rect`.
+    /// `subset` is measured in pixels of the image. Every pixel is multiplied by `tint`
+    fn draw_image_subset_tinted(
+        &mut self,
+        src: &str,
+        rect: Rectangle,
+        tint: Color,
+        subset: Rectangle,
+    );
+
+    fn draw_rect(&mut self, rect: Rectangle, color: Color);
+
+    /// Draws `text` with its top left at `pos`, wrapping lines longer than `wrap_width`
+    fn draw_text(&mut self, pos: (f32, f32), color: Color, size: f32, wrap_width: f32, text: &str);
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+use rusttype::{point, Font, Scale};
+use speedy2d::{color::Color, shape::Rectangle};
+
+use crate::draw::ui::text;
+
+use super::Renderer;
+
+/// Rasterizes everything into an image in memory. Sprites are sampled nearest-neighbor like in the window
+pub struct SoftwareRenderer {
+    frame: RgbaImage,
+    images: HashMap<String, RgbaImage>,
+    font: Font<'static>,
+}
+
+impl SoftwareRenderer {
+    /// A black frame of `width` by `height` pixels
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer {
+            frame: RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])),
+            images: HashMap::new(),
+            font: Font::try_from_bytes(text::FONT_BYTES).unwrap(),
+        }
+    }
+
+    /// Everything drawn so far
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    pub fn into_frame(self) -> RgbaImage {
+        self.frame
+    }
+
+    /// Mixes `color` into the pixel at (`x`, `y`) by its alpha times `coverage`
+    fn blend(&mut self, x: u32, y: u32, color: [f32; 4], coverage: f32) {
+        let alpha = color[3] * coverage;
+        if alpha <= 0.0 {
+            return;
+        }
+        let pixel = self.frame.get_pixel_mut(x, y);
+        for i in 0..3 {
+            let under = pixel[i] as f32 / 255.0;
+            pixel[i] = ((color[i] * alpha + under * (1.0 - alpha)) * 255.0).round() as u8;
+        }
+        let under = pixel[3] as f32 / 255.0;
+        pixel[3] = ((alpha + under * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+
+    /// Pixels of the frame covered by `rect`, as (left, top, right, bottom). Right and bottom are exclusive
+    fn pixel_bounds(&self, rect: &Rectangle) -> (u32, u32, u32, u32) {
+        let clamp = |value: f32, max: u32| value.round().max(0.0).min(max as f32) as u32;
+        (
+            clamp(rect.top_left().x, self.frame.width()),
+            clamp(rect.top_left().y, self.frame.height()),
+            clamp(rect.bottom_right().x, self.frame.width()),
+            clamp(rect.bottom_right().y, self.frame.height()),
+        )
+    }
+}
+
+fn channels(color: Color) -> [f32; 4] {
+    [color.r(), color.g(), color.b(), color.a()]
+}
+
+impl Renderer for SoftwareRenderer {
+    fn resolution(&self) -> (u32, u32) {
+        self.frame.dimensions()
+    }
+
+    fn clear(&mut self, color: Color) {
+        let [r, g, b, a] = channels(color);
+        let pixel = Rgba([
+            (r * 255.0).round() as u8,
+            (g * 255.0).round() as u8,
+            (b * 255.0).round() as u8,
+            (a * 255.0).round() as u8,
+        ]);
+        for p in self.frame.pixels_mut() {
+            *p = pixel;
+        }
+    }
+
+    fn draw_image_subset_tinted(
+        &mut self,
+        src: &str,
+        rect: Rectangle,
+        tint: Color,
+        subset: Rectangle,
+    ) {
+        if !self.images.contains_key(src) {
+            let img = image::open(src).unwrap().to_rgba8();
+            self.images.insert(src.to_string(), img);
+        }
+        // Taken out while drawing so pixels can be blended into the frame
+        let img = self.images.remove(src).unwrap();
+
+        let tint = channels(tint);
+        let (left, top, right, bottom) = self.pixel_bounds(&rect);
+        let sample = |pixel: u32, from: f32, size: f32, sub_from: f32, sub_size: f32| {
+            let along = (pixel as f32 + 0.5 - from) / size;
+            (sub_from + along * sub_size).floor().max(sub_from) as u32
+        };
+        for y in top..bottom {
+            let src_y = sample(
+                y,
+                rect.top_left().y,
+                rect.height(),
+                subset.top_left().y,
+                subset.height(),
+            );
+            for x in left..right {
+                let src_x = sample(
+                    x,
+                    rect.top_left().x,
+                    rect.width(),
+                    subset.top_left().x,
+                    subset.width(),
+                );
+                if src_x >= img.width() || src_y >= img.height() {
+                    continue;
+                }
+                let texel = img.get_pixel(src_x, src_y);
+                let mut color = [0.0; 4];
+                for i in 0..4 {
+                    color[i] = texel[i] as f32 / 255.0 * tint[i];
+                }
+                self.blend(x, y, color, 1.0);
+            }
+        }
+
+        self.images.insert(src.to_string(), img);
+    }
+
+    fn draw_rect(&mut self, rect: Rectangle, color: Color) {
+        let color = channels(color);
+        let (left, top, right, bottom) = self.pixel_bounds(&rect);
+        for y in top..bottom {
+            for x in left..right {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, pos: (f32, f32), color: Color, size: f32, wrap_width: f32, text: &str) {
+        let color = channels(color);
+        let scale = Scale::uniform(size);
+        let metrics = self.font.v_metrics(scale);
+        let line_height = metrics.ascent - metrics.descent + metrics.line_gap;
+        let advance = |c: char| self.font.glyph(c).scaled(scale).h_metrics().advance_width;
+        let space = advance(' ');
+
+        // Lay out word by word, moving whole words to the next line when they don't fit
+        let mut glyphs = Vec::new();
+        let mut caret = (0.0, 0.0);
+        for line in text.lines() {
+            for word in line.split_whitespace() {
+                let width: f32 = word.chars().map(advance).sum();
+                if caret.0 > 0.0 && caret.0 + width > wrap_width {
+                    caret = (0.0, caret.1 + line_height);
+                }
+                for c in word.chars() {
+                    glyphs.push((c, caret));
+                    caret.0 += advance(c);
+                }
+                caret.0 += space;
+            }
+            caret = (0.0, caret.1 + line_height);
+        }
+
+        let (width, height) = self.frame.dimensions();
+        for (c, (x, y)) in glyphs {
+            let glyph = self
+                .font
+                .glyph(c)
+                .scaled(scale)
+                .positioned(point(pos.0 + x, pos.1 + y + metrics.ascent));
+            let bounds = match glyph.pixel_bounding_box() {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            glyph.draw(|gx, gy, coverage| {
+                let x = bounds.min.x + gx as i32;
+                let y = bounds.min.y + gy as i32;
+                if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                    self.blend(x as u32, y as u32, color, coverage);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use speedy2d::{
+    color::Color,
+    font::{Font, TextAlignment, TextLayout, TextOptions},
+    shape::Rectangle,
+    Graphics2D,
+};
+
+use crate::draw::{screen, ui::img::ImgManager};
+
+use super::Renderer;
+
+/// Draws to the window for one frame
+pub struct WindowRenderer<'a> {
+    graphics: &'a mut Graphics2D,
+    images: &'a mut ImgManager,
+    font: &'a Font,
+}
+
+impl<'a> WindowRenderer<'a> {
+    pub fn new(
+        graphics: &'a mut Graphics2D,
+        images: &'a mut ImgManager,
+        font: &'a Font,
+    ) -> WindowRenderer<'a> {
+        WindowRenderer {
+            graphics,
+            images,
+            font,
+        }
+    }
+}
+
+impl<'a> Renderer for WindowRenderer<'a> {
+    fn resolution(&self) -> (u32, u32) {
+        screen::get_resolution()
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.graphics.clear_screen(color);
+    }
+
+    fn draw_image_subset_tinted(
+        &mut self,
+        src: &str,
+        rect: Rectangle,
+        tint: Color,
+        subset: Rectangle,
+    ) {
+        let img = self.images.get_img(src, self.graphics);
+        // speedy2d wants the subset as a fraction of the image
+        let size = img.size();
+        let (width, height) = (size.x as f32, size.y as f32);
+        let subset = Rectangle::from_tuples(
+            (subset.top_left().x / width, subset.top_left().y / height),
+            (
+                subset.bottom_right().x / width,
+                subset.bottom_right().y / height,
+            ),
+        );
+        self.graphics
+            .draw_rectangle_image_subset_tinted(rect, tint, subset, &img);
+    }
+
+    fn draw_rect(&mut self, rect: Rectangle, color: Color) {
+        self.graphics.draw_rectangle(rect, color);
+    }
+
+    fn draw_text(&mut self, pos: (f32, f32), color: Color, size: f32, wrap_width: f32, text: &str) {
+        let layout = self.font.layout_text(
+            text,
+            size,
+            TextOptions::new().with_wrap_to_width(wrap_width, TextAlignment::Left),
+        );
+        self.graphics.draw_text(pos, color, &layout);
+    }
+}
@@ -9,6 +9,15 @@ pub struct Camera {
     pub pos: GamePos,
     pub width: f32,
     pub height: f32,
+    /// Size in pixels of what the camera is drawn to. Kept up to date by [`crate::world::World::draw`]
+    #[serde(skip, default = "get_resolution")]
+    pub resolution: (u32, u32),
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(GamePos::origin(), 10.0, 10.0)
+    }
 }
 
 impl Camera {
@@ -18,9 +27,10 @@ impl Camera {
             pos,
             width,
             height,
+            resolution: get_resolution(),
         }
     }
-    
+
     /// moves the camera. Can't say move because it's a rust keyword
     pub fn moove(&mut self, change_pos: GamePos) {
         self.pos += change_pos;
@@ -28,7 +38,7 @@ impl Camera {
 
     /// converts a game position in the world to a pixel position on the screen
     pub fn game_to_pix(&self, point: GamePos) -> (f32, f32) {
-        let res = get_resolution();
+        let res = self.resolution;
         let a_pos = (
             ((self.width / 2.0) + point.x - self.pos.x) / self.width,
             ((self.height / 2.0) + point.y - self.pos.y) / self.height,
@@ -39,7 +49,7 @@ impl Camera {
 
     /// converts a pixel position on the screen to a game position in the world
     pub fn pix_to_game(&self, point: (u32, u32)) -> GamePos {
-        let res = get_resolution();
+        let res = self.resolution;
         let rel_pos = (
             (point.0 as f32) / (res.0 as f32),
             (point.1 as f32) / (res.1 as f32),
@@ -59,7 +69,7 @@ impl Camera {
         )
     }
 
-    /// returns a rectangle given a center, size, and offset from the center. 
+    /// returns a rectangle given a center, size, and offset from the center.
     /// identical to rect_from_center but with an offset
     pub fn rect_from_offset(&self, pos: GamePos, size: GamePos, offset: GamePos) -> Rectangle {
         Rectangle::from_tuples(
@@ -1,21 +1,27 @@
 use std::{
-    env,
-    fs::{self, File},
-    io::{self, BufReader},
-    path::{Path, PathBuf}, collections::HashMap,
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
 };
 
 use speedy2d::{
     color::Color,
-    window::{MouseButton, VirtualKeyCode, WindowHandler, WindowHelper},
+    font::Font,
+    window::{MouseButton, MouseScrollDistance, VirtualKeyCode, WindowHandler, WindowHelper},
     Graphics2D,
 };
 
 use crate::{
+    draw::{
+        render::{window::WindowRenderer, Renderer},
+        ui::{img::ImgManager, text},
+    },
     screen::{title::TitleScreen, Screen},
-    draw::ui::img::ImgManager,
     world::{
+        editor,
+        entity::Entity,
         generation,
+        save::{self, SaveError},
         World,
     },
 };
@@ -36,13 +42,18 @@ pub struct GameScreen {
 
     /// for managing sprites
     img_manager: ImgManager,
+
+    /// for text drawn over the world
+    font: Font,
 }
 
 impl WindowHandler<String> for GameScreen {
     fn on_draw(&mut self, _helper: &mut WindowHelper<String>, graphics: &mut Graphics2D) {
-        graphics.clear_screen(Color::GRAY);
+        let mut renderer = WindowRenderer::new(graphics, &mut self.img_manager, &self.font);
+        renderer.clear(Color::GRAY);
 
-        self.world.draw(graphics, &mut self.img_manager);
+        self.world.update();
+        self.world.draw(&mut renderer);
     }
     fn on_key_down(
         &mut self,
@@ -52,6 +63,7 @@ impl WindowHandler<String> for GameScreen {
     ) {
         if let Some(virtual_key_code) = virtual_key_code {
             match virtual_key_code {
+                VirtualKeyCode::Escape if self.world.cancel_prompt() => (),
                 VirtualKeyCode::Escape => {
                     // on escape, save game and return to title screen
                     self.save_world();
@@ -66,6 +78,9 @@ impl WindowHandler<String> for GameScreen {
             self.current_input.insert(virtual_key_code, true);
         }
     }
+    fn on_keyboard_char(&mut self, _helper: &mut WindowHelper<String>, unicode_codepoint: char) {
+        self.world.send_char(unicode_codepoint);
+    }
     fn on_key_up(
         &mut self,
         _helper: &mut WindowHelper<String>,
@@ -90,12 +105,22 @@ impl WindowHandler<String> for GameScreen {
         self.world.on_mouse_button_down(helper, button);
     }
 
-    fn on_mouse_button_up(
+    fn on_mouse_button_up(&mut self, helper: &mut WindowHelper<String>, button: MouseButton) {
+        self.world.on_mouse_button_up(helper, button);
+    }
+
+    fn on_mouse_wheel_scroll(
         &mut self,
-        helper: &mut WindowHelper<String>,
-        button: MouseButton
+        _helper: &mut WindowHelper<String>,
+        distance: MouseScrollDistance,
     ) {
-        self.world.on_mouse_button_up(helper, button);
+        // scrolling down (negative) moves further down the palette
+        let rows = match distance {
+            MouseScrollDistance::Lines { y, .. } => -y.round(),
+            MouseScrollDistance::Pixels { y, .. } => -(y / editor::ROW_HEIGHT as f64).round(),
+            MouseScrollDistance::Pages { y, .. } => -y.round() * 10.0,
+        };
+        self.world.on_mouse_wheel(rows as i32);
     }
 }
 
@@ -116,7 +141,7 @@ impl GameScreen {
         GameScreen::with_world(generation::make_new_empty_world())
     }
 
-    pub fn load() -> io::Result<GameScreen> {
+    pub fn load() -> Result<GameScreen, SaveError> {
         let mut result = GameScreen::load_world()?;
         if let Some(minigame) = &mut result.minigame {
             minigame.reset();
@@ -125,20 +150,22 @@ impl GameScreen {
     }
 
     fn with_world(mut world: World) -> GameScreen {
+        world.load_manifest();
+        world.camera.pos = world.player.get_pos().into();
         world.update_anims();
         GameScreen {
             new_screen: None,
             current_input: HashMap::new(),
             world,
             img_manager: ImgManager::new(),
+            font: text::get_font(),
         }
     }
 
-    fn save_world(&self) {
-        let path = GameScreen::get_file_path();
-        let file = fs::File::create(path).unwrap();
-        let writer = io::LineWriter::new(file);
-        serde_json::to_writer(writer, &self.world).unwrap();
+    fn save_world(&mut self) {
+        self.world.save_region();
+
+        save::write(&GameScreen::get_file_path(), &self.world).unwrap();
     }
 
     fn get_file_path() -> PathBuf {
@@ -151,13 +178,7 @@ impl GameScreen {
         path
     }
 
-    fn load_world() -> io::Result<World> {
-        let path = GameScreen::get_file_path();
-        let file: File = File::open(path)?;
-        let rdr = BufReader::new(file);
-
-        let result = Ok(serde_json::from_reader(rdr)?);
-
-        result
+    fn load_world() -> Result<World, SaveError> {
+        save::read(&GameScreen::get_file_path())
     }
 }
@@ -138,6 +138,15 @@ impl WindowHandler<String> for RedirectHandler {
         self.cur_screen.on_mouse_button_up(helper, button);
     }
 
+    fn on_mouse_wheel_scroll(
+        &mut self,
+        helper: &mut WindowHelper<String>,
+        distance: speedy2d::window::MouseScrollDistance,
+    ) {
+        // redirect
+        self.cur_screen.on_mouse_wheel_scroll(helper, distance);
+    }
+
     fn on_key_down(
         &mut self,
         helper: &mut WindowHelper<String>,
@@ -0,0 +1,65 @@
+//! Which sprite sheet every tile and entity is drawn from.
+//! Animations aren't saved, they're looked up by type name in the sheet's atlas whenever something is created or loaded
+
+use crate::{
+    draw::{animation::Animation, atlas},
+    world::space::Direction,
+};
+
+const TILE_SHEET: &str = "assets/img/tiles.png";
+const ENTITY_SHEET: &str = "assets/img/entities.png";
+const PLAYER_SHEET: &str = "assets/img/player.png";
+const SMILE_SHEET: &str = "assets/img/smile.png";
+
+pub fn tile(name: &str, variant: Direction) -> Animation {
+    from_sheet(TILE_SHEET, name, variant)
+}
+
+pub fn entity(name: &str, variant: Direction) -> Animation {
+    if name == "Player" {
+        return player();
+    }
+    from_sheet(ENTITY_SHEET, name, variant)
+}
+
+pub fn player() -> Animation {
+    from_sheet(PLAYER_SHEET, "Player", Direction::Center)
+}
+
+pub fn smiley_win() -> Animation {
+    from_sheet(SMILE_SHEET, "SmileyWin", Direction::Center)
+}
+
+fn from_sheet(src: &str, name: &str, variant: Direction) -> Animation {
+    Animation::from_atlas(src, name, variant)
+        .unwrap_or_else(|e| panic!("No sprite for {} in {}: {:?}", name, src, e))
+}
+
+/// Works out which variant of a tile was drawn with `frame`.
+/// None if the tile looks the same in every direction
+pub fn tile_variant_of(name: &str, frame: (u16, u16)) -> Option<Direction> {
+    variant_of(TILE_SHEET, name, frame)
+}
+
+/// Same as [`tile_variant_of`] for entities
+pub fn entity_variant_of(name: &str, frame: (u16, u16)) -> Option<Direction> {
+    variant_of(ENTITY_SHEET, name, frame)
+}
+
+fn variant_of(src: &str, name: &str, frame: (u16, u16)) -> Option<Direction> {
+    let atlas = atlas::get(src)?;
+    let base_frame = |variant| {
+        let frames = atlas.animations(name, variant)?.ok()?;
+        frames.get("base").map(|(_, frames)| frames[0])
+    };
+    if Direction::ALL
+        .iter()
+        .all(|&variant| base_frame(variant) == base_frame(Direction::Center))
+    {
+        return None;
+    }
+    Direction::ALL
+        .iter()
+        .copied()
+        .find(|&variant| base_frame(variant) == Some(frame))
+}
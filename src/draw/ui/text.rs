@@ -1,5 +1,8 @@
 use speedy2d::font::Font;
 
+/// The game's font, so it can be loaded by anything that draws text
+pub const FONT_BYTES: &[u8] = include_bytes!("../../../assets/font/negative-quinpix.ttf");
+
 pub fn get_font() -> Font {
-    Font::new(include_bytes!("../../../assets/font/negative-quinpix.ttf")).unwrap()
+    Font::new(FONT_BYTES).unwrap()
 }
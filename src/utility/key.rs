@@ -1,14 +1,14 @@
 use speedy2d::window::VirtualKeyCode;
 
-use crate::world::space::GamePos;
+use crate::world::space::TilePos;
 
-pub fn match_wasd_directions(key: &VirtualKeyCode) -> GamePos {
+pub fn match_wasd_directions(key: &VirtualKeyCode) -> TilePos {
     match key {
         VirtualKeyCode::W => (0, -1),
         VirtualKeyCode::A => (-1, 0),
         VirtualKeyCode::S => (0, 1),
         VirtualKeyCode::D => (1, 0),
-        _ => (0, 0)
+        _ => (0, 0),
     }
     .into()
-}
\ No newline at end of file
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use super::space::TilePos;
+
+/// Width and height of a single chunk. Measured in tiles
+pub const CHUNK_SIZE: i32 = 16;
+
+/// Coordinates of a chunk. Chunk (1, 0) holds tiles (16, 0) through (31, 15)
+type ChunkPos = (i32, i32);
+
+/// Sparse map from tile coordinates to values.
+/// Values are bucketed into fixed-size chunks so lookups never have to scan the whole map
+#[derive(Debug)]
+pub struct ChunkMap<T> {
+    chunks: HashMap<ChunkPos, Chunk<T>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Chunk<T> {
+    cells: Vec<Option<T>>,
+    occupied: usize,
+}
+
+impl<T> Chunk<T> {
+    fn new() -> Chunk<T> {
+        Chunk {
+            cells: (0..CHUNK_SIZE * CHUNK_SIZE).map(|_| None).collect(),
+            occupied: 0,
+        }
+    }
+}
+
+/// Splits a cell into the coordinates of its chunk and its index inside that chunk
+fn split(cell: TilePos) -> (ChunkPos, usize) {
+    let chunk = (cell.0.div_euclid(CHUNK_SIZE), cell.1.div_euclid(CHUNK_SIZE));
+    let local = (cell.0.rem_euclid(CHUNK_SIZE), cell.1.rem_euclid(CHUNK_SIZE));
+    (chunk, (local.1 * CHUNK_SIZE + local.0) as usize)
+}
+
+/// Inverse of [`split`]
+fn join(chunk: ChunkPos, index: usize) -> TilePos {
+    let index = index as i32;
+    TilePos(
+        chunk.0 * CHUNK_SIZE + index % CHUNK_SIZE,
+        chunk.1 * CHUNK_SIZE + index / CHUNK_SIZE,
+    )
+}
+
+impl<T> Default for ChunkMap<T> {
+    fn default() -> Self {
+        ChunkMap::new()
+    }
+}
+
+impl<T> ChunkMap<T> {
+    pub fn new() -> ChunkMap<T> {
+        ChunkMap {
+            chunks: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of occupied cells
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, cell: TilePos) -> Option<&T> {
+        let (chunk, index) = split(cell);
+        self.chunks.get(&chunk)?.cells[index].as_ref()
+    }
+
+    pub fn get_mut(&mut self, cell: TilePos) -> Option<&mut T> {
+        let (chunk, index) = split(cell);
+        self.chunks.get_mut(&chunk)?.cells[index].as_mut()
+    }
+
+    /// Puts `value` at `cell`, returning whatever was there before
+    pub fn insert(&mut self, cell: TilePos, value: T) -> Option<T> {
+        let (chunk, index) = split(cell);
+        let chunk = self.chunks.entry(chunk).or_insert_with(Chunk::new);
+        let old = chunk.cells[index].replace(value);
+        if old.is_none() {
+            chunk.occupied += 1;
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Gets the value at `cell`, inserting one made by `f` if the cell is empty
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, cell: TilePos, f: F) -> &mut T {
+        if self.get(cell).is_none() {
+            self.insert(cell, f());
+        }
+        self.get_mut(cell).unwrap()
+    }
+
+    pub fn remove(&mut self, cell: TilePos) -> Option<T> {
+        let (chunk_pos, index) = split(cell);
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+        let old = chunk.cells[index].take();
+        if old.is_some() {
+            chunk.occupied -= 1;
+            self.len -= 1;
+            // Don't keep empty chunks around, they'd slow down iteration
+            if chunk.occupied == 0 {
+                self.chunks.remove(&chunk_pos);
+            }
+        }
+        old
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    /// Keeps only the values the predicate returns true for
+    pub fn retain<P: FnMut(TilePos, &T) -> bool>(&mut self, mut predicate: P) {
+        let mut removed = 0;
+        for (&chunk_pos, chunk) in self.chunks.iter_mut() {
+            for (index, slot) in chunk.cells.iter_mut().enumerate() {
+                if let Some(value) = slot {
+                    if !predicate(join(chunk_pos, index), value) {
+                        *slot = None;
+                        chunk.occupied -= 1;
+                        removed += 1;
+                    }
+                }
+            }
+        }
+        self.chunks.retain(|_, chunk| chunk.occupied > 0);
+        self.len -= removed;
+    }
+
+    /// Iterates over every occupied cell in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> + '_ {
+        self.chunks.iter().flat_map(|(&chunk_pos, chunk)| {
+            chunk
+                .cells
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, slot)| slot.as_ref().map(|v| (join(chunk_pos, i), v)))
+        })
+    }
+
+    /// Iterates mutably over every occupied cell in no particular order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (TilePos, &mut T)> + '_ {
+        self.chunks.iter_mut().flat_map(|(&chunk_pos, chunk)| {
+            chunk
+                .cells
+                .iter_mut()
+                .enumerate()
+                .filter_map(move |(i, slot)| slot.as_mut().map(|v| (join(chunk_pos, i), v)))
+        })
+    }
+
+    /// Iterates mutably over the occupied cells inside the rectangle from `min` to `max` (inclusive).
+    /// Only the chunks overlapping the rectangle are visited
+    pub fn iter_mut_within(
+        &mut self,
+        min: TilePos,
+        max: TilePos,
+    ) -> impl Iterator<Item = (TilePos, &mut T)> + '_ {
+        let (min_chunk, _) = split(min);
+        let (max_chunk, _) = split(max);
+        self.chunks
+            .iter_mut()
+            .filter(move |(chunk_pos, _)| {
+                (min_chunk.0..=max_chunk.0).contains(&chunk_pos.0)
+                    && (min_chunk.1..=max_chunk.1).contains(&chunk_pos.1)
+            })
+            .flat_map(move |(&chunk_pos, chunk)| {
+                chunk
+                    .cells
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(move |(i, slot)| {
+                        let cell = join(chunk_pos, i);
+                        let inside =
+                            (min.0..=max.0).contains(&cell.0) && (min.1..=max.1).contains(&cell.1);
+                        match slot {
+                            Some(v) if inside => Some((cell, v)),
+                            _ => None,
+                        }
+                    })
+            })
+    }
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    entity::{item::ItemKind, player::PlayerHat, Entity},
+    minigame::Minigame,
+    space::{Direction, TilePos},
+    story::{default_flag_value, default_item_count, Condition},
+    tile::core::BaseGround,
+    World,
+};
+
+/// Something that happens to the world. Commands are plain data,
+/// so they can be saved mid-turn and written by hand in region files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command")]
+pub enum Command {
+    MovePlayer {
+        by: TilePos,
+    },
+    /// Puts the player somewhere without it counting as a step
+    TeleportPlayer {
+        to: TilePos,
+    },
+    /// Entity indices change whenever entities are added or removed, so only use this within a turn
+    MoveEntity {
+        index: usize,
+        by: TilePos,
+    },
+    /// Pushes the row of pushable entities starting at `pos` one tile along `by`, the way the player's hat does.
+    /// If something at the end of the row stops it, the player is moved back by `by` instead
+    Push {
+        pos: TilePos,
+        by: TilePos,
+    },
+    /// Removes every entity at `pos`
+    RemoveEntity {
+        pos: TilePos,
+    },
+    /// Flips the state of the tile at `pos`, e.g. opens or closes a door
+    ToggleTile {
+        pos: TilePos,
+    },
+    StartMinigame {
+        minigame: Box<dyn Minigame>,
+    },
+    /// Saves the current region, loads `region` and puts the player at `pos`
+    Warp {
+        region: String,
+        pos: TilePos,
+    },
+    SetFlag {
+        flag: String,
+        #[serde(default = "default_flag_value")]
+        value: bool,
+    },
+    SetCounter {
+        counter: String,
+        value: i64,
+    },
+    AddToCounter {
+        counter: String,
+        by: i64,
+    },
+    SetQuestStage {
+        quest: String,
+        stage: u32,
+    },
+    /// Shows a message on screen until the player's next turn
+    ShowText {
+        text: String,
+    },
+    /// Runs `then` if `condition` holds, `otherwise` if not
+    If {
+        condition: Condition,
+        #[serde(default)]
+        then: Vec<Command>,
+        #[serde(default)]
+        otherwise: Vec<Command>,
+    },
+    /// Saves the current region and makes `pos` in it where the player respawns
+    SetCheckpoint {
+        pos: TilePos,
+    },
+    /// The player ran out of health
+    DefeatPlayer,
+    /// Reloads the region of the last checkpoint from its file and puts the player back there with full health
+    Respawn,
+    /// Defeats every creature at `pos`
+    Strike {
+        pos: TilePos,
+    },
+    /// Replaces the tile at `pos` with plain ground
+    DissolveTile {
+        pos: TilePos,
+    },
+    /// Gives the player a hat and puts it on
+    UnlockHat {
+        hat: PlayerHat,
+    },
+    /// Opens the conversation in `assets/dialogue/{dialogue}.json`
+    StartDialogue {
+        dialogue: String,
+    },
+    /// Puts items in the player's inventory
+    GiveItem {
+        item: ItemKind,
+        #[serde(default = "default_item_count")]
+        count: u32,
+    },
+    /// Takes items out of the player's inventory. Does nothing if they don't have that many
+    TakeItem {
+        item: ItemKind,
+        #[serde(default = "default_item_count")]
+        count: u32,
+    },
+}
+
+impl Command {
+    pub fn execute(&self, w: &mut World) {
+        match self {
+            Command::MovePlayer { by } => w.player.moove(*by),
+            Command::TeleportPlayer { to } => w.player.teleport(*to),
+            Command::MoveEntity { index, by } => w.mgr.move_entity(*index, *by),
+            Command::Push { pos, by } => {
+                let hat = w.player.hat().ability();
+                if !hat.push(&mut w.mgr, *pos, Direction::from(*by).direction_vector()) {
+                    w.player.moove(-*by);
+                }
+            }
+            Command::RemoveEntity { pos } => w.mgr.remove_entity_at(*pos),
+            Command::ToggleTile { pos } => {
+                if let Some(tile) = w.mgr.get_tile_at_pos(*pos) {
+                    tile.change_self();
+                }
+            }
+            Command::StartMinigame { minigame } => w.minigame = Some(minigame.create()),
+            Command::Warp { region, pos } => w.warp(region, *pos),
+            Command::SetFlag { flag, value } => w.story.set_flag(flag.clone(), *value),
+            Command::SetCounter { counter, value } => w.story.set_counter(counter.clone(), *value),
+            Command::AddToCounter { counter, by } => w.story.add_to_counter(counter, *by),
+            Command::SetQuestStage { quest, stage } => {
+                w.story.set_quest_stage(quest.clone(), *stage)
+            }
+            Command::ShowText { text } => w.show_text(text.clone()),
+            Command::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let commands = if w.check(condition) { then } else { otherwise };
+                for command in commands {
+                    command.execute(w);
+                }
+            }
+            Command::SetCheckpoint { pos } => w.set_checkpoint(*pos),
+            Command::DefeatPlayer => {
+                w.show_text(String::from("You were defeated"));
+                Command::Respawn.execute(w);
+            }
+            Command::Respawn => w.respawn(),
+            Command::Strike { pos } => w
+                .mgr
+                .remove_entity_where(|e| e.get_pos() == *pos && e.is_creature()),
+            Command::DissolveTile { pos } => {
+                if w.mgr.get_tile(*pos).is_some() {
+                    w.mgr.push_tile_override(Box::new(BaseGround::new(*pos)));
+                }
+            }
+            Command::UnlockHat { hat } => {
+                if w.player.unlock_hat(*hat) {
+                    w.show_text(format!("Found the {:?} hat! Press H to switch hats", hat));
+                }
+                w.player.set_hat(*hat);
+            }
+            Command::StartDialogue { dialogue } => w.start_dialogue(dialogue),
+            Command::GiveItem { item, count } => {
+                let added = w.player.inventory_mut().add(*item, *count);
+                match added {
+                    0 => {}
+                    1 => w.show_text(format!("Found {}", item.with_article())),
+                    _ => w.show_text(format!("Found {} {}s", added, item.name())),
+                }
+            }
+            Command::TakeItem { item, count } => {
+                w.player.inventory_mut().remove(*item, *count);
+            }
+        }
+    }
+}
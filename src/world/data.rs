@@ -1,20 +1,21 @@
-use std::{
-    cmp::Ordering,
-    env,
-    fs::{self, File},
-    io::{self, BufReader},
-    path::{Path, PathBuf},
-    thread,
-    time::Duration,
-};
-
-use serde::{Deserialize, Serialize};
-use speedy2d::Graphics2D;
+use std::{cmp::Ordering, collections::HashSet};
 
-use crate::draw::{screen::camera::Camera, ui::img::ImgManager};
+use crate::draw::{render::Renderer, screen::camera::Camera};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::{
-    entity::Entity, operation::PostOperation, space::GamePos, tile::Tile, time::Clock, VIEW_DIST,
+    chunk::ChunkMap,
+    entity::{enemy::Attack, item::Inventory, Entity},
+    manifest::{self, RegionError, WarpLink},
+    operation::PostOperation,
+    save::{self, SaveError},
+    signal::Signals,
+    space::TilePos,
+    story::StoryState,
+    tile::Tile,
+    time::Clock,
+    undo::{EntityChanges, Journal, TileChanges},
+    VIEW_DIST,
 };
 
 enum TileOrEntity<'a> {
@@ -22,7 +23,7 @@ enum TileOrEntity<'a> {
     Entity(&'a mut Box<dyn Entity>),
 }
 impl<'a> TileOrEntity<'a> {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         match self {
             Self::Tile(tile) => tile.get_pos(),
             Self::Entity(entity) => entity.get_pos(),
@@ -44,64 +45,135 @@ impl<'a> TileOrEntity<'a> {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Holds every tile and entity of the loaded region.
+/// Position queries go through chunked spatial indices so they don't depend on the size of the region
 pub struct DataManager {
+    entities: Vec<Box<dyn Entity>>,
+    /// Indices into `entities`, keyed by the cell each entity stands on
+    entity_cells: ChunkMap<Vec<usize>>,
+    tiles: ChunkMap<Box<dyn Tile>>,
+    name: String,
+    last_turn_entity_index: usize,
+    /// Tiles and entities changed since the turn started, as they were before, so the turn can be undone.
+    /// None when no turn is being recorded
+    journal: Option<Journal>,
+}
+
+/// On-disk layout of a region. Kept as flat lists so region files stay readable
+#[derive(Serialize)]
+struct RegionRef<'a> {
+    entities: &'a [Box<dyn Entity>],
+    tiles: Vec<&'a dyn Tile>,
+    name: &'a str,
+    last_turn_entity_index: usize,
+}
+
+#[derive(Deserialize)]
+struct RegionData {
     entities: Vec<Box<dyn Entity>>,
     tiles: Vec<Box<dyn Tile>>,
     name: String,
     last_turn_entity_index: usize,
 }
 
+impl Serialize for DataManager {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Sorted so saving the same region twice gives the same file
+        let mut tiles = self.tiles.iter().collect::<Vec<_>>();
+        tiles.sort_by_key(|(pos, _)| (pos.1, pos.0));
+
+        RegionRef {
+            entities: &self.entities,
+            tiles: tiles.into_iter().map(|(_, t)| t.as_ref()).collect(),
+            name: &self.name,
+            last_turn_entity_index: self.last_turn_entity_index,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DataManager {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = RegionData::deserialize(deserializer)?;
+
+        let mut result = DataManager::new(data.name);
+        result.last_turn_entity_index = data.last_turn_entity_index;
+        for mut tile in data.tiles {
+            tile.reset_anim();
+            result.tiles.insert(tile.get_pos(), tile);
+        }
+        result.entities = data.entities;
+        for entity in result.entities.iter_mut() {
+            entity.reset_anim();
+        }
+        result.reindex_entities();
+
+        Ok(result)
+    }
+}
+
 impl DataManager {
     pub fn new(name: String) -> DataManager {
         DataManager {
             entities: Vec::new(),
-            tiles: Vec::new(),
+            entity_cells: ChunkMap::new(),
+            tiles: ChunkMap::new(),
             name,
             last_turn_entity_index: 0,
+            journal: None,
         }
     }
 
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
     pub fn draw_before_player(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         clock: &Clock,
         camera: &Camera,
-        player_pos: GamePos,
+        player_pos: TilePos,
     ) {
-        self.draw_where(graphics, manager, clock, camera, |te| {
-            te.get_pos().y <= player_pos.y
-                && te.get_pos().largest_component_difference(player_pos) < VIEW_DIST
+        self.draw_where(renderer, clock, camera, player_pos, |te| {
+            te.get_pos().1 <= player_pos.1
         })
     }
     pub fn draw_after_player(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         clock: &Clock,
         camera: &Camera,
-        player_pos: GamePos,
+        player_pos: TilePos,
     ) {
-        self.draw_where(graphics, manager, clock, camera, |te| {
-            te.get_pos().y > player_pos.y
-                && te.get_pos().largest_component_difference(player_pos) < VIEW_DIST
+        self.draw_where(renderer, clock, camera, player_pos, |te| {
+            te.get_pos().1 > player_pos.1
         })
     }
 
-    /// Draws tiles and entities line-by-line by some predicate provided
+    /// Draws tiles and entities within [`VIEW_DIST`] of `center` line-by-line by some predicate provided
     fn draw_where<P: Fn(&mut TileOrEntity) -> bool>(
         &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
+        renderer: &mut dyn Renderer,
         clock: &Clock,
         camera: &Camera,
+        center: TilePos,
         predicate: P,
     ) {
+        let (min, max) = view_bounds(center);
+        let in_view =
+            |pos: TilePos| (min.0..=max.0).contains(&pos.0) && (min.1..=max.1).contains(&pos.1);
+
         let mut all_things = self
             .tiles
-            .iter_mut()
-            .filter_map(|t| {
+            .iter_mut_within(min, max)
+            .filter_map(|(_, t)| {
                 let mut t = TileOrEntity::from_tile(t);
                 if predicate(&mut t) {
                     Some(t)
@@ -115,7 +187,7 @@ impl DataManager {
                 .iter_mut()
                 .filter_map(|e| {
                     let mut e = TileOrEntity::from_entity(e);
-                    if predicate(&mut e) {
+                    if in_view(e.get_pos()) && predicate(&mut e) {
                         Some(e)
                     } else {
                         None
@@ -126,18 +198,56 @@ impl DataManager {
 
         all_things.sort_by(|t1, t2| t1.cmp(t2));
 
-        all_things.sort_by(|t1, t2| t1.get_pos().y.partial_cmp(&t2.get_pos().y).unwrap());
+        all_things.sort_by_key(|t| t.get_pos().1);
 
         for te in all_things {
             match te {
-                TileOrEntity::Tile(tile) => tile.draw(graphics, manager, clock, camera),
-                TileOrEntity::Entity(entity) => entity.draw(graphics, manager, clock, camera),
+                TileOrEntity::Tile(tile) => tile.draw(renderer, clock, camera),
+                TileOrEntity::Entity(entity) => entity.draw(renderer, clock, camera),
+            }
+        }
+    }
+
+    /// Lets tiles that depend on the story catch up with it
+    pub fn update_story(&mut self, story: &StoryState, inventory: &Inventory) {
+        for (_, tile) in self.tiles.iter_mut() {
+            tile.update_story(story, inventory);
+        }
+    }
+
+    /// Collects the signals sources put out this turn and passes them on to every sink
+    pub fn update_signals(&mut self, clock: &Clock, player_pos: TilePos) {
+        let occupied: HashSet<TilePos> = self
+            .entities
+            .iter()
+            .map(|entity| entity.get_pos())
+            .chain(std::iter::once(player_pos))
+            .collect();
+
+        let mut signals = Signals::default();
+        for (pos, tile) in self.tiles.iter_mut() {
+            let on = tile.signal(clock, occupied.contains(&pos));
+            if let Some(channel) = tile.channel_mut().filter(|channel| !channel.is_empty()) {
+                signals.emit(channel.clone(), on);
+            }
+        }
+        for entity in &mut self.entities {
+            let on = entity.signal(clock, occupied.contains(&entity.get_pos()));
+            if let Some(channel) = entity.channel_mut().filter(|channel| !channel.is_empty()) {
+                signals.emit(channel.clone(), on);
             }
         }
+
+        for (_, tile) in self.tiles.iter_mut() {
+            tile.receive(&signals);
+        }
+        for entity in &mut self.entities {
+            entity.receive(&signals);
+        }
     }
 
     pub fn update_anims(&mut self, clock: &Clock) {
-        for tile in &mut self.tiles {
+        for (_, tile) in self.tiles.iter_mut() {
             tile.update_state(clock);
             tile.update_anim();
         }
@@ -150,166 +260,464 @@ impl DataManager {
 
     // Tile stuff
 
-    pub fn push_tile_override(&mut self, tile: Box<dyn Tile>) {
-        if let Some((to_remove, _)) = self.get_tile_at_pos(tile.get_pos()) {
-            self.tiles.remove(to_remove);
-        }
-        self.push_tile(tile);
+    /// Number of tiles in the region
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
     }
 
-    /// !Warning! Possibility of overlapping tiles. Use push_tile_override unless you know what you're doing
-    pub fn push_tile(&mut self, mut tile: Box<dyn Tile>) {
+    pub fn push_tile_override(&mut self, mut tile: Box<dyn Tile>) {
+        self.note_tile(tile.get_pos());
         tile.get_anim_mut().select("base").unwrap();
-        self.tiles.push(tile);
+        self.tiles.insert(tile.get_pos(), tile);
+    }
+
+    /// A cell only ever holds one tile, so this is the same as push_tile_override
+    pub fn push_tile(&mut self, tile: Box<dyn Tile>) {
+        self.push_tile_override(tile);
+    }
+
+    pub fn get_tile(&self, pos: TilePos) -> Option<&dyn Tile> {
+        self.tiles.get(pos).map(|tile| tile.as_ref())
     }
 
-    pub fn get_tile_at_pos(&mut self, pos: GamePos) -> Option<(usize, &mut Box<dyn Tile>)> {
-        self.tiles
-            .iter_mut()
-            .enumerate()
-            .find(|(_, t)| t.get_pos() == pos)
+    pub fn get_tile_at_pos(&mut self, pos: TilePos) -> Option<&mut Box<dyn Tile>> {
+        self.note_tile(pos);
+        self.tiles.get_mut(pos)
     }
 
     pub fn remove_tile_where<P: Fn(&Box<dyn Tile>) -> bool>(&mut self, predicate: P) {
-        let mut remove_indices = vec![];
-        for (i, tile) in self.tiles.iter().enumerate() {
-            if predicate(tile) {
-                remove_indices.push(i);
+        if self.journal.is_some() {
+            let removed: Vec<TilePos> = self
+                .tiles
+                .iter()
+                .filter(|(_, tile)| predicate(tile))
+                .map(|(pos, _)| pos)
+                .collect();
+            for pos in removed {
+                self.note_tile(pos);
             }
         }
-        for i in remove_indices {
-            self.tiles.remove(i);
-        }
+        self.tiles.retain(|_, tile| !predicate(tile));
+    }
+
+    pub fn remove_tile_at(&mut self, pos: TilePos) {
+        self.note_tile(pos);
+        self.tiles.remove(pos);
     }
 
-    pub fn remove_tile_at(&mut self, pos: GamePos) {
-        self.remove_tile_where(|t| t.get_pos() == pos);
+    /// Cells reachable from `start` through cells with the same type of tile, or no tile if it has none.
+    /// None if there are more than `limit` of them, like when filling the empty space around a region
+    pub fn flood(&self, start: TilePos, limit: usize) -> Option<Vec<TilePos>> {
+        let kind = |pos: TilePos| self.get_tile(pos).map(|tile| tile.typetag_name());
+        let start_kind = kind(start);
+        let mut seen = HashSet::new();
+        seen.insert(start);
+        let mut frontier = vec![start];
+        let mut cells = Vec::new();
+        while let Some(pos) = frontier.pop() {
+            cells.push(pos);
+            if cells.len() > limit {
+                return None;
+            }
+            for &step in &[TilePos(1, 0), TilePos(-1, 0), TilePos(0, 1), TilePos(0, -1)] {
+                let next = pos + step;
+                if kind(next) == start_kind && seen.insert(next) {
+                    frontier.push(next);
+                }
+            }
+        }
+        Some(cells)
     }
 
     // Entity stuff
 
     pub fn push_entity_override(&mut self, entity: Box<dyn Entity>) {
-        if let Some((to_remove, _)) = self.get_entity_at_pos(entity.get_pos()) {
-            self.entities.remove(to_remove);
-        }
+        self.remove_entity_at(entity.get_pos());
         self.push_entity(entity);
     }
 
     /// !Warning! Possibility of overlapping entities. Use push_entity_override unless you know what you're doing
     pub fn push_entity(&mut self, mut entity: Box<dyn Entity>) {
         entity.get_anim_mut().select("base").unwrap();
+        let pos = entity.get_pos();
         self.entities.push(entity);
+        self.entity_cells
+            .get_or_insert_with(pos, Vec::new)
+            .push(self.entities.len() - 1);
+        if let Some(journal) = &mut self.journal {
+            journal.entities.origins.push(None);
+        }
     }
 
     /// Gets entity at given position
-    pub fn get_entity_at_pos(&mut self, pos: GamePos) -> Option<(usize, &mut Box<dyn Entity>)> {
-        self.entities
-            .iter_mut()
-            .enumerate()
-            .find(|(_, e)| e.get_pos() == pos)
+    pub fn get_entity_at_pos(&self, pos: TilePos) -> Option<(usize, &dyn Entity)> {
+        let index = *self.entity_cells.get(pos)?.first()?;
+        Some((index, self.entities[index].as_ref()))
+    }
+
+    pub fn get_entities_at_pos(&self, pos: TilePos) -> Vec<(usize, &dyn Entity)> {
+        match self.entity_cells.get(pos) {
+            Some(indices) => indices
+                .iter()
+                .map(|&i| (i, self.entities[i].as_ref()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_entity(&self, index: usize) -> Option<&dyn Entity> {
+        self.entities.get(index).map(|e| e.as_ref())
+    }
+
+    /// Runs `f` on the entity at `index`, keeping the position index in sync if the entity moved.
+    /// This is the only way to get mutable access to an entity
+    pub fn update_entity<R, F: FnOnce(&mut Box<dyn Entity>) -> R>(
+        &mut self,
+        index: usize,
+        f: F,
+    ) -> Option<R> {
+        self.note_entity(index);
+        self.change_entity(index, f)
     }
 
-    pub fn get_entities_at_pos(&mut self, pos: GamePos) -> Vec<(usize, &mut Box<dyn Entity>)> {
-        self.entities
-            .iter_mut()
-            .enumerate()
-            .filter(|(_, e)| e.get_pos() == pos)
-            .collect::<Vec<_>>()
+    /// [`update_entity`](Self::update_entity) without keeping the entity for undo first.
+    /// Only for changes entities with turn state make, as those are kept when the turn starts
+    fn change_entity<R, F: FnOnce(&mut Box<dyn Entity>) -> R>(
+        &mut self,
+        index: usize,
+        f: F,
+    ) -> Option<R> {
+        let entity = self.entities.get_mut(index)?;
+        let old_pos = entity.get_pos();
+        let result = f(entity);
+        let new_pos = entity.get_pos();
+        if old_pos != new_pos {
+            self.unindex_entity(old_pos, index);
+            self.entity_cells
+                .get_or_insert_with(new_pos, Vec::new)
+                .push(index);
+        }
+        Some(result)
     }
 
-    pub fn get_entity(&mut self, index: usize) -> Option<&mut Box<dyn Entity>> {
-        self.entities.get_mut(index)
+    pub fn move_entity(&mut self, index: usize, change_pos: TilePos) {
+        self.update_entity(index, |e| e.moove(change_pos));
     }
 
     pub fn remove_entity_where<P: Fn(&Box<dyn Entity>) -> bool>(&mut self, predicate: P) {
-        let mut remove_indices = Vec::new();
+        let before = self.entities.len();
+        if self.journal.is_some() {
+            let removed: Vec<bool> = self.entities.iter().map(&predicate).collect();
+            for (index, _) in removed.iter().enumerate().filter(|(_, &removed)| removed) {
+                self.note_entity(index);
+            }
+            if let Some(journal) = &mut self.journal {
+                let mut removed = removed.into_iter();
+                journal
+                    .entities
+                    .origins
+                    .retain(|_| !removed.next().unwrap_or(false));
+            }
+        }
+        self.entities.retain(|e| !predicate(e));
+        if self.entities.len() != before {
+            // Removing shifts every index after it, so the cell index has to be rebuilt
+            self.reindex_entities();
+        }
+    }
+
+    pub fn remove_entity_at(&mut self, pos: TilePos) {
+        if self.entity_cells.get(pos).is_some() {
+            self.remove_entity_where(|e| e.get_pos() == pos);
+        }
+    }
+
+    fn unindex_entity(&mut self, pos: TilePos, index: usize) {
+        if let Some(indices) = self.entity_cells.get_mut(pos) {
+            indices.retain(|&i| i != index);
+            if indices.is_empty() {
+                self.entity_cells.remove(pos);
+            }
+        }
+    }
+
+    fn reindex_entities(&mut self) {
+        self.entity_cells.clear();
         for (i, entity) in self.entities.iter().enumerate() {
-            if predicate(entity) {
-                remove_indices.push(i);
+            self.entity_cells
+                .get_or_insert_with(entity.get_pos(), Vec::new)
+                .push(i);
+        }
+    }
+
+    /// Moves the row of pushable entities starting at `pos` one tile along `step`, as long as the cell
+    /// past the end of the row is free. Returns whether they moved. Anything else in the row stops it
+    pub fn push_entities(&mut self, pos: TilePos, step: TilePos) -> bool {
+        if step == TilePos::origin() {
+            return false;
+        }
+        let mut row = Vec::new();
+        let mut cell = pos;
+        while let Some((index, entity)) = self.get_entity_at_pos(cell) {
+            if !entity.is_pushable() {
+                return false;
             }
+            row.push(index);
+            cell += step;
+        }
+        if self.blocks_entity(cell) {
+            return false;
         }
-        // remove_indices is sorted because we added the indices in increasing order
-        let mut offset = 0;
-        for i in remove_indices {
-            self.entities.remove(i - offset);
-            offset += 1;
+        // Front first, so nothing is ever moved onto something that hasn't moved yet
+        for index in row.into_iter().rev() {
+            self.move_entity(index, step);
         }
+        true
     }
 
-    pub fn remove_entity_at(&mut self, pos: GamePos) {
-        self.remove_entity_where(|t| t.get_pos() == pos);
+    fn blocks_entity(&self, pos: TilePos) -> bool {
+        match self.tiles.get(pos) {
+            Some(tile) => tile.block_movement(),
+            None => true,
+        }
     }
 
-    pub fn do_entity_turn(&mut self, player_pos: GamePos) -> Vec<PostOperation> {
+    pub fn do_entity_turn(&mut self, player_pos: TilePos) -> Vec<PostOperation> {
         let mut post_ops = Vec::new();
         for index in 0..self.entities.len() {
-            let entity = self.entities.get_mut(index).unwrap();
-
             let mut move_poss = Vec::new();
 
-            // Entity moves
-            entity.request_moves(&mut move_poss, player_pos);
+            // Entities that change on their turn are kept when the turn starts, the rest are kept if they move
+            let op = self
+                .change_entity(index, |entity| {
+                    // Entity moves
+                    entity.request_moves(&mut move_poss, player_pos);
 
-            // Entity does turn
-            post_ops.push(entity.do_turn());
+                    // Entity does turn
+                    entity.do_turn(player_pos)
+                })
+                .unwrap();
+            post_ops.push(op);
 
-            let entity_pos = entity.get_pos();
+            let entity_pos = self.entities[index].get_pos();
 
             for move_pos in move_poss {
                 let new_pos = entity_pos + move_pos;
 
                 // See if it moved onto an entity or blocking tile
-                if new_pos != player_pos && self.get_entity_at_pos(new_pos).is_none() && {
-                    if let Some((_, tile)) = self.get_tile_at_pos(new_pos) {
-                        !tile.block_movement()
-                    } else {
-                        false
-                    }
-                } {
-                    self.entities.get_mut(index).unwrap().moove(move_pos);
+                if new_pos != player_pos
+                    && self.get_entity_at_pos(new_pos).is_none()
+                    && !self.blocks_entity(new_pos)
+                {
+                    self.move_entity(index, move_pos);
                     break;
                 }
             }
         }
-        return post_ops;
+        post_ops
     }
 
-    // File stuff
+    /// Damage from every telegraphed attack that covers `pos`
+    pub fn attack_damage_at(&self, pos: TilePos) -> u32 {
+        self.attacks()
+            .filter(|attack| attack.cells.contains(&pos))
+            .map(|attack| attack.damage)
+            .sum()
+    }
+
+    /// Every attack that's been telegraphed and is yet to land
+    pub fn attacks(&self) -> impl Iterator<Item = &Attack> {
+        self.entities.iter().filter_map(|e| e.attack())
+    }
+
+    // Warp stuff
+
+    /// Every warp in the region that leads somewhere
+    pub fn warp_links(&self) -> Vec<WarpLink> {
+        let tile_targets = self
+            .tiles
+            .iter()
+            .map(|(_, t)| (t.get_pos(), t.warp_target()));
+        let entity_targets = self.entities.iter().map(|e| (e.get_pos(), e.warp_target()));
+        tile_targets
+            .chain(entity_targets)
+            .filter_map(|(from, target)| match target {
+                Some((region, to)) if !region.is_empty() => Some(WarpLink {
+                    from,
+                    region: region.clone(),
+                    to,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Points every warp leading into region `old` at region `new` instead.
+    /// Returns whether anything changed
+    pub fn retarget_warps(&mut self, old: &str, new: &str) -> bool {
+        let mut changed = false;
+        let tiles: Vec<(TilePos, TilePos)> = self
+            .tiles
+            .iter()
+            .filter_map(|(pos, tile)| match tile.warp_target() {
+                Some((region, to)) if region == old => Some((pos, to)),
+                _ => None,
+            })
+            .collect();
+        for (pos, to) in tiles {
+            if let Some(tile) = self.get_tile_at_pos(pos) {
+                tile.set_warp_target(new.to_string(), to);
+                changed = true;
+            }
+        }
+        for index in 0..self.entities.len() {
+            let to = match self.entities[index].warp_target() {
+                Some((region, to)) if region == old => to,
+                _ => continue,
+            };
+            self.update_entity(index, |entity| entity.set_warp_target(new.to_string(), to));
+            changed = true;
+        }
+        changed
+    }
 
-    pub fn load_region(&mut self, name: &String) -> io::Result<()> {
-        self.save_region();
+    // Undo stuff
 
-        let path = get_file_path(name);
-        let file = File::open(path)?;
-        let rdr = BufReader::new(file);
+    /// Starts keeping the tiles and entities changed from here on. Ones that change by themselves during
+    /// a turn are kept straight away, the rest only when they're changed
+    pub fn start_journal(&mut self) {
+        self.journal = Some(Journal {
+            tiles: TileChanges::new(),
+            entities: EntityChanges::new(self.entities.len()),
+        });
+        let live: Vec<TilePos> = self
+            .tiles
+            .iter()
+            .filter(|(_, tile)| tile.has_turn_state())
+            .map(|(pos, _)| pos)
+            .collect();
+        for pos in live {
+            self.note_tile(pos);
+        }
+        for index in 0..self.entities.len() {
+            if self.entities[index].has_turn_state() {
+                self.note_entity(index);
+            }
+        }
+    }
 
-        *self = serde_json::from_reader(rdr).unwrap();
+    /// What changed since [`start_journal`](Self::start_journal), as it was before. Stops keeping it.
+    /// None if nothing was being kept
+    pub fn take_journal(&mut self) -> Option<Journal> {
+        self.journal.take()
+    }
 
+    /// Keeps the tile at `pos` as it is now, unless it's already been kept this turn
+    fn note_tile(&mut self, pos: TilePos) {
+        let journal = match &mut self.journal {
+            Some(journal) if !journal.tiles.contains_key(&pos) => journal,
+            _ => return,
+        };
+        let saved = match self.tiles.get(pos).map(serde_json::to_value).transpose() {
+            Ok(saved) => saved,
+            Err(e) => {
+                println!("Couldn't remember the tile at {:?}: {:?}", pos, e);
+                return;
+            }
+        };
+        journal.tiles.insert(pos, saved);
+    }
+
+    /// Keeps the entity at `index` as it is now, unless it's already been kept this turn or was added during it
+    fn note_entity(&mut self, index: usize) {
+        let changes = match &mut self.journal {
+            Some(journal) => &mut journal.entities,
+            None => return,
+        };
+        let origin = match changes.origins.get(index) {
+            Some(&Some(origin)) if !changes.saved.contains_key(&origin) => origin,
+            _ => return,
+        };
+        match serde_json::to_value(&self.entities[index]) {
+            Ok(saved) => {
+                changes.saved.insert(origin, saved);
+            }
+            Err(e) => println!("Couldn't remember entity {}: {:?}", index, e),
+        }
+    }
+
+    /// Puts back the tiles kept by [`take_journal`](Self::take_journal)
+    pub fn restore_tiles(&mut self, tiles: TileChanges) -> Result<(), serde_json::Error> {
+        for (pos, saved) in tiles {
+            match saved {
+                Some(saved) => {
+                    let mut tile: Box<dyn Tile> = serde_json::from_value(saved)?;
+                    tile.reset_anim();
+                    self.tiles.insert(pos, tile);
+                }
+                None => {
+                    self.tiles.remove(pos);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back the entities kept by [`take_journal`](Self::take_journal), in the order they were in.
+    /// Entities added since are removed
+    pub fn restore_entities(&mut self, changes: EntityChanges) -> Result<(), serde_json::Error> {
+        let EntityChanges {
+            count,
+            saved,
+            origins,
+        } = changes;
+        let mut restored: Vec<Option<Box<dyn Entity>>> = (0..count).map(|_| None).collect();
+        for (entity, origin) in self.entities.drain(..).zip(origins) {
+            if let Some(origin) = origin.filter(|origin| !saved.contains_key(origin)) {
+                restored[origin] = Some(entity);
+            }
+        }
+        for (origin, value) in saved {
+            let mut entity: Box<dyn Entity> = serde_json::from_value(value)?;
+            entity.reset_anim();
+            restored[origin] = Some(entity);
+        }
+        self.entities = restored.into_iter().flatten().collect();
+        self.reindex_entities();
         Ok(())
     }
 
-    pub fn new_region(&mut self, name: String) {
-        self.save_region();
+    // File stuff
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
 
-        *self = DataManager::new(name);
+    /// Reads the region called `name` from its file
+    pub fn from_file(name: &str) -> Result<DataManager, SaveError> {
+        save::read(&manifest::region_path(name))
     }
 
-    pub fn save_region(&self) {
-        let path = get_file_path(&self.name);
-        let file = fs::File::create(path).unwrap();
-        let writer = io::LineWriter::new(file);
+    /// Replaces the loaded region with the one called `name`. Names come from warps and checkpoints in
+    /// save files, so they're checked before being turned into a path.
+    /// !Warning! Unsaved changes to the current region are lost. Save it first if they matter
+    pub fn load_region(&mut self, name: &str) -> Result<(), RegionError> {
+        manifest::check_name(name)?;
+        *self = DataManager::from_file(name)?;
 
-        serde_json::to_writer(writer, &self).unwrap();
+        Ok(())
     }
-}
 
-fn get_file_path(file_name: &String) -> PathBuf {
-    assert!(*file_name != "save");
-    let dir = env::current_dir().unwrap();
-    let path = Path::new(&dir).join("saves/");
-    if !path.exists() {
-        fs::create_dir(&path).unwrap();
+    pub fn save_region(&self) -> Result<(), SaveError> {
+        save::write(&manifest::region_path(&self.name), self)
     }
-    let file_name = format!("{}.json", file_name);
-    path.join(file_name)
+}
+
+/// Corners of the rectangle of tiles within [`VIEW_DIST`] of `center`
+fn view_bounds(center: TilePos) -> (TilePos, TilePos) {
+    let dist = VIEW_DIST as i32;
+    (
+        TilePos(center.0 - dist, center.1 - dist),
+        TilePos(center.0 + dist, center.1 + dist),
+    )
 }
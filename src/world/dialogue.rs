@@ -0,0 +1,170 @@
+//! Conversations with the people of the world. Every dialogue tree is a file `assets/dialogue/name.json`.
+//! While a conversation is open the overworld waits, the same as during a minigame
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{command::Command, manifest, story::Condition, World};
+
+/// Folder dialogue trees are read from
+const DIALOGUE_DIR: &str = "assets/dialogue";
+
+#[derive(Debug)]
+pub enum DialogueError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// Not a name a dialogue file can have, e.g. one that leads out of the dialogue folder
+    InvalidName(String),
+    /// Something leads to a node the dialogue doesn't have
+    MissingNode(String),
+}
+
+impl From<io::Error> for DialogueError {
+    fn from(e: io::Error) -> Self {
+        DialogueError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DialogueError {
+    fn from(e: serde_json::Error) -> Self {
+        DialogueError::Parse(e)
+    }
+}
+
+/// A conversation as written, made of named nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dialogue {
+    /// Node the conversation opens on
+    start: String,
+    nodes: BTreeMap<String, Node>,
+}
+
+/// One thing said, and the choices the player has in reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Run when the node is reached
+    #[serde(default)]
+    pub commands: Vec<Command>,
+    #[serde(default)]
+    choices: Vec<Choice>,
+    /// Where to go when the node has no choices. Ends the conversation if there isn't anywhere
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Choice {
+    pub text: String,
+    /// Only offered when this holds
+    #[serde(default)]
+    condition: Option<Condition>,
+    /// Run when the choice is picked
+    #[serde(default)]
+    commands: Vec<Command>,
+    /// Node the choice leads to. Ends the conversation if there isn't one
+    #[serde(default)]
+    goto: Option<String>,
+}
+
+impl Dialogue {
+    /// Reads the dialogue `name` from the dialogue folder
+    pub fn from_file(name: &str) -> Result<Dialogue, DialogueError> {
+        if manifest::check_name(name).is_err() {
+            return Err(DialogueError::InvalidName(name.to_string()));
+        }
+        let path = PathBuf::from(DIALOGUE_DIR).join(format!("{}.json", name));
+        Dialogue::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(json: &str) -> Result<Dialogue, DialogueError> {
+        let dialogue: Dialogue = serde_json::from_str(json)?;
+
+        // Catch typos in node names now rather than halfway through a conversation
+        let targets = dialogue.nodes.values().flat_map(|node| {
+            node.next.iter().chain(
+                node.choices
+                    .iter()
+                    .filter_map(|choice| choice.goto.as_ref()),
+            )
+        });
+        for target in std::iter::once(&dialogue.start).chain(targets) {
+            if !dialogue.nodes.contains_key(target) {
+                return Err(DialogueError::MissingNode(target.clone()));
+            }
+        }
+        Ok(dialogue)
+    }
+}
+
+/// A dialogue the player is partway through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    dialogue: Dialogue,
+    node: String,
+    /// Which of the choices on offer is highlighted
+    #[serde(default)]
+    selected: usize,
+}
+
+impl Conversation {
+    pub fn new(dialogue: Dialogue) -> Conversation {
+        Conversation {
+            node: dialogue.start.clone(),
+            dialogue,
+            selected: 0,
+        }
+    }
+
+    pub fn node(&self) -> &Node {
+        &self.dialogue.nodes[&self.node]
+    }
+
+    /// The choices of the current node whose conditions hold
+    pub fn choices(&self, w: &World) -> Vec<&Choice> {
+        self.node()
+            .choices
+            .iter()
+            .filter(|choice| match &choice.condition {
+                Some(condition) => w.check(condition),
+                None => true,
+            })
+            .collect()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Highlights the choice `by` places down from the current one, wrapping around
+    pub fn move_selection(&mut self, by: i32, choice_count: usize) {
+        if choice_count == 0 {
+            return;
+        }
+        self.selected = (self.selected as i32 + by).rem_euclid(choice_count as i32) as usize;
+    }
+
+    /// Where picking `choice` goes, and what it runs on the way. None if it's out of range.
+    /// A node with no choices is moved on from by picking 0
+    pub fn pick(&self, w: &World, choice: usize) -> Option<(Option<String>, Vec<Command>)> {
+        let node = self.node();
+        if node.choices.is_empty() {
+            return if choice == 0 {
+                Some((node.next.clone(), Vec::new()))
+            } else {
+                None
+            };
+        }
+        self.choices(w)
+            .get(choice)
+            .map(|choice| (choice.goto.clone(), choice.commands.clone()))
+    }
+
+    pub fn go_to(&mut self, node: String) {
+        self.node = node;
+        self.selected = 0;
+    }
+}
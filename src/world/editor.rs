@@ -0,0 +1,927 @@
+//! The level editor. Opened in the game window with E, it shows a palette of everything that can be placed,
+//! a direction picker, the properties of what's being placed and a status bar along the bottom
+
+use serde_json::Value;
+use speedy2d::{color::Color, dimen::Vector2, shape::Rectangle};
+
+use crate::draw::render::Renderer;
+
+use super::{
+    entity::Entity,
+    history::{Edit, History, Layer},
+    prefab::Prefab,
+    registry::{self, Category},
+    signal::Wiring,
+    space::{Direction, TilePos},
+    tile::Tile,
+};
+
+pub const MOUSE_LEFT: u8 = 0b10000000;
+pub const MOUSE_RIGHT: u8 = 0b01000000;
+pub const MOUSE_MID: u8 = 0b00100000;
+/// No bitflag value so it doesn't affect anything
+pub const MOUSE_OTHER: u8 = 0b00000000;
+
+/// Width of the panel down the right of the screen. Measured in pixels
+const PANEL_WIDTH: f32 = 200.0;
+/// Height of the status bar, the tabs and each row of the palette. Measured in pixels
+pub const ROW_HEIGHT: f32 = 24.0;
+const FONT_SIZE: f32 = 12.0;
+/// Width of a character as a fraction of the font size. The font is monospace
+const CHAR_WIDTH: f32 = 0.8;
+const MARGIN: f32 = 4.0;
+/// Size of each cell of the direction picker. Measured in pixels
+const DIRECTION_CELL: f32 = 20.0;
+/// Properties shown in the inspector at once, below its heading. The rest are scrolled to
+const INSPECTOR_ROWS: usize = 6;
+
+const PANEL_COLOR: Color = Color::from_rgba(0.125, 0.125, 0.125, 0.88);
+const HIGHLIGHT_COLOR: Color = Color::from_rgb(0.31, 0.31, 0.31);
+const FAINT_TEXT_COLOR: Color = Color::from_rgb(0.69, 0.69, 0.69);
+
+/// Laid out the same as the picker on screen
+const DIRECTION_GRID: [[Direction; 3]; 3] = [
+    [Direction::CornerTL, Direction::Top, Direction::CornerTR],
+    [Direction::Left, Direction::Center, Direction::Right],
+    [Direction::CornerBL, Direction::Bottom, Direction::CornerBR],
+];
+
+const HELP: &str = "E close  Ctrl+Z undo  Ctrl+Y redo  Ctrl+C/X/V copy/cut/paste  Ctrl+R/M turn/flip  Ctrl+S/O save/load prefab  B tool  G auto-tile  Z layer  R rotate  N new region  C channels  L logic  P pin warp  Q teleport";
+
+/// How the mouse places and erases things
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    /// Everything the mouse is dragged over
+    Brush,
+    /// A straight line from where the drag started to where it ended
+    Line,
+    /// A rectangle spanning the drag
+    Rectangle,
+    /// The edges of a rectangle spanning the drag
+    HollowRectangle,
+    /// The cells connected to the one clicked that have the same type of tile
+    Fill,
+    /// Picks the rectangle spanning the drag for copying and cutting
+    Select,
+}
+
+impl Tool {
+    pub fn next(self) -> Tool {
+        match self {
+            Tool::Brush => Tool::Line,
+            Tool::Line => Tool::Rectangle,
+            Tool::Rectangle => Tool::HollowRectangle,
+            Tool::HollowRectangle => Tool::Fill,
+            Tool::Fill => Tool::Select,
+            Tool::Select => Tool::Brush,
+        }
+    }
+
+    /// Whether the tool does something with the whole drag once it ends, rather than as it goes
+    pub fn is_shape(self) -> bool {
+        matches!(
+            self,
+            Tool::Line | Tool::Rectangle | Tool::HollowRectangle | Tool::Select
+        )
+    }
+
+    /// Cells covered by a drag from `start` to `end`. The brush and fill work cell by cell instead
+    pub fn shape(self, start: TilePos, end: TilePos) -> Vec<TilePos> {
+        match self {
+            Tool::Rectangle | Tool::Select => start.rect_to(end, true),
+            Tool::HollowRectangle => start.rect_to(end, false),
+            _ => start.line_to(end),
+        }
+    }
+}
+
+pub enum DrawItem {
+    Tile(Box<dyn Tile>),
+    Entity(Box<dyn Entity>),
+}
+impl DrawItem {
+    pub fn default_tile() -> DrawItem {
+        DrawItem::Tile(registry::TILES[0].make())
+    }
+    pub fn default_entity() -> DrawItem {
+        DrawItem::Entity(registry::ENTITIES[0].make())
+    }
+    /// What `tile` is drawn with once it's picked up from the map
+    pub fn pick_tile(tile: &dyn Tile) -> DrawItem {
+        DrawItem::Tile(tile.create(TilePos::origin(), Direction::Center))
+    }
+    /// What `entity` is drawn with once it's picked up from the map
+    pub fn pick_entity(entity: &dyn Entity) -> DrawItem {
+        DrawItem::Entity(entity.create(TilePos::origin(), Direction::Center))
+    }
+    /// The typetag name of the item's type
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrawItem::Tile(tile) => tile.typetag_name(),
+            DrawItem::Entity(entity) => entity.typetag_name(),
+        }
+    }
+    /// Name shown in the editor, from the registry
+    pub fn display_name(&self) -> &'static str {
+        let kind = match self {
+            DrawItem::Tile(tile) => {
+                registry::tile_kind(tile.typetag_name()).map(|kind| kind.display_name)
+            }
+            DrawItem::Entity(entity) => {
+                registry::entity_kind(entity.typetag_name()).map(|kind| kind.display_name)
+            }
+        };
+        kind.unwrap_or_else(|| self.name())
+    }
+    pub fn category(&self) -> Option<Category> {
+        match self {
+            DrawItem::Tile(tile) => {
+                registry::tile_kind(tile.typetag_name()).map(|kind| kind.category)
+            }
+            DrawItem::Entity(entity) => {
+                registry::entity_kind(entity.typetag_name()).map(|kind| kind.category)
+            }
+        }
+    }
+    pub fn layer(&self) -> Layer {
+        match self {
+            DrawItem::Tile(_) => Layer::Tile,
+            DrawItem::Entity(_) => Layer::Entity,
+        }
+    }
+    /// A new item of the kind after this one in the registry
+    pub fn next(&self) -> DrawItem {
+        match self {
+            DrawItem::Tile(tile) => {
+                DrawItem::Tile(registry::next(registry::TILES, tile.typetag_name()).make())
+            }
+            DrawItem::Entity(entity) => {
+                DrawItem::Entity(registry::next(registry::ENTITIES, entity.typetag_name()).make())
+            }
+        }
+    }
+    pub fn pick(&self) -> DrawItem {
+        match self {
+            DrawItem::Tile(tile) => DrawItem::pick_tile(&**tile),
+            DrawItem::Entity(entity) => DrawItem::pick_entity(&**entity),
+        }
+    }
+    /// The same item facing `direction`, for previewing
+    fn facing(&self, direction: Direction) -> DrawItem {
+        match self {
+            DrawItem::Tile(tile) => DrawItem::Tile(tile.create(TilePos::origin(), direction)),
+            DrawItem::Entity(entity) => {
+                DrawItem::Entity(entity.create(TilePos::origin(), direction))
+            }
+        }
+    }
+    /// Channel of the item, if it's a signal source
+    pub fn channel_mut(&mut self) -> Option<&mut String> {
+        match self {
+            DrawItem::Tile(tile) => tile.channel_mut(),
+            DrawItem::Entity(entity) => entity.channel_mut(),
+        }
+    }
+    /// Wiring of the item, if it's a signal sink
+    pub fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        match self {
+            DrawItem::Tile(tile) => tile.wiring_mut(),
+            DrawItem::Entity(entity) => entity.wiring_mut(),
+        }
+    }
+
+    /// Fields of the item that are set while making a region, as they're saved.
+    /// Leaves out its type and position, and anything that only changes during play
+    pub fn properties(&self) -> Vec<(String, Value)> {
+        let (value, runtime) = match self {
+            DrawItem::Tile(tile) => (serde_json::to_value(tile), tile.runtime_fields()),
+            DrawItem::Entity(entity) => (serde_json::to_value(entity), entity.runtime_fields()),
+        };
+        let fields = match value {
+            Ok(Value::Object(fields)) => fields,
+            _ => return Vec::new(),
+        };
+        fields
+            .into_iter()
+            .filter(|(key, _)| key != "type" && key != "pos" && !runtime.contains(&key.as_str()))
+            .map(|(key, mut value)| {
+                if key == "wiring" {
+                    if let Value::Object(wiring) = &mut value {
+                        wiring.retain(|field, _| !Wiring::RUNTIME_FIELDS.contains(&field.as_str()));
+                    }
+                }
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// Sets the field `key` to `value` by saving the item and loading it back with the field changed
+    pub fn set_property(&mut self, key: &str, value: Value) -> Result<(), serde_json::Error> {
+        let mut fields = match self {
+            DrawItem::Tile(tile) => serde_json::to_value(&*tile)?,
+            DrawItem::Entity(entity) => serde_json::to_value(&*entity)?,
+        };
+        if let Value::Object(fields) = &mut fields {
+            fields.insert(key.to_string(), value);
+        }
+        *self = match self {
+            DrawItem::Tile(_) => DrawItem::Tile(serde_json::from_value(fields)?),
+            DrawItem::Entity(_) => DrawItem::Entity(serde_json::from_value(fields)?),
+        };
+        self.reset_anim();
+        Ok(())
+    }
+
+    fn reset_anim(&mut self) {
+        match self {
+            DrawItem::Tile(tile) => {
+                tile.reset_anim();
+                tile.get_anim_mut().select("base").unwrap();
+            }
+            DrawItem::Entity(entity) => {
+                entity.reset_anim();
+                entity.get_anim_mut().select("base").unwrap();
+            }
+        }
+    }
+
+    /// Draws the item's sprite over `rect`
+    fn draw_preview(&mut self, renderer: &mut dyn Renderer, rect: Rectangle) {
+        match self {
+            DrawItem::Tile(tile) => {
+                let color = tile.draw_color();
+                tile.get_anim_mut().draw_tinted(renderer, rect, color);
+            }
+            DrawItem::Entity(entity) => {
+                let color = entity.draw_color();
+                entity.get_anim_mut().draw_tinted(renderer, rect, color);
+            }
+        }
+    }
+
+    /// One of every kind in the registry on the item's layer, in order
+    fn every_kind(&self) -> Vec<DrawItem> {
+        let mut items: Vec<DrawItem> = match self {
+            DrawItem::Tile(_) => registry::TILES
+                .iter()
+                .map(|kind| DrawItem::Tile(kind.make()))
+                .collect(),
+            DrawItem::Entity(_) => registry::ENTITIES
+                .iter()
+                .map(|kind| DrawItem::Entity(kind.make()))
+                .collect(),
+        };
+        for item in items.iter_mut() {
+            item.reset_anim();
+        }
+        items
+    }
+}
+
+/// What text being typed into the editor is for
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptKind {
+    /// Name of a region to make and go to
+    NewRegion,
+    /// Comma separated channels for the sources and sinks placed from now on
+    Channels,
+    /// New value of a property of the item being placed
+    Property(String),
+    /// Name to save the clipboard under as a prefab
+    SavePrefab,
+    /// Name of a prefab to put in the clipboard
+    LoadPrefab,
+}
+
+/// Text being typed into the editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub text: String,
+    /// The key that opened the prompt also types its character, which shouldn't end up in the text
+    skip: Option<char>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, text: String) -> Prompt {
+        Prompt {
+            kind,
+            text,
+            skip: None,
+        }
+    }
+
+    /// A prompt opened by the key for `c`
+    pub fn opened_by(kind: PromptKind, text: String, c: char) -> Prompt {
+        Prompt {
+            skip: Some(c),
+            ..Prompt::new(kind, text)
+        }
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        let skip = self.skip.take();
+        if c.is_control() || matches!(skip, Some(skip) if skip.eq_ignore_ascii_case(&c)) {
+            return;
+        }
+        self.text.push(c);
+    }
+
+    fn label(&self) -> String {
+        match &self.kind {
+            PromptKind::NewRegion => String::from("New region"),
+            PromptKind::Channels => String::from("Channels"),
+            PromptKind::Property(key) => key.clone(),
+            PromptKind::SavePrefab => String::from("Save prefab as"),
+            PromptKind::LoadPrefab => String::from("Load prefab"),
+        }
+    }
+}
+
+/// Where each part of the overlay goes on a screen of a given size
+struct Layout {
+    panel: Rectangle,
+    status: Rectangle,
+    tabs: Rectangle,
+    directions: Rectangle,
+    tool: Rectangle,
+    palette: Rectangle,
+    inspector: Rectangle,
+}
+
+impl Layout {
+    fn new(resolution: (u32, u32)) -> Layout {
+        let (width, height) = (resolution.0 as f32, resolution.1 as f32);
+        let left = width - PANEL_WIDTH;
+        let bottom = height - ROW_HEIGHT;
+
+        let directions_top = ROW_HEIGHT + MARGIN;
+        let directions_bottom = directions_top + 3.0 * DIRECTION_CELL;
+        let tool_top = directions_bottom + MARGIN;
+        let palette_top = tool_top + ROW_HEIGHT;
+        let inspector_top = bottom - (INSPECTOR_ROWS + 1) as f32 * FONT_SIZE - MARGIN;
+        Layout {
+            panel: Rectangle::from_tuples((left, 0.0), (width, bottom)),
+            status: Rectangle::from_tuples((0.0, bottom), (width, height)),
+            tabs: Rectangle::from_tuples((left, 0.0), (width, ROW_HEIGHT)),
+            directions: Rectangle::from_tuples(
+                (left + MARGIN, directions_top),
+                (left + MARGIN + 3.0 * DIRECTION_CELL, directions_bottom),
+            ),
+            tool: Rectangle::from_tuples((left, tool_top), (width, palette_top)),
+            palette: Rectangle::from_tuples(
+                (left, palette_top),
+                (width, inspector_top.max(palette_top)),
+            ),
+            inspector: Rectangle::from_tuples((left, inspector_top), (width, bottom)),
+        }
+    }
+
+    /// Number of palette rows that fit on screen
+    fn palette_rows(&self) -> usize {
+        (self.palette.height() / ROW_HEIGHT) as usize
+    }
+
+    fn tab(&self, entities: bool) -> Rectangle {
+        let half = self.tabs.width() / 2.0;
+        let left = self.tabs.top_left().x + if entities { half } else { 0.0 };
+        Rectangle::from_tuples((left, 0.0), (left + half, ROW_HEIGHT))
+    }
+
+    fn direction_cell(&self, row: usize, column: usize) -> Rectangle {
+        let top_left = self.directions.top_left();
+        let left = top_left.x + column as f32 * DIRECTION_CELL;
+        let top = top_left.y + row as f32 * DIRECTION_CELL;
+        Rectangle::from_tuples(
+            (left + 1.0, top + 1.0),
+            (left + DIRECTION_CELL - 1.0, top + DIRECTION_CELL - 1.0),
+        )
+    }
+
+    fn palette_row(&self, row: usize) -> Rectangle {
+        let top = self.palette.top_left().y + row as f32 * ROW_HEIGHT;
+        Rectangle::from_tuples(
+            (self.palette.top_left().x, top),
+            (self.palette.bottom_right().x, top + ROW_HEIGHT),
+        )
+    }
+
+    fn inspector_row(&self, row: usize) -> Rectangle {
+        let top = self.inspector.top_left().y + (row + 1) as f32 * FONT_SIZE;
+        Rectangle::from_tuples(
+            (self.inspector.top_left().x, top),
+            (self.inspector.bottom_right().x, top + FONT_SIZE),
+        )
+    }
+}
+
+/// Cuts `text` short so about `width` pixels of it show at `size`
+fn fit(text: &str, width: f32, size: f32) -> String {
+    let max = (width / (size * CHAR_WIDTH)) as usize;
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max.saturating_sub(2)).collect();
+    short.push_str("..");
+    short
+}
+
+/// Draws `text` on one line, however long it is
+fn draw_line(renderer: &mut dyn Renderer, pos: (f32, f32), color: Color, text: &str) {
+    renderer.draw_text(pos, color, FONT_SIZE, f32::MAX, text);
+}
+
+/// An item in the palette, with what's drawn for it worked out once when the palette is filled
+struct PaletteRow {
+    item: DrawItem,
+    display_name: &'static str,
+    category: Option<Category>,
+    /// To highlight it when it's the item being drawn
+    properties: Vec<(String, Value)>,
+}
+
+impl PaletteRow {
+    fn new(item: DrawItem) -> PaletteRow {
+        PaletteRow {
+            display_name: item.display_name(),
+            category: item.category(),
+            properties: item.properties(),
+            item,
+        }
+    }
+}
+
+/// State of the level editor. Only lasts for the session, nothing here is saved
+pub struct Editor {
+    /// Whether the editor is showing. The world can only be painted while it is
+    pub open: bool,
+    pub draw_item: DrawItem,
+    pub direction: Direction,
+    pub tool: Tool,
+    /// Whether tiles placed or removed re-pick their variant, and their neighbours', to fit together
+    pub auto_tile: bool,
+    pub mouse_buttons: u8,
+    /// Cell the mouse went down on, while dragging
+    pub drag_start: Option<TilePos>,
+    /// Cell the brush was last on, so fast drags are joined up
+    pub last_cell: Option<TilePos>,
+    /// Opposite corners of the rectangle picked with the select tool
+    pub selection: Option<(TilePos, TilePos)>,
+    /// Whatever was last copied or cut, or loaded from a prefab
+    pub clipboard: Option<Prefab>,
+    /// Destination given to warps placed in the editor
+    pub warp_target: Option<(String, TilePos)>,
+    /// Channels given to signal sources and sinks placed in the editor.
+    /// Sources put their signal out on the first one
+    pub channels: Option<Vec<String>>,
+    /// Text being typed, if any. Takes all the key presses while it's open
+    pub prompt: Option<Prompt>,
+    /// Edits made so far, for undoing and redoing
+    pub history: History,
+    /// Whether Ctrl is held, for the undo and redo shortcuts
+    pub ctrl_held: bool,
+    /// Everything on the same layer as the item being drawn
+    palette: Vec<PaletteRow>,
+    /// First palette row on screen
+    scroll: usize,
+    /// First property in the inspector
+    inspector_scroll: usize,
+    /// Last thing the editor had to say, shown in the status bar
+    status: Option<String>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor {
+            open: false,
+            draw_item: DrawItem::default_tile(),
+            direction: Direction::Top,
+            tool: Tool::Brush,
+            auto_tile: false,
+            mouse_buttons: 0,
+            drag_start: None,
+            last_cell: None,
+            selection: None,
+            clipboard: None,
+            warp_target: None,
+            channels: None,
+            prompt: None,
+            history: History::default(),
+            ctrl_held: false,
+            palette: Vec::new(),
+            scroll: 0,
+            inspector_scroll: 0,
+            status: None,
+        }
+    }
+}
+
+impl Editor {
+    /// Points everything the editor keeps that leads into region `old` at `new`, once it's been renamed
+    pub fn rename_region(&mut self, old: &str, new: &str) {
+        if let Some((region, _)) = &mut self.warp_target {
+            if region == old {
+                *region = new.to_string();
+            }
+        }
+        let target = match &self.draw_item {
+            DrawItem::Tile(tile) => tile.warp_target().map(|(region, pos)| (region == old, pos)),
+            DrawItem::Entity(entity) => entity
+                .warp_target()
+                .map(|(region, pos)| (region == old, pos)),
+        };
+        if let Some((true, pos)) = target {
+            match &mut self.draw_item {
+                DrawItem::Tile(tile) => tile.set_warp_target(new.to_string(), pos),
+                DrawItem::Entity(entity) => entity.set_warp_target(new.to_string(), pos),
+            }
+        }
+        if let Some(clipboard) = &mut self.clipboard {
+            clipboard.retarget_warps(old, new);
+        }
+        self.history.rename_region(old, new);
+    }
+
+    /// Forgets everything the editor keeps about a region that's been deleted
+    pub fn forget_region(&mut self, name: &str) {
+        if matches!(&self.warp_target, Some((region, _)) if region == name) {
+            self.warp_target = None;
+        }
+        self.history.forget_region(name);
+    }
+
+    /// Gives the pinned warp target to the item being drawn, if it's a warp
+    pub fn apply_warp_target(&mut self) {
+        if let Some((region, pos)) = &self.warp_target {
+            match &mut self.draw_item {
+                DrawItem::Tile(tile) => tile.set_warp_target(region.clone(), *pos),
+                DrawItem::Entity(entity) => entity.set_warp_target(region.clone(), *pos),
+            }
+        }
+    }
+
+    /// Gives the pinned channels to the item being drawn, if it's a signal source or sink
+    pub fn apply_channels(&mut self) {
+        if let Some(channels) = &self.channels {
+            if let Some(channel) = self.draw_item.channel_mut() {
+                *channel = channels.first().cloned().unwrap_or_default();
+            }
+            if let Some(wiring) = self.draw_item.wiring_mut() {
+                wiring.channels = channels.clone();
+            }
+        }
+    }
+
+    /// Shows or hides the editor. The palette is filled in the first time it opens
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.history.end_stroke();
+        self.prompt = None;
+        self.mouse_buttons = 0;
+        self.drag_start = None;
+        self.last_cell = None;
+        if self.open && self.palette.is_empty() {
+            self.fill_palette();
+        }
+    }
+
+    /// Switches between placing tiles and entities
+    pub fn switch_layer(&mut self) {
+        self.draw_item = match self.draw_item {
+            DrawItem::Entity(_) => DrawItem::default_tile(),
+            DrawItem::Tile(_) => DrawItem::default_entity(),
+        };
+        self.fill_palette();
+    }
+
+    fn fill_palette(&mut self) {
+        self.palette = self
+            .draw_item
+            .every_kind()
+            .into_iter()
+            .map(PaletteRow::new)
+            .collect();
+        self.scroll = 0;
+    }
+
+    /// Everything on the same layer as the item being drawn, as listed in the palette
+    pub fn palette(&self) -> impl Iterator<Item = &DrawItem> {
+        self.palette.iter().map(|row| &row.item)
+    }
+
+    /// Scrolls whatever's under the pixel `pos` by `rows`, down for positive.
+    /// That's the inspector if the mouse is over it, otherwise the palette
+    pub fn scroll(&mut self, pos: (f32, f32), resolution: (u32, u32), rows: i32) {
+        let layout = Layout::new(resolution);
+        if layout.inspector.contains(Vector2::new(pos.0, pos.1)) {
+            let max = self
+                .draw_item
+                .properties()
+                .len()
+                .saturating_sub(INSPECTOR_ROWS) as i32;
+            self.inspector_scroll = (self.inspector_scroll as i32 + rows).clamp(0, max) as usize;
+        } else {
+            let max = self.palette.len().saturating_sub(1) as i32;
+            self.scroll = (self.scroll as i32 + rows).clamp(0, max) as usize;
+        }
+    }
+
+    /// The properties showing in the inspector, with the index of the first, and how many there are in all
+    fn inspected(&self) -> (usize, Vec<(String, Value)>, usize) {
+        let properties = self.draw_item.properties();
+        let total = properties.len();
+        // Scrolled as far as it goes for a different item, it stays scrolled as far as it goes for this one
+        let first = self
+            .inspector_scroll
+            .min(total.saturating_sub(INSPECTOR_ROWS));
+        let showing = properties
+            .into_iter()
+            .skip(first)
+            .take(INSPECTOR_ROWS)
+            .collect();
+        (first, showing, total)
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    pub fn status(&self) -> Option<&String> {
+        self.status.as_ref()
+    }
+
+    /// Starts typing a new value for the property `key` of the item being drawn
+    fn edit_property(&mut self, key: String, value: &Value) {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            value => value.to_string(),
+        };
+        self.prompt = Some(Prompt::new(PromptKind::Property(key), text));
+    }
+
+    /// Sets the property `key` of the item being drawn from typed text.
+    /// Text that isn't JSON is taken as a string, so names don't need quotes
+    pub fn set_property(&mut self, key: &str, text: &str) {
+        let value = serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()));
+        let before = self
+            .draw_item
+            .properties()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map_or(Value::Null, |(_, value)| value);
+        match self.draw_item.set_property(key, value.clone()) {
+            Ok(()) => {
+                self.history.record(Edit::Property {
+                    kind: self.draw_item.name().to_string(),
+                    key: key.to_string(),
+                    before,
+                    after: value,
+                });
+                self.status = Some(format!("Set {}", key));
+            }
+            Err(e) => self.status = Some(format!("Couldn't set {}: {}", key, e)),
+        }
+    }
+
+    /// Whether the pixel `pos` is over the overlay rather than the world
+    pub fn covers(&self, pos: (f32, f32), resolution: (u32, u32)) -> bool {
+        let layout = Layout::new(resolution);
+        let point = Vector2::new(pos.0, pos.1);
+        self.open && (layout.panel.contains(point) || layout.status.contains(point))
+    }
+
+    /// Handles a click on the overlay at the pixel `pos`. Returns false if it missed the overlay
+    pub fn click(&mut self, pos: (f32, f32), resolution: (u32, u32)) -> bool {
+        if !self.covers(pos, resolution) {
+            return false;
+        }
+        let layout = Layout::new(resolution);
+        let point = Vector2::new(pos.0, pos.1);
+
+        if layout.tabs.contains(point) {
+            if layout.tab(true).contains(point) != matches!(self.draw_item, DrawItem::Entity(_)) {
+                self.switch_layer();
+            }
+            return true;
+        }
+        for (row, directions) in DIRECTION_GRID.iter().enumerate() {
+            for (column, &direction) in directions.iter().enumerate() {
+                if layout.direction_cell(row, column).contains(point) {
+                    self.direction = direction;
+                    return true;
+                }
+            }
+        }
+        if layout.tool.contains(point) {
+            self.tool = self.tool.next();
+            return true;
+        }
+        for row in 0..layout.palette_rows() {
+            if layout.palette_row(row).contains(point) {
+                if let Some(row) = self.palette.get(self.scroll + row) {
+                    self.draw_item = row.item.pick();
+                }
+                return true;
+            }
+        }
+        let (_, properties, _) = self.inspected();
+        for (row, (key, value)) in properties.iter().enumerate() {
+            if layout.inspector_row(row).contains(point) {
+                self.edit_property(key.clone(), value);
+                return true;
+            }
+        }
+        true
+    }
+
+    /// Draws the panel and status bar over the world. `cursor` is the tile under the mouse
+    pub fn draw(&mut self, renderer: &mut dyn Renderer, cursor: TilePos, region: &str) {
+        let layout = Layout::new(renderer.resolution());
+        renderer.draw_rect(layout.panel.clone(), PANEL_COLOR);
+        renderer.draw_rect(layout.status.clone(), PANEL_COLOR);
+
+        self.draw_tabs(renderer, &layout);
+        self.draw_directions(renderer, &layout);
+        self.draw_tool(renderer, &layout);
+        self.draw_palette(renderer, &layout);
+        self.draw_inspector(renderer, &layout);
+        self.draw_status(renderer, &layout, cursor, region);
+    }
+
+    fn draw_tabs(&self, renderer: &mut dyn Renderer, layout: &Layout) {
+        let on_entities = matches!(self.draw_item, DrawItem::Entity(_));
+        for &(entities, name) in &[(false, "Tiles"), (true, "Entities")] {
+            let tab = layout.tab(entities);
+            let color = if entities == on_entities {
+                renderer.draw_rect(tab.clone(), HIGHLIGHT_COLOR);
+                Color::WHITE
+            } else {
+                FAINT_TEXT_COLOR
+            };
+            draw_line(renderer, (tab.top_left().x + MARGIN, MARGIN), color, name);
+        }
+    }
+
+    /// The picker, with the item being drawn facing the picked direction next to it
+    fn draw_directions(&self, renderer: &mut dyn Renderer, layout: &Layout) {
+        for (row, directions) in DIRECTION_GRID.iter().enumerate() {
+            for (column, &direction) in directions.iter().enumerate() {
+                let cell = layout.direction_cell(row, column);
+                let color = if direction == self.direction {
+                    Color::YELLOW
+                } else {
+                    HIGHLIGHT_COLOR
+                };
+                renderer.draw_rect(cell, color);
+            }
+        }
+
+        let left = layout.directions.bottom_right().x + 2.0 * MARGIN;
+        let top = layout.directions.top_left().y;
+        // Sprites are 7 by 10
+        let preview = Rectangle::from_tuples(
+            (left, top),
+            (left + 2.1 * DIRECTION_CELL, top + 3.0 * DIRECTION_CELL),
+        );
+        self.draw_item
+            .facing(self.direction)
+            .draw_preview(renderer, preview.clone());
+        draw_line(
+            renderer,
+            (preview.bottom_right().x + MARGIN, top),
+            Color::WHITE,
+            &format!("{:?}", self.direction),
+        );
+    }
+
+    fn draw_tool(&self, renderer: &mut dyn Renderer, layout: &Layout) {
+        let left = layout.tool.top_left().x + MARGIN;
+        let width = layout.tool.width() - 2.0 * MARGIN;
+        let mut text = format!("Tool: {:?}", self.tool);
+        if self.auto_tile {
+            text.push_str(", auto-tile");
+        }
+        draw_line(
+            renderer,
+            (left, layout.tool.top_left().y + MARGIN),
+            Color::WHITE,
+            &fit(&text, width, FONT_SIZE),
+        );
+    }
+
+    fn draw_palette(&mut self, renderer: &mut dyn Renderer, layout: &Layout) {
+        let selected = self.draw_item.properties();
+        let name = self.draw_item.name();
+        let mut category = None;
+        for row in 0..layout.palette_rows() {
+            let rect = layout.palette_row(row);
+            let entry = match self.palette.get_mut(self.scroll + row) {
+                Some(entry) => entry,
+                None => break,
+            };
+            if entry.item.name() == name && entry.properties == selected {
+                renderer.draw_rect(rect.clone(), HIGHLIGHT_COLOR);
+            }
+            let left = rect.top_left().x + MARGIN;
+            let top = rect.top_left().y;
+            let right = rect.bottom_right().x - MARGIN;
+            // The first of each category is labelled with it, under a line from the one before
+            let mut label_width = 0.0;
+            if entry.category != category {
+                if row > 0 {
+                    renderer.draw_rect(
+                        Rectangle::from_tuples(
+                            (rect.top_left().x, top),
+                            (rect.bottom_right().x, top + 1.0),
+                        ),
+                        FAINT_TEXT_COLOR,
+                    );
+                }
+                if let Some(label) = entry.category.map(Category::label) {
+                    label_width = (label.len() as f32 + 1.0) * FONT_SIZE * CHAR_WIDTH;
+                    draw_line(
+                        renderer,
+                        (right - label_width, top + MARGIN),
+                        FAINT_TEXT_COLOR,
+                        label,
+                    );
+                }
+            }
+            category = entry.category;
+            // Sprites are 7 by 10
+            entry.item.draw_preview(
+                renderer,
+                Rectangle::from_tuples((left, top + 2.0), (left + 14.0, top + 22.0)),
+            );
+            let text_left = left + 14.0 + 2.0 * MARGIN;
+            let width = right - text_left - label_width;
+            draw_line(
+                renderer,
+                (text_left, top + MARGIN),
+                Color::WHITE,
+                &fit(entry.display_name, width, FONT_SIZE),
+            );
+        }
+    }
+
+    fn draw_inspector(&self, renderer: &mut dyn Renderer, layout: &Layout) {
+        let left = layout.inspector.top_left().x + MARGIN;
+        let width = layout.inspector.width() - 2.0 * MARGIN;
+        let (first, properties, total) = self.inspected();
+        // With more than fit, it says which are showing instead
+        let heading = if total > INSPECTOR_ROWS {
+            let last = first + properties.len();
+            format!(
+                "{} {}-{}/{}",
+                self.draw_item.display_name(),
+                first + 1,
+                last,
+                total
+            )
+        } else {
+            format!("{} properties", self.draw_item.display_name())
+        };
+        draw_line(
+            renderer,
+            (left, layout.inspector.top_left().y),
+            Color::YELLOW,
+            &fit(&heading, width, FONT_SIZE),
+        );
+        for (row, (key, value)) in properties.iter().enumerate() {
+            let rect = layout.inspector_row(row);
+            let text = format!("{}: {}", key, value);
+            draw_line(
+                renderer,
+                (left, rect.top_left().y),
+                FAINT_TEXT_COLOR,
+                &fit(&text, width, FONT_SIZE),
+            );
+        }
+    }
+
+    fn draw_status(
+        &self,
+        renderer: &mut dyn Renderer,
+        layout: &Layout,
+        cursor: TilePos,
+        region: &str,
+    ) {
+        let text = match (&self.prompt, &self.status) {
+            (Some(prompt), _) => format!("{}: {}_", prompt.label(), prompt.text),
+            (None, Some(status)) => status.clone(),
+            (None, None) => String::from(HELP),
+        };
+        let coordinates = format!("{} ({}, {})", region, cursor.0, cursor.1);
+        let top = layout.status.top_left().y + MARGIN;
+        draw_line(renderer, (MARGIN, top), Color::YELLOW, &coordinates);
+
+        let left = MARGIN + (coordinates.len() as f32 + 2.0) * FONT_SIZE * CHAR_WIDTH;
+        let width = layout.status.width() - left - MARGIN;
+        draw_line(
+            renderer,
+            (left, top),
+            Color::WHITE,
+            &fit(&text, width, FONT_SIZE),
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use speedy2d::color::Color;
+
+use crate::{
+    draw::{animation::Animation, sprite},
+    world::{
+        command::Command,
+        operation::PostOperation,
+        space::{Direction, TilePos},
+    },
+};
+
+use super::Entity;
+
+/// How far away an enemy notices the player, in tiles
+const SIGHT: i32 = 5;
+
+/// Squares about to be hit. Shown to the player for a turn before it lands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attack {
+    pub cells: Vec<TilePos>,
+    pub damage: u32,
+}
+
+/// Shape of the squares an enemy attacks, relative to the enemy when facing up.
+/// Gets turned to face whichever way the player is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AttackPattern {
+    /// The square in front
+    Jab,
+    /// Two squares straight ahead
+    Lance,
+    /// The three squares in front, diagonals included
+    Sweep,
+    /// Every square around the enemy
+    Burst,
+    Custom(Vec<TilePos>),
+}
+
+impl AttackPattern {
+    fn shape(&self) -> Vec<TilePos> {
+        match self {
+            AttackPattern::Jab => vec![TilePos(0, -1)],
+            AttackPattern::Lance => vec![TilePos(0, -1), TilePos(0, -2)],
+            AttackPattern::Sweep => vec![TilePos(-1, -1), TilePos(0, -1), TilePos(1, -1)],
+            AttackPattern::Burst => Direction::ALL
+                .iter()
+                .filter(|&&d| d != Direction::Center)
+                .map(|d| d.direction_vector())
+                .collect(),
+            AttackPattern::Custom(shape) => shape.clone(),
+        }
+    }
+
+    /// The squares to attack from `pos` so that `target` is hit. None if it's out of reach
+    pub fn aim(&self, pos: TilePos, target: TilePos) -> Option<Vec<TilePos>> {
+        let shape = self.shape();
+        [
+            Direction::Top,
+            Direction::Right,
+            Direction::Bottom,
+            Direction::Left,
+        ]
+        .iter()
+        .map(|&facing| {
+            shape
+                .iter()
+                .map(|&offset| pos + offset.rotated(facing))
+                .collect::<Vec<_>>()
+        })
+        .find(|cells| cells.contains(&target))
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            AttackPattern::Jab => Color::from_hex_argb(0xFFE04040),
+            AttackPattern::Lance => Color::from_hex_argb(0xFFE08030),
+            AttackPattern::Sweep => Color::from_hex_argb(0xFFB040E0),
+            AttackPattern::Burst => Color::from_hex_argb(0xFF40A0E0),
+            AttackPattern::Custom(_) => Color::from_hex_argb(0xFF909090),
+        }
+    }
+}
+
+fn default_damage() -> u32 {
+    1
+}
+
+fn default_rest_turns() -> u32 {
+    1
+}
+
+/// A creature from the rift. Walks up to the player, telegraphs an attack for a turn, then lands it.
+/// Defeated when the player walks into it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Enemy {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    pattern: AttackPattern,
+    #[serde(default = "default_damage")]
+    damage: u32,
+    /// Turns spent catching its breath after attacking
+    #[serde(default = "default_rest_turns")]
+    rest_turns: u32,
+    #[serde(default)]
+    resting: u32,
+    #[serde(default)]
+    attack: Option<Attack>,
+}
+
+#[typetag::serde]
+impl Entity for Enemy {
+    fn draw_color(&self) -> Color {
+        self.pattern.color()
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["attack", "resting"]
+    }
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Enemy::new(pos, self.pattern.clone()))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        // Beaten before its attack lands
+        self.attack = None;
+        PostOperation::new_empty()
+            .with_block_player(move_pos)
+            .with_command(Command::Strike { pos: self.pos })
+    }
+
+    fn request_moves(&mut self, move_pos: &mut Vec<TilePos>, player_pos: TilePos) {
+        if !self.is_ready() || self.pattern.aim(self.pos, player_pos).is_some() {
+            return;
+        }
+        if self.pos.chebyshev_distance(player_pos) > SIGHT {
+            return;
+        }
+        let TilePos(dx, dy) = player_pos - self.pos;
+        // Close the longer gap first
+        let steps = [TilePos(dx.signum(), 0), TilePos(0, dy.signum())];
+        let steps = if dx.abs() >= dy.abs() {
+            steps
+        } else {
+            [steps[1], steps[0]]
+        };
+        move_pos.extend(steps.iter().filter(|&&step| step != TilePos::origin()));
+    }
+
+    fn do_turn(&mut self, player_pos: TilePos) -> PostOperation {
+        if self.attack.take().is_some() {
+            self.resting = self.rest_turns;
+        } else if self.resting > 0 {
+            self.resting -= 1;
+        } else if let Some(cells) = self.pattern.aim(self.pos, player_pos) {
+            self.attack = Some(Attack {
+                cells,
+                damage: self.damage,
+            });
+        }
+        PostOperation::new_empty()
+    }
+
+    fn attack(&self) -> Option<&Attack> {
+        self.attack.as_ref()
+    }
+
+    fn is_creature(&self) -> bool {
+        true
+    }
+}
+
+impl Enemy {
+    pub fn new(pos: TilePos, pattern: AttackPattern) -> Enemy {
+        Enemy {
+            pos,
+            anim: sprite::entity("Enemy", Direction::Center),
+            pattern,
+            damage: default_damage(),
+            rest_turns: default_rest_turns(),
+            resting: 0,
+            attack: None,
+        }
+    }
+
+    /// Neither mid-attack nor resting
+    fn is_ready(&self) -> bool {
+        self.attack.is_none() && self.resting == 0
+    }
+}
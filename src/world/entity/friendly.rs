@@ -1,14 +1,23 @@
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
 use speedy2d::color::Color;
 
-use crate::{world::{space::{GamePos, Direction}, operation::PostOperation, minigame::smiley_win::SmileyWin}, draw::animation::Animation};
+use crate::{
+    draw::{animation::Animation, sprite},
+    world::{
+        command::Command,
+        minigame::smiley_win::SmileyWin,
+        operation::PostOperation,
+        space::{Direction, TilePos},
+    },
+};
 
-use super::{Entity, get_default_anim, utility::Button};
+use super::Entity;
 
 #[derive(Debug, Serialize, Deserialize)]
 /// Test thing. Don't let it escape lol
 pub struct MoveLeft {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
     should_move: bool,
 }
@@ -19,7 +28,7 @@ impl Entity for MoveLeft {
         Color::from_hex_argb(0xFF00FF00)
     }
 
-    fn moove(&mut self, change_pos: GamePos) {
+    fn moove(&mut self, change_pos: TilePos) {
         self.pos += change_pos;
     }
 
@@ -27,47 +36,45 @@ impl Entity for MoveLeft {
         &mut self.anim
     }
 
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
-
-    fn create(&self, pos: GamePos, _direction: Direction) -> Box<dyn Entity> {
-        Box::new(MoveLeft::new(pos))
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["should_move"]
+    }
+    fn has_turn_state(&self) -> bool {
+        true
     }
 
-    fn pick(&self) -> Box<dyn Entity> {
-        Box::new(MoveLeft::new(GamePos::origin()))
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(MoveLeft::new(pos))
     }
 
-    fn request_moves(&mut self, move_pos: &mut Vec<GamePos>, _player_pos: GamePos) {
+    fn request_moves(&mut self, move_pos: &mut Vec<TilePos>, _player_pos: TilePos) {
         if self.should_move {
             move_pos.extend([(-1, 0).into(), (0, 1).into(), (0, -1).into()].iter());
         }
     }
 
-    fn next (&self) -> Box<dyn Entity> {
-        Box::new(SmileyMan::new(GamePos::origin()))
-    }
-
-    fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
         // Player moving onto my tile means I am free to move to their tile. Default behavior for friendlies
         self.moove(-move_pos);
         self.should_move = false;
         PostOperation::new_empty()
     }
 
-    fn do_turn(&mut self) -> PostOperation {
+    fn do_turn(&mut self, _player_pos: TilePos) -> PostOperation {
         self.should_move = true;
         PostOperation::new_empty()
     }
 }
 
 impl MoveLeft {
-    pub fn new(pos: GamePos) -> MoveLeft {
+    pub fn new(pos: TilePos) -> MoveLeft {
         MoveLeft {
             pos,
-            anim: get_default_anim((0, 1)),
-            should_move: true
+            anim: sprite::entity("MoveLeft", Direction::Center),
+            should_move: true,
         }
     }
 }
@@ -75,7 +82,8 @@ impl MoveLeft {
 #[derive(Debug, Serialize, Deserialize)]
 /// Test thing. Don't let it escape lol
 pub struct SmileyMan {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
@@ -85,7 +93,7 @@ impl Entity for SmileyMan {
         Color::from_hex_argb(0xFF00FF00)
     }
 
-    fn moove(&mut self, change_pos: GamePos) {
+    fn moove(&mut self, change_pos: TilePos) {
         self.pos += change_pos;
     }
 
@@ -93,32 +101,79 @@ impl Entity for SmileyMan {
         &mut self.anim
     }
 
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
-    fn create(&self, pos: GamePos, _direction: Direction) -> Box<dyn Entity> {
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
         Box::new(SmileyMan::new(pos))
     }
 
-    fn pick(&self) -> Box<dyn Entity> {
-        Box::new(SmileyMan::new(GamePos::origin()))
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        PostOperation::new_empty()
+            .with_block_player(move_pos)
+            .with_minigame(Box::new(SmileyWin::new()))
     }
+}
 
-    fn next (&self) -> Box<dyn Entity> {
-        Box::new(Button::default())
+impl SmileyMan {
+    pub fn new(pos: TilePos) -> SmileyMan {
+        SmileyMan {
+            pos,
+            anim: sprite::entity("SmileyMan", Direction::Center),
+        }
     }
+}
 
-    fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
-        PostOperation::new_empty().with_block_player(move_pos).with_minigame(Box::new(SmileyWin::new()))
+/// Someone to talk to. Walking into them opens their dialogue
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Npc {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    /// Name of the file in `assets/dialogue` holding what they say
+    dialogue: String,
+}
+
+#[typetag::serde]
+impl Entity for Npc {
+    fn draw_color(&self) -> Color {
+        Color::from_hex_argb(0xFFFFC080)
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Npc::new(pos, self.dialogue.clone()))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        let op = PostOperation::new_empty().with_block_player(move_pos);
+        if self.dialogue.is_empty() || move_pos == TilePos::origin() {
+            return op;
+        }
+        op.with_command(Command::StartDialogue {
+            dialogue: self.dialogue.clone(),
+        })
     }
 }
 
-impl SmileyMan {
-    pub fn new(pos: GamePos) -> SmileyMan {
-        SmileyMan {
+impl Npc {
+    pub fn new(pos: TilePos, dialogue: String) -> Npc {
+        Npc {
             pos,
-            anim: get_default_anim((0, 2)),
+            anim: sprite::entity("Npc", Direction::Center),
+            dialogue,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,111 @@
+//! What each hat does. Hats are worn by the player and change how they move, fight and
+//! interact with tiles. Which ones the player has unlocked is saved with the player
+
+use crate::world::{command::Command, data::DataManager, space::TilePos, tile::Tile};
+
+use super::player::PlayerHat;
+
+/// Abilities a hat gives. Every hook defaults to how the player acts without a hat
+pub trait Hat {
+    /// Where the player ends up trying to step by `move_pos` from `from`. None if they can't move
+    fn step(&self, region: &DataManager, from: TilePos, move_pos: TilePos) -> Option<TilePos> {
+        walk(region, from + move_pos)
+    }
+
+    /// How much of a hit of `damage` the player takes
+    fn damage_taken(&self, damage: u32) -> u32 {
+        damage
+    }
+
+    /// Squares hit when the player moves into a creature, relative to the player facing up.
+    /// (0, -1) is the creature they moved into
+    fn attack_shape(&self) -> Vec<TilePos> {
+        vec![TilePos(0, -1)]
+    }
+
+    /// What happens when the player walks into `tile` but it blocks them
+    fn on_bump(&self, _tile: &dyn Tile) -> Vec<Command> {
+        Vec::new()
+    }
+
+    /// Pushes the row of pushable entities at `pos` one tile along `step` as the player walks into it.
+    /// Returns whether the row got out of the way. If it didn't the player is moved back
+    fn push(&self, region: &mut DataManager, pos: TilePos, step: TilePos) -> bool {
+        region.push_entities(pos, step)
+    }
+}
+
+impl PlayerHat {
+    pub fn ability(&self) -> &'static dyn Hat {
+        match self {
+            PlayerHat::None => &NoHat,
+            PlayerHat::Helmet => &Helmet,
+            PlayerHat::Acid => &Acid,
+            PlayerHat::Teardrop => &Teardrop,
+        }
+    }
+}
+
+/// `to` if the player can stand there
+fn walk(region: &DataManager, to: TilePos) -> Option<TilePos> {
+    match region.get_tile(to) {
+        Some(tile) if !tile.block_movement() => Some(to),
+        _ => None,
+    }
+}
+
+struct NoHat;
+
+impl Hat for NoHat {}
+
+/// Takes the edge off every hit
+struct Helmet;
+
+impl Hat for Helmet {
+    fn damage_taken(&self, damage: u32) -> u32 {
+        damage.saturating_sub(1)
+    }
+}
+
+/// Dissolves rock and boulders that won't budge, and splashes the squares either side of
+/// whatever the player attacks
+struct Acid;
+
+impl Hat for Acid {
+    fn attack_shape(&self) -> Vec<TilePos> {
+        vec![TilePos(-1, -1), TilePos(0, -1), TilePos(1, -1)]
+    }
+
+    fn on_bump(&self, tile: &dyn Tile) -> Vec<Command> {
+        if tile.dissolves_in_acid() {
+            vec![Command::DissolveTile {
+                pos: tile.get_pos(),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn push(&self, region: &mut DataManager, pos: TilePos, step: TilePos) -> bool {
+        if region.push_entities(pos, step) {
+            return true;
+        }
+        // Like rock, the boulder's gone but the player has to step in next turn
+        region.remove_entity_where(|entity| entity.get_pos() == pos && entity.is_pushable());
+        false
+    }
+}
+
+/// Hops over anything in the way, as long as there's somewhere to land
+struct Teardrop;
+
+impl Hat for Teardrop {
+    fn step(&self, region: &DataManager, from: TilePos, move_pos: TilePos) -> Option<TilePos> {
+        walk(region, from + move_pos).or_else(|| {
+            if move_pos == TilePos::origin() || region.get_tile(from + move_pos).is_none() {
+                return None;
+            }
+            walk(region, from + move_pos * 2)
+        })
+    }
+}
@@ -0,0 +1,182 @@
+//! Things the player carries. Items are picked up from [`Item`] entities, given and taken by commands,
+//! and saved with the player
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use speedy2d::color::Color;
+
+use crate::{
+    draw::{animation::Animation, sprite},
+    world::{
+        command::Command,
+        operation::PostOperation,
+        space::{Direction, TilePos},
+    },
+};
+
+use super::Entity;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ItemKind {
+    /// Vessel parts
+    Mast,
+    Engine,
+    Frame,
+    Aeronautics,
+    FlightComputer,
+    /// Opens a locked door. Used up when it does
+    Key,
+}
+
+impl ItemKind {
+    /// Unique items are held at most once. The rest stack
+    pub fn is_unique(self) -> bool {
+        self != ItemKind::Key
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemKind::Mast => "mast",
+            ItemKind::Engine => "engine",
+            ItemKind::Frame => "frame",
+            ItemKind::Aeronautics => "aeronautics",
+            ItemKind::FlightComputer => "flight computer",
+            ItemKind::Key => "key",
+        }
+    }
+
+    /// The name as it reads in a sentence, e.g. "the mast" or "a key"
+    pub fn with_article(self) -> String {
+        if self.is_unique() {
+            format!("the {}", self.name())
+        } else {
+            format!("a {}", self.name())
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            ItemKind::Key => Color::from_hex_argb(0xFFE0C040),
+            _ => Color::from_hex_argb(0xFF60C0E0),
+        }
+    }
+}
+
+/// How many of each item the player has. Items they have none of aren't stored
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Inventory {
+    items: BTreeMap<ItemKind, u32>,
+}
+
+impl Inventory {
+    /// Adds `count` of `item` and returns how many were actually added.
+    /// Unique items the player already has aren't added again
+    pub fn add(&mut self, item: ItemKind, count: u32) -> u32 {
+        let held = self.count(item);
+        let added = if !item.is_unique() {
+            count
+        } else if held == 0 {
+            count.min(1)
+        } else {
+            0
+        };
+        if added > 0 {
+            self.items.insert(item, held + added);
+        }
+        added
+    }
+
+    /// Takes away `count` of `item`. Returns false and leaves the inventory alone if there aren't that many
+    pub fn remove(&mut self, item: ItemKind, count: u32) -> bool {
+        let held = self.count(item);
+        if held < count {
+            return false;
+        }
+        if held == count {
+            self.items.remove(&item);
+        } else {
+            self.items.insert(item, held - count);
+        }
+        true
+    }
+
+    pub fn count(&self, item: ItemKind) -> u32 {
+        self.items.get(&item).copied().unwrap_or(0)
+    }
+
+    pub fn has(&self, item: ItemKind) -> bool {
+        self.count(item) > 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ItemKind, u32)> + '_ {
+        self.items.iter().map(|(&item, &count)| (item, count))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+/// An item lying on the ground. Picked up when the player steps on it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Item {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    item: ItemKind,
+    #[serde(default = "default_count")]
+    count: u32,
+}
+
+#[typetag::serde]
+impl Entity for Item {
+    fn draw_color(&self) -> Color {
+        self.item.color()
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Item::new(pos, self.item, self.count))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        if move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        PostOperation::new_empty().with_commands(vec![
+            Command::RemoveEntity { pos: self.pos },
+            Command::GiveItem {
+                item: self.item,
+                count: self.count,
+            },
+        ])
+    }
+}
+
+impl Item {
+    pub fn new(pos: TilePos, item: ItemKind, count: u32) -> Item {
+        Item {
+            pos,
+            anim: sprite::entity("Item", Direction::Center),
+            item,
+            count,
+        }
+    }
+}
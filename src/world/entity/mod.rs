@@ -1,39 +1,41 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::fmt::Debug;
 
-use speedy2d::{color::Color, Graphics2D};
+use speedy2d::color::Color;
 
 use crate::{
-    draw::{
-        animation::Animation,
-        ui::img::{Img, ImgManager},
-    },
+    draw::{animation::Animation, render::Renderer, sprite},
     screen::camera::Camera,
-    world::{space::GamePos, time::Clock},
+    world::{
+        space::{GamePos, TilePos},
+        time::Clock,
+    },
+};
+
+use super::{
+    operation::PostOperation,
+    signal::{Signals, Wiring},
+    space::{Direction, SPRITE_EXTENSION_HEIGHT},
 };
 
-use super::{operation::PostOperation, space::{SPRITE_EXTENSION_HEIGHT, Direction}};
+use self::enemy::Attack;
 
+pub mod enemy;
 pub mod friendly;
+pub mod hat;
+pub mod item;
 pub mod player;
 pub mod utility;
 
 #[typetag::serde(tag = "type")]
 pub trait Entity: Debug {
-    fn draw(
-        &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
-        clock: &Clock,
-        camera: &Camera,
-    ) {
+    fn draw(&mut self, renderer: &mut dyn Renderer, clock: &Clock, camera: &Camera) {
         let color = self.draw_color();
         let pos = self.get_pos();
         let (frame_size, offset) = self.get_frame_size_and_offset();
         self.get_anim_mut().draw_overworld(
-            graphics,
-            manager,
+            renderer,
             clock,
-            camera.rect_from_offset(pos, frame_size, offset),
+            camera.rect_from_offset(pos.into(), frame_size, offset),
             color,
         );
     }
@@ -46,50 +48,92 @@ pub trait Entity: Debug {
     fn draw_color(&self) -> Color {
         Color::YELLOW
     }
-    fn moove(&mut self, change_pos: GamePos);
+    fn moove(&mut self, change_pos: TilePos);
     fn get_anim_mut(&mut self) -> &mut Animation;
-    fn get_pos(&self) -> GamePos;
-    fn create(&self, pos: GamePos, direction: Direction) -> Box<dyn Entity>;
-    fn next(&self) -> Box<dyn Entity>;
-    fn cycle(&self) -> Box<dyn Entity> {
-        let next_entity = self.next();
-        println!("{}", format!("{:?}", next_entity).split_once(' ').unwrap().0);
-        return next_entity;
-    }
-    fn pick(&self) -> Box<dyn Entity>;
+    /// Which sprite of the entity type to draw. Only entities that face a direction have one
+    fn variant(&self) -> Direction {
+        Direction::Center
+    }
+    /// Looks the animation back up in the sprite table. Animations aren't saved so this runs on load
+    fn reset_anim(&mut self) {
+        *self.get_anim_mut() = sprite::entity(self.typetag_name(), self.variant());
+    }
+    fn get_pos(&self) -> TilePos;
+    /// An entity of the same type at `pos`, with the same properties. Used to draw with in the editor.
+    /// New types go in [`registry::ENTITIES`] so the editor lists them
+    ///
+    /// [`registry::ENTITIES`]: super::registry::ENTITIES
+    fn create(&self, pos: TilePos, direction: Direction) -> Box<dyn Entity>;
     fn update(&mut self) {}
     fn update_anim(&mut self, clock: &Clock) {
         self.get_anim_mut().select("base").unwrap();
     }
-    fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
         PostOperation::new_empty().with_block_player(move_pos)
     }
-    fn on_entity_enter(&mut self, move_pos: GamePos, index: usize) -> PostOperation {
+    fn on_entity_enter(&mut self, move_pos: TilePos, index: usize) -> PostOperation {
         PostOperation::new_empty()
     }
-    fn do_turn(&mut self) -> PostOperation {
+    fn do_turn(&mut self, _player_pos: TilePos) -> PostOperation {
         PostOperation::new_empty()
     }
+    /// The attack this entity has telegraphed. It lands on the player's next turn
+    fn attack(&self) -> Option<&Attack> {
+        None
+    }
+    /// Creatures are defeated when the player attacks them
+    fn is_creature(&self) -> bool {
+        false
+    }
+    /// Pushable entities are shoved along when the player walks into them, see [`Command::Push`]
+    ///
+    /// [`Command::Push`]: crate::world::command::Command::Push
+    fn is_pushable(&self) -> bool {
+        false
+    }
+    /// Region and position this entity sends the player to, if it's a warp
+    fn warp_target(&self) -> Option<(&String, TilePos)> {
+        None
+    }
+    /// Points a warp somewhere else. Does nothing for other entities
+    fn set_warp_target(&mut self, _region: String, _pos: TilePos) {}
+    /// Other cells in the region this entity acts on. They move along with it when it's copied and pasted
+    fn linked_positions_mut(&mut self) -> Vec<&mut TilePos> {
+        Vec::new()
+    }
+    /// Whether this signal source is putting out a signal this turn.
+    /// `occupied` is whether the player or an entity is standing on it
+    fn signal(&mut self, _clock: &Clock, _occupied: bool) -> bool {
+        false
+    }
+    /// Channel a signal source puts its signal out on. None if it isn't a source
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+    /// Channels a signal sink listens to. None if it isn't a sink
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        None
+    }
+    /// Saved fields that only change during play, like whether a plate is pressed.
+    /// The editor's inspector leaves them out
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Whether signals or its own turn can change what's saved of the entity.
+    /// These are kept before every turn so it can be undone
+    fn has_turn_state(&self) -> bool {
+        false
+    }
+    /// Called every turn with the channels that are on
+    fn receive(&mut self, signals: &Signals) {
+        if let Some(powered) = self.wiring_mut().and_then(|wiring| wiring.update(signals)) {
+            self.set_powered(powered);
+        }
+    }
+    /// What a signal sink does when its wiring powers it or stops powering it
+    fn set_powered(&mut self, _powered: bool) {}
     /// !Warning! Do NOT update state in this method as it is called multiple times per turn!
-    fn request_moves(&mut self, move_pos: &mut Vec<GamePos>, player_pos: GamePos){}
-}
-
-fn get_default_anim(frame: (u16, u16)) -> Animation {
-    let mut frames: HashMap<String, (bool, Vec<(u16, u16)>)> = HashMap::new();
-
-    frames.insert(String::from("base"), (true, vec![frame]));
-
-    anim_with_frames(frames)
-}
-
-fn anim_with_frames(frames: HashMap<String, (bool, Vec<(u16, u16)>)>) -> Animation {
-    Animation::new(
-        Img::new(String::from("assets/img/entities.png")),
-        (7, 10),
-        frames,
-        (5, 0),
-        100,
-    )
+    fn request_moves(&mut self, move_pos: &mut Vec<TilePos>, player_pos: TilePos) {}
 }
 
 fn square_anim_size() -> (GamePos, GamePos) {
@@ -1,20 +1,34 @@
-use std::collections::HashMap;
+use std::collections::BTreeSet;
 
 use speedy2d::color::Color;
 
 use crate::{
     draw::{
         animation::{Animation, AnimationSelectError},
-        ui::img::Img,
+        sprite,
+    },
+    world::{
+        space::{Direction, GamePos, TilePos},
+        time::Clock,
     },
-    world::{space::{GamePos, Direction}, time::Clock},
 };
 
 use serde::{Deserialize, Serialize};
 
-use super::Entity;
+use super::{item::Inventory, Entity};
+
+/// Hits the player can take before being defeated
+pub const MAX_HEALTH: u32 = 3;
+
+fn default_health() -> u32 {
+    MAX_HEALTH
+}
+
+fn default_hats() -> BTreeSet<PlayerHat> {
+    [PlayerHat::None].iter().copied().collect()
+}
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PlayerHat {
     None,
     Helmet,
@@ -24,34 +38,33 @@ pub enum PlayerHat {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Player {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip, default = "sprite::player")]
     anim: Animation,
     size: GamePos,
     hat: PlayerHat,
-    last_move_pos: GamePos,
+    last_move_pos: TilePos,
+    #[serde(default = "default_health")]
+    health: u32,
+    /// Hats the player has found and can switch between
+    #[serde(default = "default_hats")]
+    hats: BTreeSet<PlayerHat>,
+    #[serde(default)]
+    inventory: Inventory,
 }
 
 #[typetag::serde]
 impl Entity for Player {
-
     fn draw_color(&self) -> Color {
         Color::RED
     }
 
-    fn moove(&mut self, change_pos: GamePos) {
+    fn moove(&mut self, change_pos: TilePos) {
         self.pos += change_pos;
         self.last_move_pos = change_pos;
     }
 
-    fn create(&self, pos: GamePos, _direction: Direction) -> Box<dyn Entity> {
-        unreachable!()
-    }
-
-    fn pick(&self) -> Box<dyn Entity> {
-        unreachable!()
-    }
-
-    fn next(&self) -> Box<dyn Entity> {
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
         unreachable!()
     }
 
@@ -63,7 +76,7 @@ impl Entity for Player {
         &mut self.anim
     }
 
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -77,52 +90,90 @@ impl Entity for Player {
         }
     }
 
-    fn request_moves(&mut self, move_pos: &mut Vec<GamePos>, player_pos: GamePos) {
+    fn request_moves(&mut self, move_pos: &mut Vec<TilePos>, player_pos: TilePos) {
         unreachable!();
     }
 }
 
 impl Player {
     pub fn new() -> Player {
-        let mut frames: HashMap<String, (bool, Vec<(u16, u16)>)> = HashMap::new();
-
-        frames.insert(String::from("none"), (true, vec![(2, 3)]));
-
-        frames.insert(String::from("helmet"), (true, vec![(0, 0)]));
-
-        frames.insert(String::from("acid"), (true, vec![(2, 4)]));
-
-        frames.insert(String::from("teardrop"), (true, vec![(2, 2)]));
-
         Player {
-            pos: GamePos::origin(),
-            anim: Animation::new(
-                Img::new(String::from("assets/img/player.png")),
-                (7, 7),
-                frames,
-                (9, 0),
-                100,
-            ),
+            pos: TilePos::origin(),
+            anim: sprite::player(),
             size: (1, 1).into(),
             hat: PlayerHat::None,
-            last_move_pos: GamePos::origin()
+            last_move_pos: TilePos::origin(),
+            health: MAX_HEALTH,
+            hats: default_hats(),
+            inventory: Inventory::default(),
         }
     }
 
+    pub fn hat(&self) -> PlayerHat {
+        self.hat
+    }
+
     pub fn set_hat(&mut self, hat: PlayerHat) {
         self.hat = hat;
     }
 
+    /// Switches to the next unlocked hat
     pub fn cycle_hat(&mut self) {
-        self.hat = match self.hat {
-            PlayerHat::None => PlayerHat::Helmet,
-            PlayerHat::Helmet => PlayerHat::Acid,
-            PlayerHat::Acid => PlayerHat::Teardrop,
-            PlayerHat::Teardrop => PlayerHat::None,
-        }
+        let hat = self.hat;
+        self.hat = self
+            .hats
+            .range(hat..)
+            .find(|&&h| h != hat)
+            .or_else(|| self.hats.iter().next())
+            .copied()
+            .unwrap_or(PlayerHat::None);
+    }
+
+    /// Adds `hat` to the ones the player can switch between. False if they already had it
+    pub fn unlock_hat(&mut self, hat: PlayerHat) -> bool {
+        self.hats.insert(hat)
+    }
+
+    pub fn has_hat(&self, hat: PlayerHat) -> bool {
+        self.hats.contains(&hat)
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    /// Puts the player somewhere without counting it as a step
+    pub fn teleport(&mut self, pos: TilePos) {
+        self.pos = pos;
+        self.last_move_pos = TilePos::origin();
     }
 
-    pub fn get_last_move_pos(&self) -> GamePos {
+    /// Records that the player tried to move but didn't
+    pub fn stay(&mut self) {
+        self.last_move_pos = TilePos::origin();
+    }
+
+    pub fn get_last_move_pos(&self) -> TilePos {
         self.last_move_pos
     }
+
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    pub fn hurt(&mut self, damage: u32) {
+        self.health = self.health.saturating_sub(damage);
+    }
+
+    pub fn heal_fully(&mut self) {
+        self.health = MAX_HEALTH;
+    }
+
+    pub fn is_defeated(&self) -> bool {
+        self.health == 0
+    }
 }
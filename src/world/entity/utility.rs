@@ -1,26 +1,38 @@
+use std::mem;
+
 use serde::{Deserialize, Serialize};
 use speedy2d::color::Color;
 
 use crate::{
-    draw::animation::{self, Animation},
+    draw::{animation::Animation, sprite},
     world::{
+        command::Command,
         operation::PostOperation,
-        space::{Direction, GamePos},
+        signal::Wiring,
+        space::{Direction, TilePos},
+        time::Clock,
     },
 };
 
-use super::{friendly::MoveLeft, get_default_anim, Entity};
+use super::Entity;
 
+/// Signals for the turn it's pushed. Can also toggle a tile directly
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Button {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
-    effect_pos: GamePos,
+    #[serde(default)]
+    effect_pos: Option<TilePos>,
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    pressed: bool,
 }
 
 #[typetag::serde]
 impl Entity for Button {
-    fn moove(&mut self, change_pos: GamePos) {
+    fn moove(&mut self, change_pos: TilePos) {
         self.pos += change_pos;
     }
 
@@ -28,65 +40,203 @@ impl Entity for Button {
         &mut self.anim
     }
 
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["pressed"]
+    }
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Button {
+            effect_pos: self.effect_pos,
+            channel: self.channel.clone(),
+            ..Button::new(pos)
+        })
+    }
 
-    fn create(&self, pos: GamePos, _direction: Direction) -> Box<dyn Entity> {
-        Box::new(Button::new(pos))
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        if move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        self.pressed = true;
+        let op = PostOperation::new_empty().with_block_player(move_pos);
+        match self.effect_pos {
+            Some(pos) => op.with_command(Command::ToggleTile { pos }),
+            None => op,
+        }
     }
 
-    fn pick(&self) -> Box<dyn Entity> {
-        Box::new(Button::new(GamePos::origin()))
+    fn signal(&mut self, _clock: &Clock, _occupied: bool) -> bool {
+        mem::take(&mut self.pressed)
     }
 
-    fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
-        let effect_pos = self.effect_pos;
-        PostOperation::new_empty()
-            .with_block_player(move_pos)
-            .with_custom(move |w, p| {
-                if let Some((_, tile)) = &mut w.mgr.get_tile_at_pos(effect_pos) {
-                    tile.change_self();
-                }
-            })
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.channel)
     }
 
-    fn next(&self) -> Box<dyn Entity> {
-        Box::new(OneWay::new(GamePos::origin(), Direction::Center))
+    fn linked_positions_mut(&mut self) -> Vec<&mut TilePos> {
+        self.effect_pos.iter_mut().collect()
     }
 }
 
 impl Button {
-    pub fn new(pos: GamePos) -> Button {
-        let mut x = String::new();
-        println!("Enter the x of the tile to be affected by the button: ");
-        std::io::stdin().read_line(&mut x).unwrap();
-        let mut y = String::new();
-        println!("Enter the y of the tile to be affected by the button: ");
-        std::io::stdin().read_line(&mut y).unwrap();
-        let effect_pos = (
-            x.trim().parse::<i32>().unwrap_or_default(),
-            y.trim().parse::<i32>().unwrap_or_default(),
-        )
-            .into();
+    pub fn new(pos: TilePos) -> Button {
         Button {
             pos,
-            anim: get_default_anim((0, 0)),
-            effect_pos,
+            anim: sprite::entity("Button", Direction::Center),
+            effect_pos: None,
+            channel: String::new(),
+            pressed: false,
         }
     }
-    pub fn default() -> Button {
+    /// A button that toggles the tile at `effect_pos`
+    pub fn with_effect(pos: TilePos, effect_pos: TilePos) -> Button {
+        Button {
+            effect_pos: Some(effect_pos),
+            ..Button::new(pos)
+        }
+    }
+    /// A button that signals on `channel`
+    pub fn on_channel(pos: TilePos, channel: String) -> Button {
         Button {
-            pos: GamePos::origin(),
-            anim: get_default_anim((2, 4)),
-            effect_pos: GamePos::origin(),
+            channel,
+            ..Button::new(pos)
+        }
+    }
+    pub fn default() -> Button {
+        Button::new(TilePos::origin())
+    }
+}
+
+/// Flipped on or off by pushing it, and signals for as long as it's on
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Lever {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    on: bool,
+}
+
+#[typetag::serde]
+impl Entity for Lever {
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Lever::on_channel(pos, self.channel.clone()))
+    }
+
+    fn update_anim(&mut self, _clock: &Clock) {
+        self.anim
+            .select(if self.on { "on" } else { "base" })
+            .unwrap();
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        if move_pos != TilePos::origin() {
+            self.on = !self.on;
+        }
+        PostOperation::new_empty().with_block_player(move_pos)
+    }
+
+    fn signal(&mut self, _clock: &Clock, _occupied: bool) -> bool {
+        self.on
+    }
+
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.channel)
+    }
+}
+
+impl Lever {
+    pub fn new(pos: TilePos) -> Lever {
+        Lever::on_channel(pos, String::new())
+    }
+    pub fn on_channel(pos: TilePos, channel: String) -> Lever {
+        Lever {
+            pos,
+            anim: sprite::entity("Lever", Direction::Center),
+            channel,
+            on: false,
+        }
+    }
+}
+
+/// Pushed a tile along when the player walks into it, if there's room behind it.
+/// A row of boulders is pushed all at once. Not the same as the mountain boulder tile, which only blocks
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushBoulder {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+}
+
+#[typetag::serde]
+impl Entity for PushBoulder {
+    fn draw_color(&self) -> Color {
+        Color::from_hex_argb(0xFFB09A80)
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(PushBoulder::new(pos))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        if move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        PostOperation::new_empty().with_command(Command::Push {
+            pos: self.pos,
+            by: move_pos,
+        })
+    }
+
+    fn is_pushable(&self) -> bool {
+        true
+    }
+}
+
+impl PushBoulder {
+    pub fn new(pos: TilePos) -> PushBoulder {
+        PushBoulder {
+            pos,
+            anim: sprite::entity("PushBoulder", Direction::Center),
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OneWay {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
     direction: Direction,
 }
@@ -97,7 +247,7 @@ impl Entity for OneWay {
         Color::from_hex_argb(0xFFAAAAAA)
     }
 
-    fn moove(&mut self, change_pos: GamePos) {
+    fn moove(&mut self, change_pos: TilePos) {
         self.pos += change_pos;
     }
 
@@ -105,40 +255,194 @@ impl Entity for OneWay {
         &mut self.anim
     }
 
-    fn get_pos(&self) -> GamePos {
-        self.pos
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, direction: Direction) -> Box<dyn Entity> {
-        Box::new(OneWay::new(pos, direction))
+    fn get_pos(&self) -> TilePos {
+        self.pos
     }
 
-    fn pick(&self) -> Box<dyn Entity> {
-        Box::new(OneWay::new(GamePos::origin(), Direction::Center))
+    fn create(&self, pos: TilePos, direction: Direction) -> Box<dyn Entity> {
+        Box::new(OneWay::new(pos, direction))
     }
 
-    fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
-        let direction = self.direction;
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        let dir_vec = self.direction.direction_vector();
         PostOperation::new_empty().with_block_when(
-            move |_| {
-                let dir_vec = direction.direction_vector();
-                ((dir_vec.x * move_pos.x) < 0.) || ((dir_vec.y * move_pos.y) < 0.)
-            },
+            ((dir_vec.0 * move_pos.0) < 0) || ((dir_vec.1 * move_pos.1) < 0),
             move_pos,
         )
     }
-
-    fn next(&self) -> Box<dyn Entity> {
-        Box::new(MoveLeft::new(GamePos::origin()))
-    }
 }
 
 impl OneWay {
-    pub fn new(pos: GamePos, direction: Direction) -> OneWay {
+    pub fn new(pos: TilePos, direction: Direction) -> OneWay {
         OneWay {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (2, 1))),
+            anim: sprite::entity("OneWay", direction),
             direction,
         }
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Warp {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    target_region: String,
+    target_pos: TilePos,
+    /// Only warps while powered, for warps wired up to something
+    #[serde(default)]
+    wiring: Wiring,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[typetag::serde]
+impl Entity for Warp {
+    fn draw_color(&self) -> Color {
+        if self.disabled {
+            Color::from_hex_argb(0xFF808080)
+        } else {
+            Color::from_hex_argb(0xFFAA66FF)
+        }
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["disabled"]
+    }
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Warp {
+            wiring: self.wiring.copy_links(),
+            ..Warp::new(pos, self.target_region.clone(), self.target_pos)
+        })
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        // Only warp on the step onto the warp, not every turn the player stands here
+        if self.target_region.is_empty() || self.disabled || move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        PostOperation::new_empty().with_warp(self.target_region.clone(), self.target_pos)
+    }
+
+    fn warp_target(&self) -> Option<(&String, TilePos)> {
+        Some((&self.target_region, self.target_pos))
+    }
+
+    fn set_warp_target(&mut self, region: String, pos: TilePos) {
+        self.target_region = region;
+        self.target_pos = pos;
+    }
+
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        Some(&mut self.wiring)
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        self.disabled = !powered;
+    }
+}
+
+impl Warp {
+    pub fn new(pos: TilePos, target_region: String, target_pos: TilePos) -> Warp {
+        Warp {
+            pos,
+            anim: sprite::entity("Warp", Direction::Center),
+            target_region,
+            target_pos,
+            wiring: Wiring::default(),
+            disabled: false,
+        }
+    }
+    /// A warp at `pos` that doesn't lead anywhere yet
+    pub fn unlinked(pos: TilePos) -> Warp {
+        Warp::new(pos, String::new(), TilePos::origin())
+    }
+    /// A warp that only works while `wiring` powers it
+    pub fn wired(pos: TilePos, target_region: String, target_pos: TilePos, wiring: Wiring) -> Warp {
+        Warp {
+            wiring,
+            ..Warp::new(pos, target_region, target_pos)
+        }
+    }
+}
+
+/// Runs commands written into the region file when the player steps on it. Doesn't block the player
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Trigger {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    #[serde(default)]
+    on_enter: Vec<Command>,
+    /// Only goes off the first time it's stepped on
+    #[serde(default)]
+    once: bool,
+    #[serde(default)]
+    fired: bool,
+}
+
+#[typetag::serde]
+impl Entity for Trigger {
+    fn draw_color(&self) -> Color {
+        Color::from_hex_argb(0x8066CCFF)
+    }
+
+    fn moove(&mut self, change_pos: TilePos) {
+        self.pos += change_pos;
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["fired"]
+    }
+
+    fn create(&self, pos: TilePos, _direction: Direction) -> Box<dyn Entity> {
+        Box::new(Trigger::new(pos, self.on_enter.clone(), self.once))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        // Standing still on a trigger doesn't set it off again
+        if move_pos == TilePos::origin() || (self.once && self.fired) {
+            return PostOperation::new_empty();
+        }
+        self.fired = true;
+        PostOperation::new_empty().with_commands(self.on_enter.clone())
+    }
+}
+
+impl Trigger {
+    pub fn new(pos: TilePos, on_enter: Vec<Command>, once: bool) -> Trigger {
+        Trigger {
+            pos,
+            anim: sprite::entity("Trigger", Direction::Center),
+            on_enter,
+            once,
+            fired: false,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Edits made in the level editor, so they can be undone and redone. Only lasts for the session,
+//! and keeps edits from every region visited
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::space::TilePos;
+
+/// Most edits that can be undone
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Tile,
+    Entity,
+}
+
+/// What was in one layer of a cell before and after an edit. Kept saved so it can be put back exactly
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub pos: TilePos,
+    pub layer: Layer,
+    pub before: Vec<Value>,
+    pub after: Vec<Value>,
+}
+
+/// One action in the editor, undone all at once
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    /// Cells drawn over or erased in `region`, in the order they changed
+    Cells {
+        region: String,
+        changes: Vec<Change>,
+    },
+    /// A property of the item being drawn, which was a `kind`
+    Property {
+        kind: String,
+        key: String,
+        before: Value,
+        after: Value,
+    },
+}
+
+impl Edit {
+    /// What the edit did, for the editor's status bar
+    pub fn describe(&self) -> String {
+        match self {
+            Edit::Cells { changes, .. } if changes.len() == 1 => String::from("1 cell"),
+            Edit::Cells { changes, .. } => format!("{} cells", changes.len()),
+            Edit::Property { key, .. } => key.clone(),
+        }
+    }
+}
+
+/// Cells changed since the mouse went down, which become one edit when it comes back up
+struct Stroke {
+    region: String,
+    changes: Vec<Change>,
+    /// Where each cell's change is in `changes`, so big fills don't search through them all
+    index: HashMap<(TilePos, Layer), usize>,
+}
+
+#[derive(Default)]
+pub struct History {
+    done: Vec<Edit>,
+    undone: Vec<Edit>,
+    stroke: Option<Stroke>,
+}
+
+impl History {
+    /// Remembers an edit. Edits that were undone can't be redone after this
+    pub fn record(&mut self, edit: Edit) {
+        self.end_stroke();
+        self.push(edit);
+    }
+
+    fn push(&mut self, edit: Edit) {
+        if self.done.len() == HISTORY_LIMIT {
+            self.done.remove(0);
+        }
+        self.done.push(edit);
+        self.undone.clear();
+    }
+
+    /// Adds a change in `region` to the stroke being drawn, starting one if needed.
+    /// A cell changed twice in a stroke keeps what it had before the first change
+    pub fn change(&mut self, region: &str, change: Change) {
+        if matches!(&self.stroke, Some(stroke) if stroke.region != region) {
+            self.end_stroke();
+        }
+        let stroke = self.stroke.get_or_insert_with(|| Stroke {
+            region: region.to_string(),
+            changes: Vec::new(),
+            index: HashMap::new(),
+        });
+        match stroke.index.get(&(change.pos, change.layer)) {
+            Some(&earlier) => stroke.changes[earlier].after = change.after,
+            None => {
+                stroke
+                    .index
+                    .insert((change.pos, change.layer), stroke.changes.len());
+                stroke.changes.push(change);
+            }
+        }
+    }
+
+    /// Turns the stroke being drawn into an edit, unless it left everything as it was
+    pub fn end_stroke(&mut self) {
+        if let Some(Stroke {
+            region,
+            mut changes,
+            ..
+        }) = self.stroke.take()
+        {
+            changes.retain(|change| change.before != change.after);
+            if !changes.is_empty() {
+                self.push(Edit::Cells { region, changes });
+            }
+        }
+    }
+
+    /// The edit that would be undone next
+    pub fn last_done(&self) -> Option<&Edit> {
+        self.done.last()
+    }
+
+    /// The edit that would be redone next
+    pub fn last_undone(&self) -> Option<&Edit> {
+        self.undone.last()
+    }
+
+    /// Moves the last edit over to be redone, once it's been undone
+    pub fn step_back(&mut self) {
+        if let Some(edit) = self.done.pop() {
+            self.undone.push(edit);
+        }
+    }
+
+    /// Moves the last undone edit back, once it's been redone
+    pub fn step_forward(&mut self) {
+        if let Some(edit) = self.undone.pop() {
+            self.done.push(edit);
+        }
+    }
+
+    /// Points edits made in region `old` at `new`, once it's been renamed
+    pub fn rename_region(&mut self, old: &str, new: &str) {
+        let edits = self.done.iter_mut().chain(self.undone.iter_mut());
+        for edit in edits {
+            if let Edit::Cells { region, .. } = edit {
+                if region == old {
+                    *region = new.to_string();
+                }
+            }
+        }
+        if let Some(stroke) = &mut self.stroke {
+            if stroke.region == old {
+                stroke.region = new.to_string();
+            }
+        }
+    }
+
+    /// Forgets edits made in a region that's been deleted
+    pub fn forget_region(&mut self, name: &str) {
+        let kept = |edit: &Edit| !matches!(edit, Edit::Cells { region, .. } if region == name);
+        self.done.retain(kept);
+        self.undone.retain(kept);
+        if matches!(&self.stroke, Some(stroke) if stroke.region == name) {
+            self.stroke = None;
+        }
+    }
+
+    /// Number of edits that can be undone
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+}
@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    save::{self, SaveError},
+    space::TilePos,
+};
+
+/// File names in the saves folder that regions can't use
+const RESERVED_NAMES: [&str; 2] = ["save", "world"];
+
+/// Name of the manifest file inside the saves folder
+const MANIFEST_FILE: &str = "world.json";
+
+/// Where regions and the manifest are saved. `saves/` in the working directory unless set with [`use_saves_dir`]
+static SAVES_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum RegionError {
+    Io(io::Error),
+    /// A region or manifest file couldn't be read or written
+    Save(SaveError),
+    /// Empty, reserved or containing characters that don't belong in a file name
+    InvalidName(String),
+    AlreadyExists(String),
+    NotFound(String),
+    /// The region is the one currently loaded
+    InUse(String),
+    /// Other regions have warps leading into the region
+    Linked {
+        region: String,
+        from: Vec<String>,
+    },
+}
+
+impl From<io::Error> for RegionError {
+    fn from(e: io::Error) -> Self {
+        RegionError::Io(e)
+    }
+}
+
+impl From<SaveError> for RegionError {
+    fn from(e: SaveError) -> Self {
+        RegionError::Save(e)
+    }
+}
+
+/// A warp from one region into another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarpLink {
+    /// Where the warp sits in its own region
+    pub from: TilePos,
+    pub region: String,
+    pub to: TilePos,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegionMetadata {
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegionInfo {
+    pub spawn: TilePos,
+    #[serde(default)]
+    pub links: Vec<WarpLink>,
+    #[serde(default)]
+    pub metadata: RegionMetadata,
+}
+
+/// Something wrong with the world found by [`Manifest::validate`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestProblem {
+    /// The region is listed but its file is gone
+    MissingFile(String),
+    /// A warp leads to a region that isn't listed
+    MissingTarget { region: String, link: WarpLink },
+}
+
+/// Lists every region of the world along with its spawn point, warps and metadata.
+/// Stored in `saves/world.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    regions: BTreeMap<String, RegionInfo>,
+}
+
+impl Manifest {
+    pub fn new() -> Manifest {
+        Manifest {
+            regions: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the manifest from disk.
+    /// Worlds from before the manifest existed get one built from the region files in the saves folder
+    pub fn load() -> Result<Manifest, RegionError> {
+        let path = saves_dir().join(MANIFEST_FILE);
+        if path.exists() {
+            return Ok(save::read(&path)?);
+        }
+
+        let mut result = Manifest::new();
+        for entry in fs::read_dir(saves_dir())? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                if check_name(name).is_ok() {
+                    result.register(name.to_string(), TilePos::origin());
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn save(&self) -> Result<(), RegionError> {
+        Ok(save::write_pretty(&saves_dir().join(MANIFEST_FILE), self)?)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&String, &RegionInfo)> {
+        self.regions.iter()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.regions.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegionInfo> {
+        self.regions.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut RegionInfo> {
+        self.regions.get_mut(name)
+    }
+
+    /// Adds a region if it isn't listed yet
+    pub fn register(&mut self, name: String, spawn: TilePos) -> &mut RegionInfo {
+        self.regions.entry(name).or_insert_with(|| RegionInfo {
+            spawn,
+            ..RegionInfo::default()
+        })
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<RegionInfo> {
+        self.regions.remove(name)
+    }
+
+    /// Moves a region's entry to a new name and points every link into it at the new name
+    pub fn rename(&mut self, old: &str, new: String) {
+        if let Some(info) = self.regions.remove(old) {
+            self.regions.insert(new.clone(), info);
+        }
+        for info in self.regions.values_mut() {
+            for link in info.links.iter_mut().filter(|l| l.region == old) {
+                link.region = new.clone();
+            }
+        }
+    }
+
+    /// Names of the other regions with warps leading into `name`
+    pub fn linked_from(&self, name: &str) -> Vec<String> {
+        self.regions
+            .iter()
+            .filter(|(from, info)| {
+                from.as_str() != name && info.links.iter().any(|l| l.region == name)
+            })
+            .map(|(from, _)| from.clone())
+            .collect()
+    }
+
+    /// Checks that every region has a file and every warp leads somewhere that exists
+    pub fn validate(&self) -> Vec<ManifestProblem> {
+        let mut problems = Vec::new();
+        for (name, info) in &self.regions {
+            if !region_path(name).exists() {
+                problems.push(ManifestProblem::MissingFile(name.clone()));
+            }
+            for link in &info.links {
+                if !self.contains(&link.region) {
+                    problems.push(ManifestProblem::MissingTarget {
+                        region: name.clone(),
+                        link: link.clone(),
+                    });
+                }
+            }
+        }
+        problems
+    }
+}
+
+/// Makes sure `name` can be used as a region name
+pub fn check_name(name: &str) -> Result<(), RegionError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if name.is_empty() || !valid_chars || RESERVED_NAMES.contains(&name) {
+        return Err(RegionError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Location of the file a region is saved to
+pub fn region_path(name: &str) -> PathBuf {
+    saves_dir().join(format!("{}.json", name))
+}
+
+/// Location of the file a prefab is saved to. Prefabs get their own folder so they aren't taken for regions
+pub fn prefab_path(name: &str) -> PathBuf {
+    saves_dir().join("prefabs").join(format!("{}.json", name))
+}
+
+/// Keeps regions somewhere other than `saves/`, e.g. so tests don't touch the real saves.
+/// Only works before anything has been saved or loaded. Returns false if it's too late
+pub fn use_saves_dir(dir: PathBuf) -> bool {
+    SAVES_DIR.set(dir).is_ok()
+}
+
+fn saves_dir() -> PathBuf {
+    let path = SAVES_DIR.get_or_init(|| env::current_dir().unwrap().join("saves/"));
+    if !path.exists() {
+        fs::create_dir_all(path).unwrap();
+    }
+    path.clone()
+}
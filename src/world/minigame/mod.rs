@@ -1,6 +1,8 @@
-use speedy2d::{window::VirtualKeyCode, Graphics2D};
+use std::fmt::Debug;
 
-use crate::{draw::ui::img::ImgManager, screen::camera::Camera};
+use speedy2d::window::VirtualKeyCode;
+
+use crate::{draw::render::Renderer, screen::camera::Camera};
 
 pub mod smiley_win;
 
@@ -11,10 +13,10 @@ pub enum GameResult {
 }
 
 #[typetag::serde(tag = "type")]
-pub trait Minigame {
+pub trait Minigame: Debug {
     fn update(&mut self) -> GameResult;
 
-    fn draw(&mut self, graphics: &mut Graphics2D, manager: &mut ImgManager, camera: &Camera);
+    fn draw(&mut self, renderer: &mut dyn Renderer, camera: &Camera);
 
     fn key_down(&mut self, key: &VirtualKeyCode);
 
@@ -26,3 +28,9 @@ pub trait Minigame {
 
     fn create(&self) -> Box<dyn Minigame>;
 }
+
+impl Clone for Box<dyn Minigame> {
+    fn clone(&self) -> Self {
+        self.create()
+    }
+}
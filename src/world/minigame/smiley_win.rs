@@ -1,12 +1,9 @@
 use serde::{Deserialize, Serialize};
-use speedy2d::{shape::Rectangle, window::VirtualKeyCode, Graphics2D};
+use speedy2d::{shape::Rectangle, window::VirtualKeyCode};
 
 use crate::{
-    draw::{
-        animation::Animation,
-        ui::img::{Img, ImgManager},
-    },
-    screen::{self, camera::Camera},
+    draw::{animation::Animation, render::Renderer, sprite},
+    screen::camera::Camera,
     utility::time::NInstant,
 };
 
@@ -14,7 +11,9 @@ use super::{GameResult, Minigame};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SmileyWin {
+    #[serde(skip, default = "NInstant::now")]
     start: NInstant,
+    #[serde(skip, default = "sprite::smiley_win")]
     anim: Animation,
 }
 
@@ -22,11 +21,7 @@ impl SmileyWin {
     pub fn new() -> SmileyWin {
         SmileyWin {
             start: NInstant::now(),
-            anim: Animation::still(
-                Img::new(String::from("assets/img/smile.png")),
-                (400, 400),
-                (0, 0),
-            ),
+            anim: sprite::smiley_win(),
         }
     }
 }
@@ -40,11 +35,10 @@ impl Minigame for SmileyWin {
         GameResult::Processing
     }
 
-    fn draw(&mut self, graphics: &mut Graphics2D, manager: &mut ImgManager, camera: &Camera) {
-        let res = screen::get_resolution();
+    fn draw(&mut self, renderer: &mut dyn Renderer, camera: &Camera) {
+        let res = renderer.resolution();
         self.anim.draw(
-            graphics,
-            manager,
+            renderer,
             Rectangle::from_tuples((50., 50.), ((res.0 - 50) as f32, (res.1 - 50) as f32)),
         );
     }
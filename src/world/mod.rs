@@ -1,125 +1,295 @@
 use crate::{
     draw::{
+        render::Renderer,
         screen::{self, camera::Camera},
-        ui::img::ImgManager,
     },
     utility::key::match_wasd_directions,
-    world::{entity::player::Player, space::GamePos, tile::Tile, time::Clock},
+    world::{
+        entity::player::{Player, MAX_HEALTH},
+        space::{GamePos, TilePos},
+        tile::Tile,
+        time::Clock,
+    },
 };
 
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use speedy2d::{
+    color::Color,
+    shape::Rectangle,
     window::{MouseButton, VirtualKeyCode, WindowHelper},
-    Graphics2D,
 };
 
 use self::{
+    command::Command,
     data::DataManager,
-    entity::{utility::Button, Entity},
+    dialogue::{Conversation, Dialogue},
+    editor::{
+        DrawItem, Editor, Prompt, PromptKind, Tool, MOUSE_LEFT, MOUSE_MID, MOUSE_OTHER, MOUSE_RIGHT,
+    },
+    entity::Entity,
+    history::{Change, Edit, Layer},
+    manifest::{Manifest, RegionError},
     minigame::{GameResult, Minigame},
     operation::PostOperation,
-    tile::core::BaseGround, space::Direction,
+    prefab::Prefab,
+    space::Direction,
+    story::{Condition, StoryState},
+    undo::{Turn, UndoStack},
 };
 
+pub mod chunk;
+pub mod command;
 pub mod data;
+pub mod dialogue;
+pub mod editor;
 pub mod entity;
 pub mod generation;
+pub mod history;
+pub mod manifest;
 pub mod minigame;
 pub mod operation;
+pub mod prefab;
+pub mod registry;
+pub mod save;
+pub mod signal;
+pub mod sim;
 pub mod space;
+pub mod story;
 pub mod tile;
 pub mod time;
+pub mod undo;
 
+/// Everything in the game. Only the persistent state is saved, see [`save`] for the file format
 #[derive(Serialize, Deserialize)]
 pub struct World {
     pub mgr: DataManager,
     pub player: Player,
-    pub camera: Camera,
     pub clock: Clock,
     pub minigame: Option<Box<dyn Minigame>>,
-    // For editing
-    draw_item: DrawItem,
-    editor_direction: Direction,
+    /// The conversation the player is in, if any
+    #[serde(default)]
+    pub dialogue: Option<Conversation>,
+    /// Flags, counters and quest stages set as the player makes their way through the story
+    #[serde(default)]
+    pub story: StoryState,
+    /// Whatever hasn't run yet of the turn the game was saved on
+    #[serde(default)]
     post_ops: Vec<PostOperation>,
-    mouse_buttons: u8,
+    /// Where the player goes back to when defeated
+    #[serde(default)]
+    pub checkpoint: Option<RespawnPoint>,
+    // Rebuilt every session
+    #[serde(skip)]
+    pub camera: Camera,
+    /// Every region in the world. Lives in its own file, see [`World::load_manifest`]
+    #[serde(skip)]
+    pub manifest: Manifest,
+    #[serde(skip)]
+    pub editor: Editor,
+    /// Turns taken in the current region, for taking them back
+    #[serde(skip)]
+    undo: UndoStack,
+    /// Shown on screen until the player's next turn
+    #[serde(skip)]
+    message: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
-enum DrawItem {
-    Tile(Box<dyn Tile>),
-    Entity(Box<dyn Entity>),
+/// Somewhere the player can respawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RespawnPoint {
+    pub region: String,
+    pub pos: TilePos,
 }
-impl DrawItem {
-    fn default() -> DrawItem {
-        DrawItem::Tile(Box::new(BaseGround::default(GamePos::origin())))
-    }
-}
-
-const MOUSE_LEFT: u8 = 0b10000000;
-const MOUSE_RIGHT: u8 = 0b01000000;
-const MOUSE_MID: u8 = 0b00100000;
-/// No bitflag value so it doesn't affect anything
-const MOUSE_OTHER: u8 = 0b00000000;
 
 const VIEW_DIST: f32 = 40.0;
 
+/// Most cells the editor's fill tool changes at once
+const FILL_LIMIT: usize = 4096;
+
+/// Height of the box messages are shown in. Measured in pixels
+const MESSAGE_HEIGHT: f32 = 80.0;
+const MESSAGE_FONT_SIZE: f32 = 32.0;
+
+/// Size and spacing of the health shown in the top left. Measured in pixels
+const HEALTH_SIZE: f32 = 16.0;
+const HEALTH_MARGIN: f32 = 8.0;
+
+/// Size of the text listing the player's items, under the health. Measured in pixels
+const INVENTORY_FONT_SIZE: f32 = 16.0;
+
+/// Height of the box conversations are shown in. Measured in pixels
+const DIALOGUE_HEIGHT: f32 = 160.0;
+const DIALOGUE_FONT_SIZE: f32 = 20.0;
+
 impl World {
     pub fn new() -> World {
         World {
             mgr: DataManager::new(String::from("start")),
             player: Player::new(),
-            camera: Camera::new(GamePos::origin(), 10.0, 10.0),
             clock: Clock::new(),
             minigame: None,
-            draw_item: DrawItem::default(),
-            editor_direction: Direction::Top,
+            dialogue: None,
+            story: StoryState::default(),
             post_ops: Vec::new(),
-            mouse_buttons: 0,
+            checkpoint: None,
+            camera: Camera::default(),
+            manifest: Manifest::new(),
+            editor: Editor::default(),
+            undo: UndoStack::default(),
+            message: None,
         }
     }
 
     pub fn update_overworld(&mut self) {
         // Player moved ✅
         // Tile checked ✅
+        // Player enter tile ⬇️
         // Player enter entity ⬇️
+        // Execute postops ⬇️
+        // Attacks land ⬇️
         // Entity turn ⬇️
         // Execute postops ⬇️
+        // Signals pass along wires ⬇️
         // Camera moves ⬇️
         // Update anims ⬇️
         // Tick clock ⬇️
 
+        self.message = None;
+
+        // Player enter tile
+        let move_pos = self.player.get_last_move_pos();
+        if let Some(tile) = self.mgr.get_tile_at_pos(self.player.get_pos()) {
+            self.post_ops.push(tile.on_player_enter(move_pos));
+        }
+
         // Player enter entity
-        if let Some((_, entity)) = self.mgr.get_entity_at_pos(self.player.get_pos()) {
-            self.post_ops
-                .push(entity.on_player_enter(self.player.get_last_move_pos()));
+        if let Some((index, entity)) = self.mgr.get_entity_at_pos(self.player.get_pos()) {
+            let move_pos = self.player.get_last_move_pos();
+            if entity.is_creature() {
+                self.post_ops.push(self.hat_strikes(move_pos));
+            }
+            if let Some(op) = self
+                .mgr
+                .update_entity(index, |entity| entity.on_player_enter(move_pos))
+            {
+                self.post_ops.push(op);
+            }
         }
 
+        // Execute postops, so the player is where they ended up before anything attacks
+        self.execute_post_ops();
+
+        // Attacks land
+        self.resolve_attacks();
+
         // Entity turn
-        self.post_ops.extend(self.mgr.do_entity_turn(self.player.get_pos()));
+        self.post_ops
+            .extend(self.mgr.do_entity_turn(self.player.get_pos()));
 
         // Execute postops
-        while let Some(op) = self.post_ops.pop() {
-            op.execute(self);
-        }
+        self.execute_post_ops();
+
+        // Signals pass along wires
+        self.mgr.update_signals(&self.clock, self.player.get_pos());
 
         // Camera moves
-        self.camera.pos = self.player.get_pos();
+        self.camera.pos = self.player.get_pos().into();
 
         // Update anims & tick clock
         self.update_anims();
         self.clock.tick();
     }
 
+    /// Hits everything in the hat's attack shape, after the player moved by `move_pos` into a creature
+    fn hat_strikes(&self, move_pos: TilePos) -> PostOperation {
+        let from = self.player.get_pos() - move_pos;
+        let facing = Direction::from(move_pos);
+        PostOperation::new_empty().with_commands(
+            self.player
+                .hat()
+                .ability()
+                .attack_shape()
+                .into_iter()
+                .map(|offset| Command::Strike {
+                    pos: from + offset.rotated(facing),
+                }),
+        )
+    }
+
+    fn execute_post_ops(&mut self) {
+        while let Some(op) = self.post_ops.pop() {
+            op.execute(self);
+        }
+    }
+
+    /// Hits the player with every telegraphed attack covering where they're standing
+    fn resolve_attacks(&mut self) {
+        let damage = self.mgr.attack_damage_at(self.player.get_pos());
+        let damage = self.player.hat().ability().damage_taken(damage);
+        if damage == 0 {
+            return;
+        }
+        self.player.hurt(damage);
+        if self.player.is_defeated() {
+            self.post_ops
+                .push(PostOperation::new_empty().with_command(Command::DefeatPlayer));
+        }
+    }
+
+    /// Saves the current region and makes `pos` in it the respawn point
+    pub fn set_checkpoint(&mut self, pos: TilePos) {
+        self.save_region();
+        let point = RespawnPoint {
+            region: self.mgr.get_name().clone(),
+            pos,
+        };
+        if self.checkpoint.as_ref() != Some(&point) {
+            self.show_text(String::from("Checkpoint reached"));
+        }
+        self.checkpoint = Some(point);
+    }
+
+    /// Puts the player back at the last checkpoint with full health, reloading its region so
+    /// anything that happened since is undone. Without a checkpoint, or if its region can't be loaded,
+    /// it's the current region's spawn
+    pub fn respawn(&mut self) {
+        // Taking back the turn the player was defeated on would skip the respawn
+        self.undo.clear();
+        let reloaded = match self.checkpoint.clone() {
+            Some(point) => match self.mgr.load_region(&point.region) {
+                Ok(()) => Some(point.pos),
+                Err(e) => {
+                    println!("Couldn't reload {}: {:?}", point.region, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let pos = reloaded.unwrap_or_else(|| {
+            self.manifest
+                .get(self.mgr.get_name())
+                .map(|info| info.spawn)
+                .unwrap_or_else(TilePos::origin)
+        });
+        self.player.teleport(pos);
+        self.player.heal_fully();
+        self.camera.pos = self.player.get_pos().into();
+    }
+
     pub fn update_anims(&mut self) {
         self.player.update_anim(&self.clock);
+        self.mgr.update_story(&self.story, self.player.inventory());
         self.mgr.update_anims(&self.clock);
     }
 
-    /// Every frame. Draws world to screen
-    pub fn draw(&mut self, graphics: &mut Graphics2D, manager: &mut ImgManager) {
+    /// Every frame, before drawing. Runs the minigame and the editor's mouse painting
+    pub fn update(&mut self) {
         match &mut self.minigame {
             Some(minigame) => match minigame.update() {
-                GameResult::Processing => minigame.draw(graphics, manager, &self.camera),
+                GameResult::Processing => (),
                 GameResult::Success => {
                     self.minigame = None;
                 }
@@ -127,79 +297,464 @@ impl World {
                     self.minigame = None;
                 }
             },
+            None => self.create_tiles(),
+        }
+    }
+
+    /// Every frame. Draws world to screen
+    pub fn draw(&mut self, renderer: &mut dyn Renderer) {
+        self.camera.resolution = renderer.resolution();
+        match &mut self.minigame {
+            Some(minigame) => minigame.draw(renderer, &self.camera),
             None => {
-                self.draw_world(graphics, manager);
+                self.draw_world(renderer);
+                self.draw_attacks(renderer);
+                self.draw_health(renderer);
+                self.draw_inventory(renderer);
+                match &self.dialogue {
+                    Some(conversation) => self.draw_dialogue(renderer, conversation),
+                    None => self.draw_message(renderer),
+                }
+                if self.editor.open {
+                    self.draw_editor(renderer);
+                }
+            }
+        }
+    }
+
+    /// Saves the current region, loads `region` and puts the player at `pos`.
+    /// Stays put if the region can't be loaded
+    pub fn warp(&mut self, region: &String, pos: TilePos) {
+        if region != self.mgr.get_name() {
+            self.save_region();
+            if let Err(e) = self.mgr.load_region(region) {
+                println!("Couldn't warp to {}: {:?}", region, e);
+                return;
             }
         }
+        self.player.teleport(pos);
+        self.camera.pos = pos.into();
+    }
+
+    pub fn check(&self, condition: &Condition) -> bool {
+        condition.holds(&self.story, self.player.inventory())
+    }
+
+    pub fn show_text(&mut self, text: String) {
+        self.message = Some(text);
+    }
+
+    /// Text to show over the world, if any
+    pub fn get_message(&self) -> Option<&String> {
+        self.message.as_ref()
     }
 
     fn create_tiles(&mut self) {
-        let pos = self.camera.pix_to_game(screen::get_mouse_pos()).round();
+        let (x, y) = screen::get_mouse_pos();
+        if !self.editor.open || self.editor.tool != Tool::Brush {
+            return;
+        }
+        if self
+            .editor
+            .covers((x as f32, y as f32), self.camera.resolution)
+        {
+            // Don't join up strokes across the overlay
+            self.editor.last_cell = None;
+            return;
+        }
+        let pos = self.cursor();
 
-        if self.mouse_buttons & MOUSE_LEFT > 0 {
-            match &self.draw_item {
-                DrawItem::Tile(tile) => {
-                    let tile = tile.create(pos, self.editor_direction);
-                    self.mgr.push_tile_override(tile);
-                }
-                DrawItem::Entity(entity) => {
-                    let entity = entity.create(pos, self.editor_direction);
-                    self.mgr.push_entity_override(entity);
+        if self.editor.mouse_buttons & MOUSE_LEFT > 0 {
+            self.brush_to(pos, false);
+        } else if self.editor.mouse_buttons & MOUSE_RIGHT > 0 {
+            self.brush_to(pos, true);
+        }
+    }
+
+    /// Moves the brush to `pos`, painting or erasing every cell on the way from where it last was
+    pub fn brush_to(&mut self, pos: TilePos, erase: bool) {
+        let from = self.editor.last_cell.unwrap_or(pos);
+        for cell in from.line_to(pos) {
+            self.draw_cell(cell, erase);
+        }
+        self.editor.last_cell = Some(pos);
+    }
+
+    /// Paints or erases every cell the editor's tool covers from `start` to `end`, as one edit.
+    /// The fill tool starts from `end`
+    pub fn use_tool(&mut self, start: TilePos, end: TilePos, erase: bool) {
+        let cells = match self.editor.tool {
+            Tool::Select => {
+                self.editor.selection = Some((start, end));
+                return;
+            }
+            Tool::Fill => match self.mgr.flood(end, FILL_LIMIT) {
+                Some(cells) => cells,
+                None => {
+                    self.editor
+                        .set_status(format!("Can't fill more than {} cells at once", FILL_LIMIT));
+                    return;
                 }
+            },
+            tool => tool.shape(start, end),
+        };
+        for cell in cells {
+            self.draw_cell(cell, erase);
+        }
+        self.editor.history.end_stroke();
+    }
+
+    fn draw_cell(&mut self, pos: TilePos, erase: bool) {
+        if erase {
+            self.erase(pos);
+        } else {
+            self.paint(pos);
+        }
+    }
+
+    /// Places the item being drawn at `pos`, as part of the stroke being drawn
+    pub fn paint(&mut self, pos: TilePos) {
+        let layer = self.editor.draw_item.layer();
+        let before = self.cell(pos, layer);
+        match &self.editor.draw_item {
+            DrawItem::Tile(tile) => {
+                let tile = tile.create(pos, self.editor.direction);
+                self.mgr.push_tile_override(tile);
+            }
+            DrawItem::Entity(entity) => {
+                let entity = entity.create(pos, self.editor.direction);
+                self.mgr.push_entity_override(entity);
+            }
+        }
+        self.record_change(pos, layer, before);
+        if layer == Layer::Tile && self.editor.auto_tile {
+            self.auto_tile_around(pos);
+        }
+    }
+
+    /// Clears whatever's at `pos` on the layer being drawn, as part of the stroke being drawn
+    pub fn erase(&mut self, pos: TilePos) {
+        let layer = self.editor.draw_item.layer();
+        let before = self.cell(pos, layer);
+        match layer {
+            Layer::Tile => self.mgr.remove_tile_at(pos),
+            Layer::Entity => self.mgr.remove_entity_at(pos),
+        }
+        self.record_change(pos, layer, before);
+        if layer == Layer::Tile && self.editor.auto_tile {
+            self.auto_tile_around(pos);
+        }
+    }
+
+    /// Re-picks the variants of the tile at `pos` and the tiles next to it, as part of the stroke being drawn
+    fn auto_tile_around(&mut self, pos: TilePos) {
+        self.auto_tile(pos);
+        for &side in &[
+            Direction::Top,
+            Direction::Bottom,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            self.auto_tile(pos + side.direction_vector());
+        }
+    }
+
+    /// Gives the tile at `pos` the variant that fits in with the same type of tile around it
+    fn auto_tile(&mut self, pos: TilePos) {
+        let (name, variant) = match self.mgr.get_tile(pos) {
+            Some(tile) if tile.auto_tiles() => (tile.typetag_name(), tile.variant()),
+            _ => return,
+        };
+        let open = |side: Direction| {
+            let next = self.mgr.get_tile(pos + side.direction_vector());
+            next.map(|tile| tile.typetag_name()) != Some(name)
+        };
+        let fitting = Direction::from_open_sides(
+            open(Direction::Top),
+            open(Direction::Bottom),
+            open(Direction::Left),
+            open(Direction::Right),
+        );
+        if fitting == variant {
+            return;
+        }
+        let before = self.cell(pos, Layer::Tile);
+        if let Some(tile) = self.mgr.get_tile(pos).map(|tile| tile.create(pos, fitting)) {
+            self.mgr.push_tile_override(tile);
+        }
+        self.record_change(pos, Layer::Tile, before);
+    }
+
+    fn record_change(&mut self, pos: TilePos, layer: Layer, before: Vec<Value>) {
+        let after = self.cell(pos, layer);
+        let change = Change {
+            pos,
+            layer,
+            before,
+            after,
+        };
+        self.editor.history.change(self.mgr.get_name(), change);
+    }
+
+    fn draw_world(&mut self, renderer: &mut dyn Renderer) {
+        self.mgr
+            .draw_before_player(renderer, &self.clock, &self.camera, self.player.get_pos());
+        self.player.draw(renderer, &self.clock, &self.camera);
+        self.mgr
+            .draw_after_player(renderer, &self.clock, &self.camera, self.player.get_pos());
+    }
+
+    /// Outlines the tile under the mouse and draws the editor's overlay
+    fn draw_editor(&mut self, renderer: &mut dyn Renderer) {
+        let cursor = self.cursor();
+        let (x, y) = screen::get_mouse_pos();
+        // Shapes being dragged out are shown before they're placed
+        let cells = match self.editor.drag_start {
+            Some(start) if self.editor.tool.is_shape() => self.editor.tool.shape(start, cursor),
+            _ if self
+                .editor
+                .covers((x as f32, y as f32), renderer.resolution()) =>
+            {
+                Vec::new()
             }
-        } else if self.mouse_buttons & MOUSE_RIGHT > 0 {
-            match &self.draw_item {
-                DrawItem::Tile(_) => self.mgr.remove_tile_at(pos),
-                DrawItem::Entity(_) => self.mgr.remove_entity_at(pos),
+            _ => vec![cursor],
+        };
+        if let Some((corner, opposite)) = self.editor.selection {
+            for cell in corner.rect_to(opposite, false) {
+                renderer.draw_rect(
+                    self.camera.rect_from_center(cell.into(), (1.0, 1.0).into()),
+                    Color::from_hex_argb(0x404080FF),
+                );
+            }
+        }
+        for cell in cells {
+            renderer.draw_rect(
+                self.camera.rect_from_center(cell.into(), (1.0, 1.0).into()),
+                Color::from_hex_argb(0x40FFFF00),
+            );
+        }
+        self.editor.draw(renderer, cursor, self.mgr.get_name());
+    }
+
+    /// Marks the squares enemies are about to hit
+    fn draw_attacks(&self, renderer: &mut dyn Renderer) {
+        for attack in self.mgr.attacks() {
+            for &cell in &attack.cells {
+                renderer.draw_rect(
+                    self.camera.rect_from_center(cell.into(), (1.0, 1.0).into()),
+                    Color::from_hex_argb(0x60FF2020),
+                );
             }
         }
     }
 
-    fn draw_world(&mut self, graphics: &mut Graphics2D, manager: &mut ImgManager) {
-        self.create_tiles();
+    /// Shows the player's health once they've been hurt
+    fn draw_health(&self, renderer: &mut dyn Renderer) {
+        if self.player.health() >= MAX_HEALTH {
+            return;
+        }
+        for i in 0..MAX_HEALTH {
+            let left = HEALTH_MARGIN + i as f32 * (HEALTH_SIZE + HEALTH_MARGIN);
+            let color = if i < self.player.health() {
+                Color::RED
+            } else {
+                Color::from_hex_argb(0xFF402020)
+            };
+            renderer.draw_rect(
+                Rectangle::from_tuples(
+                    (left, HEALTH_MARGIN),
+                    (left + HEALTH_SIZE, HEALTH_MARGIN + HEALTH_SIZE),
+                ),
+                color,
+            );
+        }
+    }
 
-        self.mgr.draw_before_player(
-            graphics,
-            manager,
-            &self.clock,
-            &self.camera,
-            self.player.get_pos(),
+    /// Lists what the player is carrying down the left of the screen
+    fn draw_inventory(&self, renderer: &mut dyn Renderer) {
+        let top = 2.0 * HEALTH_MARGIN + HEALTH_SIZE;
+        let width = renderer.resolution().0 as f32;
+        for (i, (item, count)) in self.player.inventory().iter().enumerate() {
+            let text = if item.is_unique() {
+                String::from(item.name())
+            } else {
+                format!("{} x{}", item.name(), count)
+            };
+            let pos = (HEALTH_MARGIN, top + i as f32 * INVENTORY_FONT_SIZE);
+            // Shadowed so it reads over light and dark ground alike
+            for &(offset, color) in &[(2.0, Color::BLACK), (0.0, Color::WHITE)] {
+                renderer.draw_text(
+                    (pos.0 + offset, pos.1 + offset),
+                    color,
+                    INVENTORY_FONT_SIZE,
+                    width,
+                    &text,
+                );
+            }
+        }
+    }
+
+    /// Draws the message in a box along the bottom of the screen
+    fn draw_message(&self, renderer: &mut dyn Renderer) {
+        let message = match &self.message {
+            Some(message) => message,
+            None => return,
+        };
+        let (width, height) = renderer.resolution();
+        let (width, height) = (width as f32, height as f32);
+
+        renderer.draw_rect(
+            Rectangle::from_tuples((0.0, height - MESSAGE_HEIGHT), (width, height)),
+            Color::from_hex_argb(0xC0000000),
         );
-        self.player
-            .draw(graphics, manager, &self.clock, &self.camera);
-        self.mgr.draw_after_player(
-            graphics,
-            manager,
-            &self.clock,
-            &self.camera,
-            self.player.get_pos(),
+        renderer.draw_text(
+            (
+                MESSAGE_FONT_SIZE / 2.0,
+                height - MESSAGE_HEIGHT + MESSAGE_FONT_SIZE / 2.0,
+            ),
+            Color::WHITE,
+            MESSAGE_FONT_SIZE,
+            width - MESSAGE_FONT_SIZE,
+            message,
         );
     }
 
+    /// Draws what's being said in a box along the bottom of the screen, with the player's choices under it
+    fn draw_dialogue(&self, renderer: &mut dyn Renderer, conversation: &Conversation) {
+        let (width, height) = renderer.resolution();
+        let (width, height) = (width as f32, height as f32);
+        let margin = DIALOGUE_FONT_SIZE / 2.0;
+        let node = conversation.node();
+
+        renderer.draw_rect(
+            Rectangle::from_tuples((0.0, height - DIALOGUE_HEIGHT), (width, height)),
+            Color::from_hex_argb(0xE0000000),
+        );
+        let mut top = height - DIALOGUE_HEIGHT + margin;
+        if let Some(speaker) = &node.speaker {
+            renderer.draw_text(
+                (margin, top),
+                Color::YELLOW,
+                DIALOGUE_FONT_SIZE,
+                width - 2.0 * margin,
+                speaker,
+            );
+            top += DIALOGUE_FONT_SIZE;
+        }
+        renderer.draw_text(
+            (margin, top),
+            Color::WHITE,
+            DIALOGUE_FONT_SIZE,
+            width - 2.0 * margin,
+            &node.text,
+        );
+
+        let choices = conversation.choices(self);
+        let first = height - margin - choices.len() as f32 * DIALOGUE_FONT_SIZE;
+        for (i, choice) in choices.iter().enumerate() {
+            let top = first + i as f32 * DIALOGUE_FONT_SIZE;
+            let color = if i == conversation.selected() {
+                renderer.draw_text((margin, top), Color::YELLOW, DIALOGUE_FONT_SIZE, width, ">");
+                Color::YELLOW
+            } else {
+                Color::from_hex_argb(0xFFB0B0B0)
+            };
+            renderer.draw_text(
+                (margin + DIALOGUE_FONT_SIZE, top),
+                color,
+                DIALOGUE_FONT_SIZE,
+                width - 2.0 * margin - DIALOGUE_FONT_SIZE,
+                &choice.text,
+            );
+        }
+    }
+
     pub fn send_input_down(&mut self, key: &VirtualKeyCode) {
+        if let VirtualKeyCode::LControl | VirtualKeyCode::RControl = key {
+            self.editor.ctrl_held = true;
+        }
+        if self.editor.prompt.is_some() {
+            self.handle_prompt_controls(key);
+            return;
+        }
+        if self.dialogue.is_some() {
+            self.handle_dialogue_controls(key);
+            return;
+        }
         match &mut self.minigame {
             Some(minigame) => {
                 minigame.key_down(key);
             }
             None => match key {
-                // Need to remove this (V) before release
-                VirtualKeyCode::N | VirtualKeyCode::B | VirtualKeyCode::Q | VirtualKeyCode::R | VirtualKeyCode::T | VirtualKeyCode::Z => self.handle_editor_controls(key),
-                VirtualKeyCode::W | VirtualKeyCode::A | VirtualKeyCode::S | VirtualKeyCode::D /*| VirtualKeyCode::H*/=> self.handle_movement_controls(key),
+                VirtualKeyCode::E => self.editor.toggle(),
+                _ if self.editor.open && self.editor.ctrl_held => self.handle_editor_shortcuts(key),
+                VirtualKeyCode::N
+                | VirtualKeyCode::B
+                | VirtualKeyCode::C
+                | VirtualKeyCode::G
+                | VirtualKeyCode::L
+                | VirtualKeyCode::P
+                | VirtualKeyCode::Q
+                | VirtualKeyCode::R
+                | VirtualKeyCode::T
+                | VirtualKeyCode::Z
+                    if self.editor.open =>
+                {
+                    self.handle_editor_controls(key)
+                }
+                VirtualKeyCode::W | VirtualKeyCode::A | VirtualKeyCode::S | VirtualKeyCode::D => {
+                    self.handle_movement_controls(key)
+                }
+                VirtualKeyCode::H => self.switch_hat(),
+                VirtualKeyCode::U => {
+                    self.undo();
+                }
                 _ => (),
             },
         }
     }
 
+    fn handle_dialogue_controls(&mut self, key: &VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::W | VirtualKeyCode::Up => self.move_dialogue_selection(-1),
+            VirtualKeyCode::S | VirtualKeyCode::Down => self.move_dialogue_selection(1),
+            VirtualKeyCode::Space | VirtualKeyCode::Return => self.confirm_choice(),
+            _ => (),
+        }
+    }
+
     /// Only update world on movement key press
     fn handle_movement_controls(&mut self, key: &VirtualKeyCode) {
-        // if let VirtualKeyCode::H = key {
-        //     self.player.cycle_hat();
-        // }
-        let move_pos = match_wasd_directions(key);
+        self.take_turn(match_wasd_directions(key));
+    }
+
+    /// Puts on the next unlocked hat. Doesn't take a turn
+    pub fn switch_hat(&mut self) {
+        self.player.cycle_hat();
+        self.player.update_anim(&self.clock);
+    }
 
-        if let Some((_, tile)) = self.mgr.get_tile_at_pos(self.player.get_pos() + move_pos) {
-            if !tile.block_movement() {
-                self.player.moove(move_pos);
+    /// Moves the player by `move_pos` if the tile there lets them, then plays out the turn.
+    /// Where they end up is up to their hat. A `move_pos` of zero waits a turn
+    pub fn take_turn(&mut self, move_pos: TilePos) {
+        // The overworld waits while the player is talking
+        if self.dialogue.is_some() {
+            return;
+        }
+        self.record_turn();
+        let from = self.player.get_pos();
+        let hat = self.player.hat().ability();
+        match hat.step(&self.mgr, from, move_pos) {
+            Some(to) => self.player.moove(to - from),
+            None => {
+                self.player.stay();
+                if let Some(tile) = self.mgr.get_tile(from + move_pos) {
+                    self.post_ops.push(
+                        PostOperation::new_empty()
+                            .with_commands(tile.on_player_bump())
+                            .with_commands(hat.on_bump(tile)),
+                    );
+                }
             }
         }
 
@@ -209,54 +764,209 @@ impl World {
     fn handle_editor_controls(&mut self, key: &VirtualKeyCode) {
         match key {
             VirtualKeyCode::N => {
-                println!("Please enter name of new region: ");
-
-                let mut line = String::new();
-
-                std::io::stdin().read_line(&mut line).unwrap();
-
-                self.mgr.new_region(line.trim().to_string());
+                self.editor.prompt =
+                    Some(Prompt::opened_by(PromptKind::NewRegion, String::new(), 'n'));
             }
             VirtualKeyCode::B => {
-                let pos = self.camera.pix_to_game(screen::get_mouse_pos()).round();
-                println!("({},{})", pos.x, pos.y);
+                self.editor.tool = self.editor.tool.next();
+                self.editor
+                    .set_status(format!("Tool: {:?}", self.editor.tool));
+            }
+            VirtualKeyCode::G => {
+                self.editor.auto_tile = !self.editor.auto_tile;
+                let state = if self.editor.auto_tile { "on" } else { "off" };
+                self.editor.set_status(format!("Auto-tile {}", state));
+            }
+            VirtualKeyCode::C => {
+                // Pin channels for sources and sinks placed from now on. Sources use the first one
+                let channels = self.editor.channels.clone().unwrap_or_default().join(", ");
+                self.editor.prompt = Some(Prompt::opened_by(PromptKind::Channels, channels, 'c'));
+            }
+            VirtualKeyCode::L => {
+                let status = match self.editor.draw_item.wiring_mut() {
+                    Some(wiring) => {
+                        wiring.logic = wiring.logic.next();
+                        format!("Logic: {:?}", wiring.logic)
+                    }
+                    None => String::from("Only doors, bridges and warps have logic"),
+                };
+                self.editor.set_status(status);
+            }
+            VirtualKeyCode::P => {
+                // Pin the tile under the mouse as the destination for warps placed from now on
+                let pos = self.cursor();
+                let region = self.mgr.get_name().clone();
+                self.editor
+                    .set_status(format!("Warp target: {} ({}, {})", region, pos.0, pos.1));
+                self.editor.warp_target = Some((region, pos));
+                self.editor.apply_warp_target();
             }
             VirtualKeyCode::Q => {
-                let pos = self.camera.pix_to_game(screen::get_mouse_pos()).round();
+                let pos = self.cursor();
                 self.player.moove(pos - self.player.get_pos());
-                self.camera.moove(self.player.get_pos() - self.camera.pos);
+                self.camera
+                    .moove(GamePos::from(self.player.get_pos()) - self.camera.pos);
             }
             VirtualKeyCode::R => {
-                self.editor_direction.rotate_cw();
+                self.editor.direction.rotate_cw();
             }
             VirtualKeyCode::T => {
-                match &mut self.draw_item {
-                    DrawItem::Tile(tile) => {
-                        *tile = tile.cycle();
-                    }
-                    DrawItem::Entity(entity) => {
-                        // todo add cycling
-                        *entity = entity.cycle();
-                    }
-                }
+                self.editor.draw_item = self.editor.draw_item.next();
+                self.editor.apply_warp_target();
+                self.editor.apply_channels();
             }
             VirtualKeyCode::Z => {
-                self.draw_item = match self.draw_item {
-                    DrawItem::Entity(_) => {
-                        println!("Tile");
-                        DrawItem::Tile(Box::new(BaseGround::default(GamePos::origin())))
+                self.editor.switch_layer();
+                self.editor.apply_warp_target();
+                self.editor.apply_channels();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Keys pressed with Ctrl held while the editor is open
+    fn handle_editor_shortcuts(&mut self, key: &VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::Z => {
+                self.undo_edit();
+            }
+            VirtualKeyCode::Y => {
+                self.redo_edit();
+            }
+            VirtualKeyCode::C => {
+                self.copy_selection();
+            }
+            VirtualKeyCode::X => {
+                self.cut_selection();
+            }
+            VirtualKeyCode::V => {
+                let pos = self.cursor();
+                self.paste(pos);
+            }
+            VirtualKeyCode::R | VirtualKeyCode::M => {
+                let rotate = *key == VirtualKeyCode::R;
+                let result = match &mut self.editor.clipboard {
+                    Some(clipboard) if rotate => clipboard.rotate_cw(),
+                    Some(clipboard) => clipboard.mirror(),
+                    None => {
+                        self.editor.set_status(String::from("Nothing copied"));
+                        return;
                     }
-                    DrawItem::Tile(_) => {
-                        println!("Entity");
-                        DrawItem::Entity(Box::new(Button::default()))
+                };
+                match result {
+                    Ok(()) if rotate => {
+                        self.editor.set_status(String::from("Turned the clipboard"))
                     }
+                    Ok(()) => self
+                        .editor
+                        .set_status(String::from("Flipped the clipboard")),
+                    Err(e) => self
+                        .editor
+                        .set_status(format!("Couldn't change the clipboard: {}", e)),
                 }
             }
-            _ => unreachable!(),
+            VirtualKeyCode::S => {
+                self.editor.prompt = Some(Prompt::opened_by(
+                    PromptKind::SavePrefab,
+                    String::new(),
+                    's',
+                ));
+            }
+            VirtualKeyCode::O => {
+                self.editor.prompt = Some(Prompt::opened_by(
+                    PromptKind::LoadPrefab,
+                    String::new(),
+                    'o',
+                ));
+            }
+            _ => (),
         }
     }
 
+    fn handle_prompt_controls(&mut self, key: &VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::Return => self.submit_prompt(),
+            VirtualKeyCode::Backspace => {
+                if let Some(prompt) = &mut self.editor.prompt {
+                    prompt.text.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Types a character into the editor's prompt, if it has one open
+    pub fn send_char(&mut self, c: char) {
+        if let Some(prompt) = &mut self.editor.prompt {
+            prompt.type_char(c);
+        }
+    }
+
+    /// Closes the editor's prompt without using what was typed. Returns false if there wasn't one open
+    pub fn cancel_prompt(&mut self) -> bool {
+        self.editor.prompt.take().is_some()
+    }
+
+    /// Uses what was typed into the editor's prompt
+    pub fn submit_prompt(&mut self) {
+        let prompt = match self.editor.prompt.take() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        let text = prompt.text.trim();
+        match prompt.kind {
+            PromptKind::NewRegion => match self.create_region(text) {
+                Ok(()) => {
+                    self.warp(&text.to_string(), TilePos::origin());
+                    self.editor.set_status(format!("Made {}", text));
+                }
+                Err(e) => self
+                    .editor
+                    .set_status(format!("Couldn't create region {}: {:?}", text, e)),
+            },
+            PromptKind::Channels => {
+                let channels: Vec<String> = text
+                    .split(',')
+                    .map(|channel| channel.trim().to_string())
+                    .filter(|channel| !channel.is_empty())
+                    .collect();
+                self.editor
+                    .set_status(format!("Channels: {}", channels.join(", ")));
+                self.editor.channels = Some(channels);
+                self.editor.apply_channels();
+            }
+            PromptKind::Property(key) => self.editor.set_property(&key, text),
+            PromptKind::SavePrefab => {
+                let status = match &self.editor.clipboard {
+                    Some(clipboard) => match clipboard.save(text) {
+                        Ok(()) => format!("Saved prefab {}", text),
+                        Err(e) => format!("Couldn't save prefab {}: {:?}", text, e),
+                    },
+                    None => String::from("Nothing copied"),
+                };
+                self.editor.set_status(status);
+            }
+            PromptKind::LoadPrefab => match Prefab::load(text) {
+                Ok(prefab) => {
+                    self.editor.set_status(format!("Loaded prefab {}", text));
+                    self.editor.clipboard = Some(prefab);
+                }
+                Err(e) => self
+                    .editor
+                    .set_status(format!("Couldn't load prefab {}: {:?}", text, e)),
+            },
+        }
+    }
+
+    /// The tile under the mouse
+    fn cursor(&self) -> TilePos {
+        self.camera.pix_to_game(screen::get_mouse_pos()).into()
+    }
+
     pub fn send_input_up(&mut self, key: &VirtualKeyCode) {
+        if let VirtualKeyCode::LControl | VirtualKeyCode::RControl = key {
+            self.editor.ctrl_held = false;
+        }
         match &mut self.minigame {
             Some(minigame) => {
                 minigame.key_up(key);
@@ -272,25 +982,49 @@ impl World {
         _helper: &mut WindowHelper<String>,
         button: MouseButton,
     ) {
-        let pos = self.camera.pix_to_game(screen::get_mouse_pos()).round();
+        if !self.editor.open {
+            return;
+        }
+        let (x, y) = screen::get_mouse_pos();
+        if let MouseButton::Left = button {
+            if self.click_editor((x as f32, y as f32)) {
+                return;
+            }
+        }
+        if self
+            .editor
+            .covers((x as f32, y as f32), self.camera.resolution)
+        {
+            return;
+        }
+        let pos = self.cursor();
 
         // No line-dragging for this action. Keep it here
         if let MouseButton::Middle = button {
-            match &self.draw_item {
+            match &self.editor.draw_item {
                 DrawItem::Tile(_) => {
-                    if let Some((_, tile)) = self.mgr.get_tile_at_pos(pos) {
-                        self.draw_item = DrawItem::Tile(tile.pick());
+                    if let Some(tile) = self.mgr.get_tile_at_pos(pos) {
+                        self.editor.draw_item = DrawItem::pick_tile(&**tile);
                     }
                 }
                 DrawItem::Entity(_) => {
                     if let Some((_, entity)) = self.mgr.get_entity_at_pos(pos) {
-                        self.draw_item = DrawItem::Entity(entity.pick());
+                        self.editor.draw_item = DrawItem::pick_entity(entity);
                     }
                 }
             }
         }
 
-        self.mouse_buttons |= match button {
+        if let MouseButton::Left | MouseButton::Right = button {
+            let erase = matches!(button, MouseButton::Right);
+            self.editor.drag_start = Some(pos);
+            self.editor.last_cell = None;
+            if self.editor.tool == Tool::Fill {
+                self.use_tool(pos, pos, erase);
+            }
+        }
+
+        self.editor.mouse_buttons |= match button {
             MouseButton::Left => MOUSE_LEFT,
             MouseButton::Right => MOUSE_RIGHT,
             MouseButton::Middle => MOUSE_MID,
@@ -299,7 +1033,20 @@ impl World {
     }
 
     pub fn on_mouse_button_up(&mut self, _helper: &mut WindowHelper<String>, button: MouseButton) {
-        self.mouse_buttons &= match button {
+        let erase = match button {
+            MouseButton::Left => Some(false),
+            MouseButton::Right => Some(true),
+            _ => None,
+        };
+        if let (Some(erase), Some(start)) = (erase, self.editor.drag_start) {
+            self.editor.drag_start = None;
+            if self.editor.open && self.editor.tool.is_shape() {
+                self.use_tool(start, self.cursor(), erase);
+            }
+        }
+        self.editor.last_cell = None;
+        self.editor.history.end_stroke();
+        self.editor.mouse_buttons &= match button {
             MouseButton::Left => (!MOUSE_LEFT),
             MouseButton::Right => (!MOUSE_RIGHT),
             MouseButton::Middle => (!MOUSE_MID),
@@ -307,4 +1054,527 @@ impl World {
         };
         // Mouse up handling if needed
     }
+
+    /// Clicks the editor's overlay at the pixel `pos`. Returns false if it missed the overlay
+    pub fn click_editor(&mut self, pos: (f32, f32)) -> bool {
+        if !self.editor.click(pos, self.camera.resolution) {
+            return false;
+        }
+        self.editor.apply_warp_target();
+        self.editor.apply_channels();
+        true
+    }
+
+    /// Scrolls the editor's palette, or its inspector if the mouse is over it. Positive `rows` scroll down
+    pub fn on_mouse_wheel(&mut self, rows: i32) {
+        if self.editor.open {
+            let (x, y) = screen::get_mouse_pos();
+            self.editor
+                .scroll((x as f32, y as f32), self.camera.resolution, rows);
+        }
+    }
+}
+
+// Region management
+impl World {
+    /// Reads the world manifest from disk, registering the loaded region if it's new,
+    /// and reports any broken warps
+    pub fn load_manifest(&mut self) {
+        self.manifest = match Manifest::load() {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Couldn't load world manifest: {:?}", e);
+                Manifest::new()
+            }
+        };
+        self.manifest
+            .register(self.mgr.get_name().clone(), TilePos::origin())
+            .links = self.mgr.warp_links();
+
+        for problem in self.manifest.validate() {
+            println!("World manifest: {:?}", problem);
+        }
+    }
+
+    /// Writes the loaded region to its file and records its warps in the manifest.
+    /// If it can't be written, says why in the editor's status bar
+    pub fn save_region(&mut self) {
+        if let Err(e) = self.mgr.save_region() {
+            self.editor
+                .set_status(format!("Couldn't save {}: {:?}", self.mgr.get_name(), e));
+            return;
+        }
+        self.manifest
+            .register(self.mgr.get_name().clone(), TilePos::origin())
+            .links = self.mgr.warp_links();
+        if let Err(e) = self.manifest.save() {
+            println!("Couldn't save world manifest: {:?}", e);
+        }
+    }
+
+    /// Names of every region in the world
+    pub fn region_names(&self) -> Vec<&String> {
+        self.manifest.regions().map(|(name, _)| name).collect()
+    }
+
+    /// Makes a new empty region with its spawn at the origin
+    pub fn create_region(&mut self, name: &str) -> Result<(), RegionError> {
+        manifest::check_name(name)?;
+        if self.manifest.contains(name) || manifest::region_path(name).exists() {
+            return Err(RegionError::AlreadyExists(name.to_string()));
+        }
+
+        DataManager::new(name.to_string()).save_region()?;
+        self.manifest.register(name.to_string(), TilePos::origin());
+        self.manifest.save()
+    }
+
+    /// Renames a region's file and repoints every warp into it, including those in other regions
+    pub fn rename_region(&mut self, old: &str, new: &str) -> Result<(), RegionError> {
+        manifest::check_name(new)?;
+        if !self.manifest.contains(old) {
+            return Err(RegionError::NotFound(old.to_string()));
+        }
+        if self.manifest.contains(new) || manifest::region_path(new).exists() {
+            return Err(RegionError::AlreadyExists(new.to_string()));
+        }
+
+        // Region files store their own name, so they're rewritten rather than just moved
+        let mut region = if self.mgr.get_name() == old {
+            None
+        } else {
+            Some(DataManager::from_file(old)?)
+        };
+        let renamed = region.as_mut().unwrap_or(&mut self.mgr);
+        renamed.set_name(new.to_string());
+        renamed.retarget_warps(old, new);
+        renamed.save_region()?;
+        std::fs::remove_file(manifest::region_path(old))?;
+
+        for from in self.manifest.linked_from(old) {
+            if from == *self.mgr.get_name() || from == new {
+                continue;
+            }
+            let mut region = DataManager::from_file(&from)?;
+            if region.retarget_warps(old, new) {
+                region.save_region()?;
+            }
+        }
+        if self.mgr.retarget_warps(old, new) {
+            self.mgr.save_region()?;
+        }
+
+        self.manifest.rename(old, new.to_string());
+        if let Some(point) = &mut self.checkpoint {
+            if point.region == old {
+                point.region = new.to_string();
+            }
+        }
+        self.undo.rename_region(old, new);
+        self.editor.rename_region(old, new);
+        self.manifest.save()
+    }
+
+    /// Deletes a region. Refuses if it's loaded or if other regions still warp into it
+    pub fn delete_region(&mut self, name: &str) -> Result<(), RegionError> {
+        if !self.manifest.contains(name) {
+            return Err(RegionError::NotFound(name.to_string()));
+        }
+        if self.mgr.get_name() == name {
+            return Err(RegionError::InUse(name.to_string()));
+        }
+        let from = self.manifest.linked_from(name);
+        if !from.is_empty() {
+            return Err(RegionError::Linked {
+                region: name.to_string(),
+                from,
+            });
+        }
+
+        let path = manifest::region_path(name);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        self.manifest.remove(name);
+        if matches!(&self.checkpoint, Some(point) if point.region == name) {
+            self.checkpoint = None;
+        }
+        self.undo.forget_region(name);
+        self.editor.forget_region(name);
+        self.manifest.save()
+    }
+
+    /// Moves where the player starts in a region
+    pub fn set_region_spawn(&mut self, name: &str, spawn: TilePos) -> Result<(), RegionError> {
+        match self.manifest.get_mut(name) {
+            Some(info) => info.spawn = spawn,
+            None => return Err(RegionError::NotFound(name.to_string())),
+        }
+        self.manifest.save()
+    }
+}
+
+// Dialogue
+impl World {
+    /// Opens the dialogue `name` from the dialogue folder. Stays in the overworld if it can't be read
+    pub fn start_dialogue(&mut self, name: &str) {
+        match Dialogue::from_file(name) {
+            Ok(dialogue) => self.open_dialogue(dialogue),
+            Err(e) => println!("Couldn't open dialogue {}: {:?}", name, e),
+        }
+    }
+
+    pub fn open_dialogue(&mut self, dialogue: Dialogue) {
+        self.dialogue = Some(Conversation::new(dialogue));
+        self.run_node_commands();
+    }
+
+    /// Picks one of the choices on offer, counting from 0. Does nothing if there isn't that choice
+    pub fn choose(&mut self, choice: usize) {
+        let mut conversation = match self.dialogue.take() {
+            Some(conversation) => conversation,
+            None => return,
+        };
+        let (goto, commands) = match conversation.pick(self, choice) {
+            Some(picked) => picked,
+            None => {
+                self.dialogue = Some(conversation);
+                return;
+            }
+        };
+
+        if let Some(node) = &goto {
+            conversation.go_to(node.clone());
+            self.dialogue = Some(conversation);
+        }
+        for command in commands {
+            command.execute(self);
+        }
+        if goto.is_some() {
+            self.run_node_commands();
+        }
+    }
+
+    /// Picks the highlighted choice
+    pub fn confirm_choice(&mut self) {
+        if let Some(conversation) = &self.dialogue {
+            self.choose(conversation.selected());
+        }
+    }
+
+    pub fn move_dialogue_selection(&mut self, by: i32) {
+        let choice_count = match &self.dialogue {
+            Some(conversation) => conversation.choices(self).len(),
+            None => return,
+        };
+        if let Some(conversation) = &mut self.dialogue {
+            conversation.move_selection(by, choice_count);
+        }
+    }
+
+    fn run_node_commands(&mut self) {
+        let commands = match &self.dialogue {
+            Some(conversation) => conversation.node().commands.clone(),
+            None => return,
+        };
+        for command in commands {
+            command.execute(self);
+        }
+    }
+}
+
+// Undo
+impl World {
+    /// Remembers the world as it is before a turn, so the turn can be taken back.
+    /// Tiles and entities are only kept once they change, and are added to the turn when the next one starts
+    fn record_turn(&mut self) {
+        if let Some(journal) = self.mgr.take_journal() {
+            self.undo.amend(self.mgr.get_name(), journal);
+        }
+        match Turn::capture(&self.player, &self.clock, &self.story) {
+            Ok(turn) => self.undo.push(self.mgr.get_name(), turn),
+            Err(e) => println!("Couldn't remember the turn: {:?}", e),
+        }
+        self.mgr.start_journal();
+    }
+
+    /// Puts everything back how it was before the last turn taken in this region.
+    /// Returns false if there's nothing to take back
+    pub fn undo(&mut self) -> bool {
+        if let Some(journal) = self.mgr.take_journal() {
+            self.undo.amend(self.mgr.get_name(), journal);
+        }
+        let turn = match self.undo.pop(self.mgr.get_name()) {
+            Some(turn) => turn,
+            None => return false,
+        };
+        match turn.restore(&mut self.mgr) {
+            Ok((player, clock, story)) => {
+                self.player = player;
+                self.clock = clock;
+                self.story = story;
+            }
+            Err(e) => {
+                println!("Couldn't take back the turn: {:?}", e);
+                return false;
+            }
+        }
+        self.post_ops.clear();
+        self.message = None;
+        self.camera.pos = self.player.get_pos().into();
+        self.update_anims();
+        true
+    }
+
+    /// Number of turns that can be taken back
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of tiles kept so turns can be taken back
+    pub fn undo_saved_tiles(&self) -> usize {
+        self.undo.saved_tiles()
+    }
+
+    /// Number of entities kept so turns can be taken back
+    pub fn undo_saved_entities(&self) -> usize {
+        self.undo.saved_entities()
+    }
+}
+
+// Edit history
+impl World {
+    /// Everything on `layer` at `pos`, saved
+    fn cell(&self, pos: TilePos, layer: Layer) -> Vec<Value> {
+        let saved = match layer {
+            Layer::Tile => self
+                .mgr
+                .get_tile(pos)
+                .into_iter()
+                .map(serde_json::to_value)
+                .collect(),
+            Layer::Entity => self
+                .mgr
+                .get_entities_at_pos(pos)
+                .into_iter()
+                .map(|(_, entity)| serde_json::to_value(entity))
+                .collect(),
+        };
+        match saved {
+            Ok(saved) => saved,
+            Err(e) => {
+                println!(
+                    "Couldn't remember what was at ({}, {}): {:?}",
+                    pos.0, pos.1, e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Replaces everything on `layer` at `pos` with `contents`
+    fn set_cell(region: &mut DataManager, pos: TilePos, layer: Layer, contents: &[Value]) {
+        match layer {
+            Layer::Tile => region.remove_tile_at(pos),
+            Layer::Entity => region.remove_entity_at(pos),
+        }
+        for value in contents {
+            let result = match layer {
+                Layer::Tile => {
+                    serde_json::from_value::<Box<dyn Tile>>(value.clone()).map(|mut tile| {
+                        tile.reset_anim();
+                        region.push_tile(tile);
+                    })
+                }
+                Layer::Entity => {
+                    serde_json::from_value::<Box<dyn Entity>>(value.clone()).map(|mut entity| {
+                        entity.reset_anim();
+                        region.push_entity(entity);
+                    })
+                }
+            };
+            if let Err(e) = result {
+                println!(
+                    "Couldn't put back what was at ({}, {}): {:?}",
+                    pos.0, pos.1, e
+                );
+            }
+        }
+    }
+
+    /// Puts `region`'s cells back how they were before `changes`, or how they were after when redoing
+    fn apply_changes(region: &mut DataManager, changes: &[Change], undo: bool) {
+        if undo {
+            for change in changes.iter().rev() {
+                Self::set_cell(region, change.pos, change.layer, &change.before);
+            }
+        } else {
+            for change in changes {
+                Self::set_cell(region, change.pos, change.layer, &change.after);
+            }
+        }
+    }
+
+    /// Puts back one side of `edit`. Edits to other regions are made to their files, so the editor stays put.
+    /// Returns false, with why in the status line, if it can't be put back
+    fn apply_edit(&mut self, edit: &Edit, undo: bool) -> bool {
+        match edit {
+            Edit::Cells { region, changes } if region == self.mgr.get_name() => {
+                Self::apply_changes(&mut self.mgr, changes, undo);
+                self.update_anims();
+            }
+            Edit::Cells { region, changes } => {
+                let mut other = DataManager::new(region.clone());
+                if let Err(e) = other.load_region(region) {
+                    self.editor
+                        .set_status(format!("Couldn't load {}: {:?}", region, e));
+                    return false;
+                }
+                Self::apply_changes(&mut other, changes, undo);
+                if let Err(e) = other.save_region() {
+                    self.editor
+                        .set_status(format!("Couldn't save {}: {:?}", region, e));
+                    return false;
+                }
+            }
+            Edit::Property { kind, key, .. } if kind != self.editor.draw_item.name() => {
+                self.editor
+                    .set_status(format!("{} was set on a {}, pick one first", key, kind));
+                return false;
+            }
+            Edit::Property {
+                key, before, after, ..
+            } => {
+                let value = if undo { before } else { after };
+                if let Err(e) = self.editor.draw_item.set_property(key, value.clone()) {
+                    println!("Couldn't set {}: {:?}", key, e);
+                }
+            }
+        }
+        true
+    }
+
+    /// Takes back the last edit made in the editor. Returns false if there's nothing to take back
+    pub fn undo_edit(&mut self) -> bool {
+        self.editor.history.end_stroke();
+        let edit = match self.editor.history.last_done() {
+            Some(edit) => edit.clone(),
+            None => {
+                self.editor.set_status(String::from("Nothing to undo"));
+                return false;
+            }
+        };
+        if !self.apply_edit(&edit, true) {
+            return false;
+        }
+        self.editor.history.step_back();
+        self.editor.set_status(format!("Undid {}", edit.describe()));
+        true
+    }
+
+    /// Makes the last undone edit again. Returns false if there's nothing to redo
+    pub fn redo_edit(&mut self) -> bool {
+        self.editor.history.end_stroke();
+        let edit = match self.editor.history.last_undone() {
+            Some(edit) => edit.clone(),
+            None => {
+                self.editor.set_status(String::from("Nothing to redo"));
+                return false;
+            }
+        };
+        if !self.apply_edit(&edit, false) {
+            return false;
+        }
+        self.editor.history.step_forward();
+        self.editor.set_status(format!("Redid {}", edit.describe()));
+        true
+    }
+}
+
+// Clipboard
+impl World {
+    /// Copies what's in the selection. Returns false if nothing is selected
+    pub fn copy_selection(&mut self) -> bool {
+        let (corner, opposite) = match self.editor.selection {
+            Some(selection) => selection,
+            None => {
+                self.editor.set_status(String::from("Nothing selected"));
+                return false;
+            }
+        };
+        match Prefab::copy(&self.mgr, corner, opposite) {
+            Ok(prefab) => {
+                self.editor
+                    .set_status(format!("Copied {} by {}", prefab.size.0, prefab.size.1));
+                self.editor.clipboard = Some(prefab);
+                true
+            }
+            Err(e) => {
+                self.editor.set_status(format!("Couldn't copy: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Copies what's in the selection, then clears it as one edit. Returns false if nothing is selected
+    pub fn cut_selection(&mut self) -> bool {
+        if !self.copy_selection() {
+            return false;
+        }
+        self.editor.history.end_stroke();
+        if let Some((corner, opposite)) = self.editor.selection {
+            for pos in corner.rect_to(opposite, true) {
+                for &layer in &[Layer::Tile, Layer::Entity] {
+                    let before = self.cell(pos, layer);
+                    if before.is_empty() {
+                        continue;
+                    }
+                    Self::set_cell(&mut self.mgr, pos, layer, &[]);
+                    self.record_change(pos, layer, before);
+                }
+            }
+        }
+        self.editor.history.end_stroke();
+        true
+    }
+
+    /// Puts down the clipboard with its top left corner at `origin`, as one edit.
+    /// Returns false if there's nothing to paste
+    pub fn paste(&mut self, origin: TilePos) -> bool {
+        let placed = match &self.editor.clipboard {
+            Some(clipboard) => clipboard.place(origin),
+            None => {
+                self.editor.set_status(String::from("Nothing copied"));
+                return false;
+            }
+        };
+        let (tiles, entities) = match placed {
+            Ok(placed) => placed,
+            Err(e) => {
+                self.editor.set_status(format!("Couldn't paste: {}", e));
+                return false;
+            }
+        };
+        self.editor.history.end_stroke();
+        for tile in tiles {
+            let pos = tile.get_pos();
+            let before = self.cell(pos, Layer::Tile);
+            self.mgr.push_tile_override(tile);
+            self.record_change(pos, Layer::Tile, before);
+        }
+        // Entities pasted onto the same cell go together, replacing what was there
+        let mut cleared = HashSet::new();
+        for entity in entities {
+            let pos = entity.get_pos();
+            let before = self.cell(pos, Layer::Entity);
+            if cleared.insert(pos) {
+                self.mgr.remove_entity_at(pos);
+            }
+            self.mgr.push_entity(entity);
+            self.record_change(pos, Layer::Entity, before);
+        }
+        self.editor.history.end_stroke();
+        self.update_anims();
+        true
+    }
 }
@@ -0,0 +1,152 @@
+//! Rectangles of a region copied in the editor, to be pasted somewhere else. Named ones are saved as prefabs,
+//! in their own files so they can be stamped into any region
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    data::DataManager,
+    entity::Entity,
+    manifest::{self, RegionError},
+    save,
+    space::{Direction, TilePos},
+    tile::Tile,
+};
+
+/// The tiles and entities a prefab puts down
+pub type Placed = (Vec<Box<dyn Tile>>, Vec<Box<dyn Entity>>);
+
+/// Tiles and entities, saved, with positions relative to the top left corner of the rectangle they came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    /// Width and height in cells
+    pub size: TilePos,
+    pub tiles: Vec<Value>,
+    pub entities: Vec<Value>,
+}
+
+impl Prefab {
+    /// Copies everything in the rectangle with `corner` and `opposite` at opposite corners
+    pub fn copy(
+        region: &DataManager,
+        corner: TilePos,
+        opposite: TilePos,
+    ) -> Result<Prefab, serde_json::Error> {
+        let top_left = TilePos(corner.0.min(opposite.0), corner.1.min(opposite.1));
+        let bottom_right = TilePos(corner.0.max(opposite.0), corner.1.max(opposite.1));
+        let mut prefab = Prefab {
+            size: bottom_right - top_left + TilePos(1, 1),
+            ..Prefab::default()
+        };
+        for pos in top_left.rect_to(bottom_right, true) {
+            if let Some(tile) = region.get_tile(pos) {
+                prefab.tiles.push(serde_json::to_value(tile)?);
+            }
+            for (_, entity) in region.get_entities_at_pos(pos) {
+                prefab.entities.push(serde_json::to_value(entity)?);
+            }
+        }
+        prefab.transform(|pos| pos - top_left, |direction| direction)?;
+        Ok(prefab)
+    }
+
+    /// Turns the prefab a quarter turn clockwise, along with the directions of everything in it
+    pub fn rotate_cw(&mut self) -> Result<(), serde_json::Error> {
+        let height = self.size.1;
+        self.size = TilePos(self.size.1, self.size.0);
+        self.transform(
+            |TilePos(x, y)| TilePos(height - 1 - y, x),
+            Direction::quarter_turn_cw,
+        )
+    }
+
+    /// Flips the prefab left to right, along with the directions of everything in it
+    pub fn mirror(&mut self) -> Result<(), serde_json::Error> {
+        let width = self.size.0;
+        self.transform(
+            |TilePos(x, y)| TilePos(width - 1 - x, y),
+            Direction::mirrored,
+        )
+    }
+
+    /// The tiles and entities of the prefab with its top left corner at `origin`
+    pub fn place(&self, origin: TilePos) -> Result<Placed, serde_json::Error> {
+        let mut placed = self.clone();
+        placed.transform(|pos| pos + origin, |direction| direction)?;
+        let tiles = placed
+            .tiles
+            .into_iter()
+            .map(|tile| {
+                let mut tile: Box<dyn Tile> = serde_json::from_value(tile)?;
+                tile.reset_anim();
+                Ok(tile)
+            })
+            .collect::<Result<_, serde_json::Error>>()?;
+        let entities = placed
+            .entities
+            .into_iter()
+            .map(|entity| {
+                let mut entity: Box<dyn Entity> = serde_json::from_value(entity)?;
+                entity.reset_anim();
+                Ok(entity)
+            })
+            .collect::<Result<_, serde_json::Error>>()?;
+        Ok((tiles, entities))
+    }
+
+    /// Moves everything in the prefab and the cells entities are linked to with `map`, and turns them with `turn`
+    fn transform<M, T>(&mut self, map: M, turn: T) -> Result<(), serde_json::Error>
+    where
+        M: Fn(TilePos) -> TilePos,
+        T: Fn(Direction) -> Direction,
+    {
+        for entity in self.entities.iter_mut() {
+            let mut linked: Box<dyn Entity> = serde_json::from_value(entity.clone())?;
+            for pos in linked.linked_positions_mut() {
+                *pos = map(*pos);
+            }
+            *entity = serde_json::to_value(&linked)?;
+        }
+        for item in self.tiles.iter_mut().chain(self.entities.iter_mut()) {
+            if let Some(pos) = item.get_mut("pos") {
+                *pos = serde_json::to_value(map(serde_json::from_value(pos.clone())?))?;
+            }
+            if let Some(direction) = item.get_mut("direction") {
+                *direction =
+                    serde_json::to_value(turn(serde_json::from_value(direction.clone())?))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Points warps in the prefab that lead into region `old` at `new`
+    pub fn retarget_warps(&mut self, old: &str, new: &str) {
+        for item in self.tiles.iter_mut().chain(self.entities.iter_mut()) {
+            if let Some(region) = item.get_mut("target_region") {
+                if region == old {
+                    *region = Value::from(new);
+                }
+            }
+        }
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), RegionError> {
+        manifest::check_name(name)?;
+        let path = manifest::prefab_path(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(save::write_pretty(&path, self)?)
+    }
+
+    pub fn load(name: &str) -> Result<Prefab, RegionError> {
+        manifest::check_name(name)?;
+        let path = manifest::prefab_path(name);
+        if !path.exists() {
+            return Err(RegionError::NotFound(name.to_string()));
+        }
+        Ok(save::read(&path)?)
+    }
+}
@@ -0,0 +1,252 @@
+//! Every kind of tile and entity that can be placed in the editor. The palette lists them in this order,
+//! and cycling with T goes through them the same way. A new type only needs adding here
+
+use super::{
+    entity::{
+        enemy::{AttackPattern, Enemy},
+        friendly::{MoveLeft, Npc, SmileyMan},
+        item::{Item, ItemKind},
+        utility::{self, Button, Lever, OneWay, PushBoulder, Trigger},
+        Entity,
+    },
+    space::{Direction, TilePos},
+    tile::{
+        beehive::HoneyComb,
+        core::{
+            self, Arrow, BaseGround, BasePillar, Bridge, Checkpoint, Door, Edge, Grass, InvisWall,
+            Moon, PressurePlate, Stair, Sun,
+        },
+        mountain::{self, CliffFace, Rock},
+        Tile,
+    },
+};
+
+/// Which group a kind is listed under in the palette
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Terrain,
+    Mechanism,
+    Item,
+    Creature,
+}
+impl Category {
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Terrain => "Terrain",
+            Category::Mechanism => "Mechanism",
+            Category::Item => "Item",
+            Category::Creature => "Creature",
+        }
+    }
+}
+
+pub struct Kind<T: ?Sized> {
+    /// Shown in the editor
+    pub display_name: &'static str,
+    pub category: Category,
+    /// Makes a new one at `pos`, facing the direction if it has one
+    pub create: fn(TilePos, Direction) -> Box<T>,
+}
+impl<T: ?Sized> Kind<T> {
+    /// A new one with nothing set, at the origin
+    pub fn make(&self) -> Box<T> {
+        (self.create)(TilePos::origin(), Direction::Center)
+    }
+}
+impl<T: ?Sized + Tagged> Kind<T> {
+    /// The type's typetag name, which saves and the sprite tables know it by.
+    /// Taken from what `create` makes so it can't drift from the type
+    pub fn name(&self) -> &'static str {
+        self.make().tag()
+    }
+}
+
+/// Tiles and entities, which both know their typetag name
+pub trait Tagged {
+    fn tag(&self) -> &'static str;
+}
+impl Tagged for dyn Tile {
+    fn tag(&self) -> &'static str {
+        self.typetag_name()
+    }
+}
+impl Tagged for dyn Entity {
+    fn tag(&self) -> &'static str {
+        self.typetag_name()
+    }
+}
+
+pub type TileKind = Kind<dyn Tile>;
+pub type EntityKind = Kind<dyn Entity>;
+
+pub static TILES: &[TileKind] = &[
+    TileKind {
+        display_name: "Ground",
+        category: Category::Terrain,
+        create: |pos, _| Box::new(BaseGround::new(pos)),
+    },
+    TileKind {
+        display_name: "Pillar",
+        category: Category::Terrain,
+        create: |pos, _| Box::new(BasePillar::new(pos)),
+    },
+    TileKind {
+        display_name: "Edge",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(Edge::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Grass",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(Grass::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Stair",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(Stair::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Invisible wall",
+        category: Category::Terrain,
+        create: |pos, _| Box::new(InvisWall::new(pos)),
+    },
+    TileKind {
+        display_name: "Boulder",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(mountain::Boulder::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Cliff face",
+        category: Category::Terrain,
+        create: |pos, _| Box::new(CliffFace::new(pos)),
+    },
+    TileKind {
+        display_name: "Rock",
+        category: Category::Terrain,
+        create: |pos, _| Box::new(Rock::new(pos)),
+    },
+    TileKind {
+        display_name: "Honeycomb",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(HoneyComb::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Arrow",
+        category: Category::Terrain,
+        create: |pos, variant| Box::new(Arrow::new(pos, variant)),
+    },
+    TileKind {
+        display_name: "Bridge",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Bridge::new(pos)),
+    },
+    TileKind {
+        display_name: "Door",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Door::new(pos)),
+    },
+    TileKind {
+        display_name: "Pressure plate",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(PressurePlate::new(pos)),
+    },
+    TileKind {
+        display_name: "Sun",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Sun::new(pos)),
+    },
+    TileKind {
+        display_name: "Moon",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Moon::new(pos)),
+    },
+    TileKind {
+        display_name: "Warp",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(core::Warp::unlinked(pos)),
+    },
+    TileKind {
+        display_name: "Checkpoint",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Checkpoint::new(pos)),
+    },
+];
+
+pub static ENTITIES: &[EntityKind] = &[
+    EntityKind {
+        display_name: "Button",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Button::new(pos)),
+    },
+    EntityKind {
+        display_name: "Lever",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Lever::new(pos)),
+    },
+    EntityKind {
+        display_name: "Push boulder",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(PushBoulder::new(pos)),
+    },
+    EntityKind {
+        display_name: "One-way gate",
+        category: Category::Mechanism,
+        create: |pos, direction| Box::new(OneWay::new(pos, direction)),
+    },
+    EntityKind {
+        display_name: "Warp pad",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(utility::Warp::unlinked(pos)),
+    },
+    EntityKind {
+        display_name: "Trigger",
+        category: Category::Mechanism,
+        create: |pos, _| Box::new(Trigger::new(pos, Vec::new(), false)),
+    },
+    EntityKind {
+        display_name: "Item",
+        category: Category::Item,
+        create: |pos, _| Box::new(Item::new(pos, ItemKind::Mast, 1)),
+    },
+    EntityKind {
+        display_name: "Enemy",
+        category: Category::Creature,
+        create: |pos, _| Box::new(Enemy::new(pos, AttackPattern::Jab)),
+    },
+    EntityKind {
+        display_name: "Walker",
+        category: Category::Creature,
+        create: |pos, _| Box::new(MoveLeft::new(pos)),
+    },
+    EntityKind {
+        display_name: "Smiley man",
+        category: Category::Creature,
+        create: |pos, _| Box::new(SmileyMan::new(pos)),
+    },
+    EntityKind {
+        display_name: "NPC",
+        category: Category::Creature,
+        create: |pos, _| Box::new(Npc::new(pos, String::new())),
+    },
+];
+
+/// The kind of tile with the typetag name `name`. None for tiles that can't be placed in the editor
+pub fn tile_kind(name: &str) -> Option<&'static TileKind> {
+    TILES.iter().find(|kind| kind.name() == name)
+}
+
+/// The kind of entity with the typetag name `name`. None for entities that can't be placed in the editor,
+/// like the player
+pub fn entity_kind(name: &str) -> Option<&'static EntityKind> {
+    ENTITIES.iter().find(|kind| kind.name() == name)
+}
+
+/// The kind listed after the one named `name`, going back around to the first after the last.
+/// The first if `name` isn't listed
+pub fn next<T: ?Sized + Tagged>(kinds: &'static [Kind<T>], name: &str) -> &'static Kind<T> {
+    let index = kinds
+        .iter()
+        .position(|kind| kind.name() == name)
+        .map_or(0, |index| (index + 1) % kinds.len());
+    &kinds[index]
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::draw::sprite;
+
+use super::{data::DataManager, manifest::Manifest, prefab::Prefab, space::Direction, World};
+
+/// Upgrades the data of a save file by one version, in place
+pub type Migration = fn(&mut Value);
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// The file was written by a newer version of the game
+    UnsupportedVersion {
+        found: u64,
+        supported: u64,
+    },
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Parse(e)
+    }
+}
+
+/// Anything written to its own save file.
+/// Files are wrapped in an envelope holding the version they were written with,
+/// so older files can be brought up to date before they're deserialized
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Step `i` upgrades version `i` to `i + 1`.
+    /// Version 0 is a bare file from before envelopes existed
+    const MIGRATIONS: &'static [Migration];
+
+    /// The version files are written with
+    fn version() -> u64 {
+        Self::MIGRATIONS.len() as u64
+    }
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T> {
+    version: u64,
+    data: &'a T,
+}
+
+pub fn write<T: Versioned>(path: &Path, value: &T) -> Result<(), SaveError> {
+    let writer = io::LineWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, &envelope(value))?;
+    Ok(())
+}
+
+/// Same as [`write`] but indented, for files meant to be read by people
+pub fn write_pretty<T: Versioned>(path: &Path, value: &T) -> Result<(), SaveError> {
+    let writer = io::LineWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, &envelope(value))?;
+    Ok(())
+}
+
+fn envelope<T: Versioned>(value: &T) -> EnvelopeRef<'_, T> {
+    EnvelopeRef {
+        version: T::version(),
+        data: value,
+    }
+}
+
+pub fn read<T: Versioned>(path: &Path) -> Result<T, SaveError> {
+    let rdr = BufReader::new(File::open(path)?);
+    from_value(serde_json::from_reader(rdr)?)
+}
+
+/// Unwraps and migrates the contents of a save file, then deserializes it
+pub fn from_value<T: Versioned>(file: Value) -> Result<T, SaveError> {
+    let (version, mut data) = unwrap_envelope(file);
+    if version > T::version() {
+        return Err(SaveError::UnsupportedVersion {
+            found: version,
+            supported: T::version(),
+        });
+    }
+
+    for migration in &T::MIGRATIONS[version as usize..] {
+        migration(&mut data);
+    }
+    Ok(serde_json::from_value(data)?)
+}
+
+/// Splits a file into its version and data. Anything that isn't an envelope is version 0
+fn unwrap_envelope(file: Value) -> (u64, Value) {
+    match file {
+        Value::Object(mut map) if map.contains_key("data") => {
+            match map.get("version").and_then(Value::as_u64) {
+                Some(version) => (version, map.remove("data").unwrap()),
+                None => (0, Value::Object(map)),
+            }
+        }
+        file => (0, file),
+    }
+}
+
+impl Versioned for World {
+    const MIGRATIONS: &'static [Migration] = &[drop_transient_state, drop_world_anims];
+}
+
+impl Versioned for DataManager {
+    const MIGRATIONS: &'static [Migration] = &[envelope_only, drop_anims];
+}
+
+impl Versioned for Manifest {
+    const MIGRATIONS: &'static [Migration] = &[envelope_only];
+}
+
+/// Prefabs came after envelopes, so they start at version 0
+impl Versioned for Prefab {
+    const MIGRATIONS: &'static [Migration] = &[];
+}
+
+/// For files whose contents didn't change when envelopes were introduced
+fn envelope_only(_: &mut Value) {}
+
+/// Editor and camera state used to be saved with the world. It's rebuilt every session now
+fn drop_transient_state(world: &mut Value) {
+    if let Value::Object(map) = world {
+        for key in [
+            "camera",
+            "draw_item",
+            "editor_direction",
+            "post_ops",
+            "mouse_buttons",
+            "warp_target",
+        ] {
+            map.remove(key);
+        }
+    }
+}
+
+/// Animations used to be saved with every tile and entity. They come from the sprite table now
+fn drop_anims(region: &mut Value) {
+    for (list, variant_of) in [
+        (
+            "tiles",
+            sprite::tile_variant_of as fn(&str, (u16, u16)) -> Option<Direction>,
+        ),
+        ("entities", sprite::entity_variant_of),
+    ] {
+        if let Some(objects) = region.get_mut(list).and_then(Value::as_array_mut) {
+            for object in objects {
+                drop_anim(object, variant_of);
+            }
+        }
+    }
+}
+
+/// [`drop_anims`] for the region saved inside the world, plus the player's and minigame's animations
+fn drop_world_anims(world: &mut Value) {
+    if let Some(mgr) = world.get_mut("mgr") {
+        drop_anims(mgr);
+    }
+    for key in ["player", "minigame"] {
+        if let Some(Value::Object(map)) = world.get_mut(key) {
+            map.remove("anim");
+        }
+    }
+}
+
+/// Removes the animation from a tile or entity.
+/// Directional ones didn't store their direction, so it's worked out from the frame they were drawn with
+fn drop_anim(object: &mut Value, variant_of: fn(&str, (u16, u16)) -> Option<Direction>) {
+    let map = match object {
+        Value::Object(map) => map,
+        _ => return,
+    };
+    let anim = match map.remove("anim") {
+        Some(anim) => anim,
+        None => return,
+    };
+    if map.contains_key("direction") {
+        return;
+    }
+
+    let name = map.get("type").and_then(Value::as_str);
+    let frame = anim
+        .pointer("/frames/base/1/0")
+        .and_then(|frame| serde_json::from_value::<(u16, u16)>(frame.clone()).ok());
+    if let (Some(name), Some(frame)) = (name, frame) {
+        if let Some(direction) = variant_of(name, frame) {
+            map.insert(
+                String::from("direction"),
+                serde_json::to_value(direction).unwrap(),
+            );
+        }
+    }
+}
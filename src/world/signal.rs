@@ -0,0 +1,130 @@
+//! Wiring between things in a region. Sources like buttons and levers put a signal out on a named channel,
+//! and sinks like doors listen to one or more channels and decide whether they're powered.
+//! Channel names are saved on the sources and sinks themselves, so links live in the region file
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// Channels that are on this turn
+#[derive(Debug, Default, Clone)]
+pub struct Signals {
+    on: BTreeSet<String>,
+}
+
+impl Signals {
+    /// Channels are on if any of their sources are
+    pub fn emit(&mut self, channel: String, on: bool) {
+        if on {
+            self.on.insert(channel);
+        }
+    }
+
+    pub fn is_on(&self, channel: &str) -> bool {
+        self.on.contains(channel)
+    }
+}
+
+/// How a sink combines its channels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Logic {
+    /// Powered while any channel is on
+    #[default]
+    Any,
+    /// Powered while every channel is on
+    All,
+    /// Flips every time the channels go from off to on
+    Toggle,
+    /// Stays powered for `turns` turns after the channels go off
+    Timed { turns: u32 },
+}
+
+impl Logic {
+    /// The next kind of logic, for cycling through them in the editor
+    pub fn next(self) -> Logic {
+        match self {
+            Logic::Any => Logic::All,
+            Logic::All => Logic::Toggle,
+            Logic::Toggle => Logic::Timed { turns: 3 },
+            Logic::Timed { .. } => Logic::Any,
+        }
+    }
+}
+
+/// What a sink listens to, and what it remembers between turns to work out whether it's powered
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wiring {
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub logic: Logic,
+    /// Powered exactly when the logic says it isn't
+    #[serde(default)]
+    pub inverted: bool,
+    /// Whether the channels were on last turn
+    #[serde(default)]
+    was_on: bool,
+    /// Whether a toggle is flipped on
+    #[serde(default)]
+    latched: bool,
+    /// Turns a timer has left
+    #[serde(default)]
+    remaining: u32,
+}
+
+impl Wiring {
+    /// What the wiring remembers between turns, rather than what it's set up with
+    pub const RUNTIME_FIELDS: &'static [&'static str] = &["was_on", "latched", "remaining"];
+
+    pub fn new(channels: Vec<String>, logic: Logic) -> Wiring {
+        Wiring {
+            channels,
+            logic,
+            ..Wiring::default()
+        }
+    }
+
+    /// The same links without anything remembered from past turns, for placing copies in the editor
+    pub fn copy_links(&self) -> Wiring {
+        Wiring {
+            inverted: self.inverted,
+            ..Wiring::new(self.channels.clone(), self.logic)
+        }
+    }
+
+    /// Whether the sink is powered this turn. None if it isn't wired to anything,
+    /// so unwired sinks keep working the way they would otherwise
+    pub fn update(&mut self, signals: &Signals) -> Option<bool> {
+        if self.channels.is_empty() {
+            return None;
+        }
+        let on = match self.logic {
+            Logic::All => self.channels.iter().all(|c| signals.is_on(c)),
+            _ => self.channels.iter().any(|c| signals.is_on(c)),
+        };
+        let rising = on && !self.was_on;
+        self.was_on = on;
+
+        let powered = match self.logic {
+            Logic::Any | Logic::All => on,
+            Logic::Toggle => {
+                if rising {
+                    self.latched = !self.latched;
+                }
+                self.latched
+            }
+            Logic::Timed { turns } => {
+                if on {
+                    self.remaining = turns;
+                    true
+                } else if self.remaining > 0 {
+                    self.remaining -= 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        Some(powered != self.inverted)
+    }
+}
@@ -0,0 +1,133 @@
+//! Plays the game without a window. Turns are driven by [`Input`]s instead of key presses,
+//! so game logic can be stepped through and checked in tests
+
+use image::RgbaImage;
+use speedy2d::color::Color;
+
+use crate::draw::{
+    render::{software::SoftwareRenderer, Renderer},
+    screen::game::CAMERA_SCALE,
+};
+
+use super::{
+    data::DataManager,
+    entity::Entity,
+    save::SaveError,
+    space::{Direction, GamePos, TilePos},
+    tile::Tile,
+    time::Clock,
+    World,
+};
+
+/// What the player does on their turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Step one tile. Bumping into something still takes the turn
+    Move(Direction),
+    Wait,
+    /// Pick one of the choices on offer in a conversation, counting from 0. Doesn't take a turn
+    Choose(usize),
+    /// Take back the last turn taken in this region. Doesn't take a turn
+    Undo,
+}
+
+impl Input {
+    fn move_pos(self) -> TilePos {
+        match self {
+            Input::Move(direction) => direction.direction_vector(),
+            Input::Wait | Input::Choose(_) | Input::Undo => TilePos::origin(),
+        }
+    }
+}
+
+pub struct Simulation {
+    pub world: World,
+    turns: usize,
+}
+
+impl Simulation {
+    /// Starts a game in `region` with the player at the origin
+    pub fn new(region: DataManager) -> Simulation {
+        let mut world = World::new();
+        world.mgr = region;
+        // Tiles work out their state while updating their animations
+        world.update_anims();
+        Simulation { world, turns: 0 }
+    }
+
+    /// Starts a game in a region from the saves folder
+    pub fn load(region: &str) -> Result<Simulation, SaveError> {
+        Ok(Simulation::new(DataManager::from_file(region)?))
+    }
+
+    pub fn with_player_at(mut self, pos: TilePos) -> Simulation {
+        self.world.player.teleport(pos);
+        self
+    }
+
+    /// Centers the camera on `pos`. The camera doesn't follow the player on its own
+    pub fn with_camera_at(mut self, pos: GamePos) -> Simulation {
+        self.world.camera.pos = pos;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Simulation {
+        self.world.clock = clock;
+        self.world.update_anims();
+        self
+    }
+
+    /// Plays out one turn. Moving or waiting does nothing while the player is in a conversation
+    pub fn step(&mut self, input: Input) {
+        if let Input::Choose(choice) = input {
+            self.world.choose(choice);
+            return;
+        }
+        if self.world.dialogue.is_some() {
+            return;
+        }
+        if let Input::Undo = input {
+            self.world.undo();
+            return;
+        }
+        self.world.take_turn(input.move_pos());
+        self.turns += 1;
+    }
+
+    pub fn run<I: IntoIterator<Item = Input>>(&mut self, inputs: I) {
+        for input in inputs {
+            self.step(input);
+        }
+    }
+
+    /// Draws a frame offscreen, `width` by `height` pixels at the same scale as the game window
+    pub fn render(&mut self, width: u32, height: u32) -> RgbaImage {
+        self.world.camera.width = width as f32 / CAMERA_SCALE;
+        self.world.camera.height = height as f32 / CAMERA_SCALE;
+
+        let mut renderer = SoftwareRenderer::new(width, height);
+        renderer.clear(Color::GRAY);
+        self.world.draw(&mut renderer);
+        renderer.into_frame()
+    }
+
+    /// Number of turns played so far
+    pub fn turns(&self) -> usize {
+        self.turns
+    }
+
+    pub fn player_pos(&self) -> TilePos {
+        self.world.player.get_pos()
+    }
+
+    pub fn tile_at(&self, pos: TilePos) -> Option<&dyn Tile> {
+        self.world.mgr.get_tile(pos)
+    }
+
+    pub fn entity_at(&self, pos: TilePos) -> Option<&dyn Entity> {
+        self.world
+            .mgr
+            .get_entity_at_pos(pos)
+            .map(|(_, entity)| entity)
+    }
+}
@@ -109,7 +109,155 @@ impl GamePos {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+// TilePos
+
+/// Logical position of a tile or entity on the grid.
+/// Use [`GamePos`] only for rendering and camera math
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Default,
+)]
+#[serde(from = "TilePosRepr")]
+pub struct TilePos(pub i32, pub i32);
+
+/// Every way a [`TilePos`] has been written to save files
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TilePosRepr {
+    Pair(i32, i32),
+    /// Saves from before TilePos, which stored positions as a GamePos
+    Legacy {
+        x: f32,
+        y: f32,
+    },
+}
+impl From<TilePosRepr> for TilePos {
+    fn from(repr: TilePosRepr) -> Self {
+        match repr {
+            TilePosRepr::Pair(x, y) => TilePos(x, y),
+            TilePosRepr::Legacy { x, y } => GamePos { x, y }.into(),
+        }
+    }
+}
+
+impl Add for TilePos {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        TilePos(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+impl AddAssign for TilePos {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.add(rhs);
+    }
+}
+impl Sub for TilePos {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        TilePos(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+impl SubAssign for TilePos {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.sub(rhs);
+    }
+}
+impl Mul<i32> for TilePos {
+    type Output = TilePos;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        TilePos(self.0 * rhs, self.1 * rhs)
+    }
+}
+impl Neg for TilePos {
+    type Output = TilePos;
+
+    fn neg(self) -> Self::Output {
+        TilePos(-self.0, -self.1)
+    }
+}
+impl From<(i32, i32)> for TilePos {
+    fn from(pos: (i32, i32)) -> Self {
+        TilePos(pos.0, pos.1)
+    }
+}
+impl From<TilePos> for (i32, i32) {
+    fn from(pos: TilePos) -> Self {
+        (pos.0, pos.1)
+    }
+}
+/// Lossless. Every TilePos is exactly representable as a GamePos
+impl From<TilePos> for GamePos {
+    fn from(pos: TilePos) -> Self {
+        (pos.0, pos.1).into()
+    }
+}
+/// Rounds to the nearest tile. Lossless for any GamePos that came from a TilePos
+impl From<GamePos> for TilePos {
+    fn from(pos: GamePos) -> Self {
+        let pos = pos.round();
+        TilePos(pos.x as i32, pos.y as i32)
+    }
+}
+
+impl TilePos {
+    pub fn origin() -> TilePos {
+        TilePos(0, 0)
+    }
+    /// Number of king moves between two tiles
+    pub fn chebyshev_distance(&self, rhs: TilePos) -> i32 {
+        (self.0 - rhs.0).abs().max((self.1 - rhs.1).abs())
+    }
+    /// Tiles on a line from this tile to `end`, both included, without gaps or doubling back
+    pub fn line_to(self, end: TilePos) -> Vec<TilePos> {
+        // Bresenham's line algorithm, which works in all octants
+        let (dx, dy) = ((end.0 - self.0).abs(), -(end.1 - self.1).abs());
+        let (step_x, step_y) = ((end.0 - self.0).signum(), (end.1 - self.1).signum());
+        let mut error = dx + dy;
+        let mut pos = self;
+        let mut line = vec![pos];
+        while pos != end {
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                pos.0 += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                pos.1 += step_y;
+            }
+            line.push(pos);
+        }
+        line
+    }
+    /// Tiles in the rectangle with this tile and `corner` at opposite corners. Only its edges if not `filled`
+    pub fn rect_to(self, corner: TilePos, filled: bool) -> Vec<TilePos> {
+        let (left, right) = (self.0.min(corner.0), self.0.max(corner.0));
+        let (top, bottom) = (self.1.min(corner.1), self.1.max(corner.1));
+        let mut rect = Vec::new();
+        for y in top..=bottom {
+            for x in left..=right {
+                if filled || x == left || x == right || y == top || y == bottom {
+                    rect.push(TilePos(x, y));
+                }
+            }
+        }
+        rect
+    }
+    /// Turns an offset from facing up to facing `facing`. Diagonals and center leave it as is
+    pub fn rotated(self, facing: Direction) -> TilePos {
+        let TilePos(x, y) = self;
+        match facing {
+            Direction::Right => TilePos(-y, x),
+            Direction::Bottom => TilePos(-x, -y),
+            Direction::Left => TilePos(y, -x),
+            _ => self,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Direction {
     Left,
     Right,
@@ -122,6 +270,18 @@ pub enum Direction {
     Center,
 }
 impl Direction {
+    pub const ALL: [Direction; 9] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Top,
+        Direction::Bottom,
+        Direction::CornerBL,
+        Direction::CornerBR,
+        Direction::CornerTR,
+        Direction::CornerTL,
+        Direction::Center,
+    ];
+
     pub fn rotate_cw(&mut self) {
         use Direction::*;
         *self = match self {
@@ -150,7 +310,43 @@ impl Direction {
             Left => Center,
         };
     }
-    pub fn direction_vector(&self) -> GamePos {
+    /// The piece of a nine-piece sheet for a cell whose sides are open, or not, in each direction.
+    /// A side is open when the next cell over isn't part of the same area
+    pub fn from_open_sides(top: bool, bottom: bool, left: bool, right: bool) -> Direction {
+        use Direction::*;
+        let vertical = if top {
+            Some(Top)
+        } else if bottom {
+            Some(Bottom)
+        } else {
+            None
+        };
+        let horizontal = if left {
+            Some(Left)
+        } else if right {
+            Some(Right)
+        } else {
+            None
+        };
+        match (vertical, horizontal) {
+            (Some(Top), Some(Left)) => CornerTL,
+            (Some(Top), Some(_)) => CornerTR,
+            (Some(_), Some(Left)) => CornerBL,
+            (Some(_), Some(_)) => CornerBR,
+            (Some(side), None) | (None, Some(side)) => side,
+            (None, None) => Center,
+        }
+    }
+    /// The direction a quarter turn clockwise from this one
+    pub fn quarter_turn_cw(self) -> Direction {
+        Direction::from(self.direction_vector().rotated(Direction::Right))
+    }
+    /// The direction flipped left to right
+    pub fn mirrored(self) -> Direction {
+        let TilePos(x, y) = self.direction_vector();
+        Direction::from(TilePos(-x, y))
+    }
+    pub fn direction_vector(&self) -> TilePos {
         use Direction::*;
         match self {
             Left => (-1, 0),
@@ -166,24 +362,9 @@ impl Direction {
         .into()
     }
 }
-impl From<GamePos> for Direction {
-    fn from(dir: GamePos) -> Self {
-        let unit_dir = (
-            if dir.x > 0. {
-                1
-            } else if dir.x < 0. {
-                -1
-            } else {
-                0
-            },
-            if dir.y > 0. {
-                1
-            } else if dir.y < 0. {
-                -1
-            } else {
-                0
-            },
-        );
+impl From<TilePos> for Direction {
+    fn from(dir: TilePos) -> Self {
+        let unit_dir = (dir.0.signum(), dir.1.signum());
         use Direction::*;
         match unit_dir {
             (-1, 0) => Left,
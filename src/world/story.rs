@@ -0,0 +1,129 @@
+//! How far the player has got, saved with the world. Changed by commands,
+//! and checked by [`Condition`]s in dialogue and region files
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::entity::item::{Inventory, ItemKind};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoryState {
+    /// E.g. whether a switch was pulled. Unset flags are false
+    #[serde(default)]
+    flags: BTreeMap<String, bool>,
+    /// Named numbers, e.g. how many times a puzzle was failed. Unset counters are 0
+    #[serde(default)]
+    counters: BTreeMap<String, i64>,
+    /// Stage each quest has reached. Quests that haven't started are at stage 0
+    #[serde(default)]
+    quests: BTreeMap<String, u32>,
+}
+
+impl StoryState {
+    pub fn flag(&self, flag: &str) -> bool {
+        self.flags.get(flag).copied().unwrap_or(false)
+    }
+
+    pub fn set_flag(&mut self, flag: String, value: bool) {
+        self.flags.insert(flag, value);
+    }
+
+    pub fn counter(&self, counter: &str) -> i64 {
+        self.counters.get(counter).copied().unwrap_or(0)
+    }
+
+    pub fn set_counter(&mut self, counter: String, value: i64) {
+        self.counters.insert(counter, value);
+    }
+
+    pub fn add_to_counter(&mut self, counter: &str, by: i64) {
+        *self.counters.entry(counter.to_string()).or_insert(0) += by;
+    }
+
+    pub fn quest_stage(&self, quest: &str) -> u32 {
+        self.quests.get(quest).copied().unwrap_or(0)
+    }
+
+    pub fn set_quest_stage(&mut self, quest: String, stage: u32) {
+        self.quests.insert(quest, stage);
+    }
+}
+
+/// Something about the state of the story that can be checked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Condition {
+    Flag {
+        flag: String,
+        #[serde(default = "default_flag_value")]
+        value: bool,
+    },
+    /// The counter is between `min` and `max`, inclusive. Either can be left out
+    Counter {
+        counter: String,
+        #[serde(default)]
+        min: Option<i64>,
+        #[serde(default)]
+        max: Option<i64>,
+    },
+    /// The quest's stage is between `min` and `max`, inclusive. Either can be left out
+    Quest {
+        quest: String,
+        #[serde(default)]
+        min: Option<u32>,
+        #[serde(default)]
+        max: Option<u32>,
+    },
+    HasItem {
+        item: ItemKind,
+        #[serde(default = "default_item_count")]
+        count: u32,
+    },
+    Not {
+        condition: Box<Condition>,
+    },
+    All {
+        conditions: Vec<Condition>,
+    },
+    Any {
+        conditions: Vec<Condition>,
+    },
+}
+
+/// Flags are set and checked for being on unless said otherwise
+pub fn default_flag_value() -> bool {
+    true
+}
+
+/// Items are given, taken and checked for one at a time unless said otherwise
+pub fn default_item_count() -> u32 {
+    1
+}
+
+/// Whether `value` is within the optional bounds
+fn within<T: PartialOrd>(value: T, min: &Option<T>, max: &Option<T>) -> bool {
+    let above = match min {
+        Some(min) => value >= *min,
+        None => true,
+    };
+    let below = match max {
+        Some(max) => value <= *max,
+        None => true,
+    };
+    above && below
+}
+
+impl Condition {
+    pub fn holds(&self, story: &StoryState, inventory: &Inventory) -> bool {
+        match self {
+            Condition::Flag { flag, value } => story.flag(flag) == *value,
+            Condition::Counter { counter, min, max } => within(story.counter(counter), min, max),
+            Condition::Quest { quest, min, max } => within(story.quest_stage(quest), min, max),
+            Condition::HasItem { item, count } => inventory.count(*item) >= *count,
+            Condition::Not { condition } => !condition.holds(story, inventory),
+            Condition::All { conditions } => conditions.iter().all(|c| c.holds(story, inventory)),
+            Condition::Any { conditions } => conditions.iter().any(|c| c.holds(story, inventory)),
+        }
+    }
+}
@@ -1,21 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{world::{
-    tile::{get_default_anim, Animation},
-    GamePos, PostOperation, Tile, Direction,
-}, draw::animation};
+use crate::{
+    draw::sprite,
+    world::{tile::Animation, Direction, PostOperation, Tile, TilePos},
+};
 
 // HoneyComb
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HoneyComb {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for HoneyComb {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -23,27 +25,25 @@ impl Tile for HoneyComb {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(super::core::Arrow::new(GamePos::origin(), Direction::Center))
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
-        Box::new(HoneyComb::new(pos, variant))
+    fn auto_tiles(&self) -> bool {
+        true
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
+        Box::new(HoneyComb::new(pos, variant))
     }
 }
 
 impl HoneyComb {
-    pub fn new(pos: GamePos, direction: Direction) -> HoneyComb {
+    pub fn new(pos: TilePos, direction: Direction) -> HoneyComb {
         HoneyComb {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (4, 4))),
+            anim: sprite::tile("HoneyComb", direction),
+            direction,
         }
     }
 }
@@ -1,25 +1,32 @@
 use serde::{Deserialize, Serialize};
-
-use crate::{world::{
-    tile::{self, get_default_anim, Animation, Obstruction},
-    GamePos, Tile, Direction, Clock
-}, draw::animation::{AnimationSelectError, self}};
-
-use std::collections::HashMap;
-
-use super::mountain::Boulder;
+use speedy2d::color::Color;
+
+use crate::{
+    draw::{animation::AnimationSelectError, sprite},
+    world::{
+        command::Command,
+        entity::item::{Inventory, ItemKind},
+        operation::PostOperation,
+        signal::Wiring,
+        story::{Condition, StoryState},
+        tile::{Animation, Obstruction},
+        Clock, Direction, Tile, TilePos,
+    },
+};
 
 // Arrow
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Arrow {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for Arrow {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -27,27 +34,21 @@ impl Tile for Arrow {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(BaseGround::default(GamePos::origin()))
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
         Box::new(Arrow::new(pos, variant))
     }
-
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
 }
 
 impl Arrow {
-    pub fn new(pos: GamePos, direction: Direction) -> Arrow {
+    pub fn new(pos: TilePos, direction: Direction) -> Arrow {
         Arrow {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (4, 7))),
+            anim: sprite::tile("Arrow", direction),
+            direction,
         }
     }
 }
@@ -56,13 +57,14 @@ impl Arrow {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BaseGround {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
 #[typetag::serde]
 impl Tile for BaseGround {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -70,31 +72,20 @@ impl Tile for BaseGround {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(BasePillar::default(GamePos::origin()))
-    }
-
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
         Box::new(BaseGround::default(pos))
     }
-
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
 }
 
 impl BaseGround {
-    pub fn new(pos: GamePos, anim_frame: (u16, u16)) -> BaseGround {
+    pub fn new(pos: TilePos) -> BaseGround {
         BaseGround {
             pos,
-            anim: get_default_anim(anim_frame),
+            anim: sprite::tile("BaseGround", Direction::Center),
         }
     }
-    pub fn default(pos: GamePos) -> BaseGround {
-        BaseGround::new(pos, (0, 0))
+    pub fn default(pos: TilePos) -> BaseGround {
+        BaseGround::new(pos)
     }
 }
 
@@ -102,13 +93,14 @@ impl BaseGround {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BasePillar {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
 #[typetag::serde]
 impl Tile for BasePillar {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -120,56 +112,127 @@ impl Tile for BasePillar {
         true
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Door::new(GamePos::origin()))
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(BasePillar::default(pos))
     }
+}
 
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
-        Box::new(BasePillar::default(pos))
+impl BasePillar {
+    pub fn new(pos: TilePos) -> BasePillar {
+        BasePillar {
+            pos,
+            anim: sprite::tile("BasePillar", Direction::Center),
+        }
+    }
+    pub fn default(pos: TilePos) -> BasePillar {
+        BasePillar::new(pos)
+    }
+}
+
+// Bridge
+
+/// A gap that can't be crossed until the bridge over it is laid, e.g. by powering it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bridge {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    state: Obstruction,
+    #[serde(default)]
+    wiring: Wiring,
+}
+
+#[typetag::serde]
+impl Tile for Bridge {
+    fn get_pos(&self) -> TilePos {
+        self.pos
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn block_movement(&self) -> bool {
+        self.state == Obstruction::Blocking
+    }
+
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Bridge {
+            wiring: self.wiring.copy_links(),
+            ..Bridge::new(pos)
         })
     }
+
+    fn update_anim(&mut self) {
+        if let Err(AnimationSelectError::NotFound) = self.anim.select(match self.state {
+            Obstruction::Blocking => "base",
+            Obstruction::Free => "laid",
+        }) {
+            panic!();
+        };
+    }
+
+    fn change_self(&mut self) {
+        self.state.toggle();
+    }
+
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        Some(&mut self.wiring)
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        self.state = if powered {
+            Obstruction::Free
+        } else {
+            Obstruction::Blocking
+        };
+    }
 }
 
-impl BasePillar {
-    pub fn new(pos: GamePos, anim_frame: (u16, u16)) -> BasePillar {
-        BasePillar {
+impl Bridge {
+    pub fn new(pos: TilePos) -> Bridge {
+        Bridge {
             pos,
-            anim: get_default_anim(anim_frame),
+            anim: sprite::tile("Bridge", Direction::Center),
+            state: Obstruction::Blocking,
+            wiring: Wiring::default(),
         }
     }
-    pub fn default(pos: GamePos) -> BasePillar {
-        BasePillar::new(pos, (2, 0))
+    /// A bridge laid while `wiring` powers it
+    pub fn wired(pos: TilePos, wiring: Wiring) -> Bridge {
+        Bridge {
+            wiring,
+            ..Bridge::new(pos)
+        }
     }
 }
 
 // Button
 // #[derive(Debug, Serialize, Deserialize)]
 // pub struct Button {
-//     pos: GamePos,
+//     pos: TilePos,
 //     anim: Animation,
-//     effect_pos: GamePos,
+//     effect_pos: TilePos,
 // }
 // #[typetag::serde]
 // impl Tile for Button {
-//     fn get_pos(&self) -> GamePos {
+//     fn get_pos(&self) -> TilePos {
 //         self.pos
 //     }
 //     fn get_anim_mut(&mut self) -> &mut Animation {
 //         &mut self.anim
 //     }
 //     fn next(&self) -> Box<dyn Tile> {
-//         Some(Box::new(Door::new(GamePos::origin())))
+//         Some(Box::new(Door::new(TilePos::origin())))
 //     }
-//     fn create(&self, pos: GamePos, _variant: TileVariant) -> Box<dyn Tile> {
+//     fn create(&self, pos: TilePos, _variant: TileVariant) -> Box<dyn Tile> {
 //         Box::new(Button::new(pos))
 //     }
-//     fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
+//     fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
 //         PostOperation::new_empty()
 //             .with_block_player(move_pos)
 //             .with_custom(Box::new(move |w, p| {
@@ -181,14 +244,14 @@ impl BasePillar {
 //     }
 //     fn pick_tile(&self) -> Box<dyn Tile> {
 //         Box::new(Self {
-//             pos: GamePos::origin(),
+//             pos: TilePos::origin(),
 //             anim: get_default_anim((0, 0)),
-//             effect_pos: GamePos::origin(),
+//             effect_pos: TilePos::origin(),
 //         })
 //     }
 // }
 // impl Button {
-//     pub fn new(pos: GamePos) -> Button {
+//     pub fn new(pos: TilePos) -> Button {
 //         let mut x = String::new();
 //         println!("Enter the x of the tile to be affected by the button: ");
 //         std::io::stdin().read_line(&mut x).unwrap();
@@ -208,25 +271,77 @@ impl BasePillar {
 //     }
 //     pub fn default() -> Button {
 //         Button {
-//             pos: GamePos::origin(),
+//             pos: TilePos::origin(),
 //             anim: get_default_anim((2, 4)),
-//             effect_pos: GamePos::origin(),
+//             effect_pos: TilePos::origin(),
 //         }
 //     }
 // }
 
+// Checkpoint
+
+/// Saves the region and marks where the player comes back to when defeated
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+}
+
+#[typetag::serde]
+impl Tile for Checkpoint {
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Checkpoint::new(pos))
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        // Standing still doesn't save again
+        if move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        PostOperation::new_empty().with_command(Command::SetCheckpoint { pos: self.pos })
+    }
+}
+
+impl Checkpoint {
+    pub fn new(pos: TilePos) -> Checkpoint {
+        Checkpoint {
+            pos,
+            anim: sprite::tile("Checkpoint", Direction::Center),
+        }
+    }
+}
+
 // Door
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Door {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
     state: Obstruction,
+    /// Item that opens the door when the player walks into it. Keys get used up, other items don't
+    #[serde(default)]
+    requires: Option<ItemKind>,
+    /// Open exactly when this holds, whatever else opens or closes the door
+    #[serde(default)]
+    open_when: Option<Condition>,
+    /// Open while powered, for doors wired up to something
+    #[serde(default)]
+    wiring: Wiring,
 }
 
 #[typetag::serde]
 impl Tile for Door {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -238,12 +353,13 @@ impl Tile for Door {
         self.state == Obstruction::Blocking
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Edge::new(GamePos::origin(), Direction::Center))
-    }
-
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
-        Box::new(Door::new(pos))
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Door {
+            requires: self.requires,
+            open_when: self.open_when.clone(),
+            wiring: self.wiring.copy_links(),
+            ..Door::new(pos)
+        })
     }
 
     fn update_anim(&mut self) {
@@ -259,26 +375,81 @@ impl Tile for Door {
         self.state.toggle();
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-            state: Obstruction::Free,
-        })
+    fn has_turn_state(&self) -> bool {
+        true
     }
-}
 
-impl Door {
-    pub fn new(pos: GamePos) -> Door {
-        let mut frames = HashMap::new();
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        Some(&mut self.wiring)
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        self.state = if powered {
+            Obstruction::Free
+        } else {
+            Obstruction::Blocking
+        };
+    }
+
+    fn update_story(&mut self, story: &StoryState, inventory: &Inventory) {
+        if let Some(condition) = &self.open_when {
+            self.state = if condition.holds(story, inventory) {
+                Obstruction::Free
+            } else {
+                Obstruction::Blocking
+            };
+        }
+    }
 
-        frames.insert(String::from("open"), (true, vec![(2, 6)]));
-        frames.insert(String::from("base"), (true, vec![(2, 5)]));
+    fn on_player_bump(&self) -> Vec<Command> {
+        let item = match self.requires {
+            Some(item) if self.state == Obstruction::Blocking => item,
+            _ => return Vec::new(),
+        };
+        let mut unlock = vec![Command::ToggleTile { pos: self.pos }];
+        if !item.is_unique() {
+            unlock.push(Command::TakeItem { item, count: 1 });
+        }
+        vec![Command::If {
+            condition: Condition::HasItem { item, count: 1 },
+            then: unlock,
+            otherwise: vec![Command::ShowText {
+                text: format!("It needs {}", item.with_article()),
+            }],
+        }]
+    }
+}
 
+impl Door {
+    pub fn new(pos: TilePos) -> Door {
         Door {
             pos,
-            anim: tile::anim_with_frames(frames),
+            anim: sprite::tile("Door", Direction::Center),
             state: Obstruction::Blocking,
+            requires: None,
+            open_when: None,
+            wiring: Wiring::default(),
+        }
+    }
+    /// A door that only opens for a player carrying `item`
+    pub fn locked(pos: TilePos, item: ItemKind) -> Door {
+        Door {
+            requires: Some(item),
+            ..Door::new(pos)
+        }
+    }
+    /// A door that's open while `wiring` powers it
+    pub fn wired(pos: TilePos, wiring: Wiring) -> Door {
+        Door {
+            wiring,
+            ..Door::new(pos)
+        }
+    }
+    /// A door that's open while `condition` holds
+    pub fn open_when(pos: TilePos, condition: Condition) -> Door {
+        Door {
+            open_when: Some(condition),
+            ..Door::new(pos)
         }
     }
 }
@@ -287,13 +458,15 @@ impl Door {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Edge {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for Edge {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -301,27 +474,25 @@ impl Tile for Edge {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Grass::new(GamePos::origin(), Direction::Center))
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
-        Box::new(Edge::new(pos, variant))
+    fn auto_tiles(&self) -> bool {
+        true
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
+        Box::new(Edge::new(pos, variant))
     }
 }
 
 impl Edge {
-    pub fn new(pos: GamePos, direction: Direction) -> Edge {
+    pub fn new(pos: TilePos, direction: Direction) -> Edge {
         Edge {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (4, 1))),
+            anim: sprite::tile("Edge", direction),
+            direction,
         }
     }
 }
@@ -330,13 +501,15 @@ impl Edge {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Grass {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for Grass {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -344,27 +517,25 @@ impl Tile for Grass {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(InvisWall::new(GamePos::origin()))
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
-        Box::new(Grass::new(pos, variant))
+    fn auto_tiles(&self) -> bool {
+        true
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
+        Box::new(Grass::new(pos, variant))
     }
 }
 
 impl Grass {
-    pub fn new(pos: GamePos, direction: Direction) -> Grass {
+    pub fn new(pos: TilePos, direction: Direction) -> Grass {
         Grass {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (10, 1))),
+            anim: sprite::tile("Grass", direction),
+            direction,
         }
     }
 }
@@ -373,13 +544,14 @@ impl Grass {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InvisWall {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
 #[typetag::serde]
 impl Tile for InvisWall {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -387,31 +559,20 @@ impl Tile for InvisWall {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Moon::new(GamePos::origin()))
-    }
-
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
         Box::new(InvisWall::new(pos))
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
-
     fn block_movement(&self) -> bool {
         true
     }
 }
 
 impl InvisWall {
-    pub fn new(pos: GamePos) -> InvisWall {
+    pub fn new(pos: TilePos) -> InvisWall {
         InvisWall {
             pos,
-            anim: get_default_anim((17, 0)),
+            anim: sprite::tile("InvisWall", Direction::Center),
         }
     }
 }
@@ -420,16 +581,23 @@ impl InvisWall {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Moon {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
     state: Obstruction,
+    /// Signals on this channel through the night
+    #[serde(default)]
+    channel: String,
 }
 
 #[typetag::serde]
 impl Tile for Moon {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["state"]
+    }
 
     fn get_anim_mut(&mut self) -> &mut Animation {
         &mut self.anim
@@ -437,6 +605,11 @@ impl Tile for Moon {
     fn block_movement(&self) -> bool {
         self.state == Obstruction::Blocking
     }
+
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
     fn update_state(&mut self, clock: &Clock) {
         self.state = if clock.is_day() {
             Obstruction::Free
@@ -445,29 +618,96 @@ impl Tile for Moon {
         };
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Stair::new(GamePos::origin(), Direction::Center))
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Moon {
+            channel: self.channel.clone(),
+            ..Moon::new(pos)
+        })
     }
 
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
-        Box::new(Moon::new(pos))
+    fn signal(&mut self, clock: &Clock, _occupied: bool) -> bool {
+        !clock.is_day()
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-            state: Obstruction::Free,
-        })
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.channel)
     }
 }
 
 impl Moon {
-    pub fn new(pos: GamePos) -> Moon {
+    pub fn new(pos: TilePos) -> Moon {
         Moon {
             pos,
-            anim: get_default_anim((6, 0)),
+            anim: sprite::tile("Moon", Direction::Center),
             state: Obstruction::Free,
+            channel: String::new(),
+        }
+    }
+}
+
+// PressurePlate
+
+/// Signals while the player or an entity is standing on it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PressurePlate {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    pressed: bool,
+}
+
+#[typetag::serde]
+impl Tile for PressurePlate {
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["pressed"]
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(PressurePlate::on_channel(pos, self.channel.clone()))
+    }
+
+    fn update_anim(&mut self) {
+        if let Err(AnimationSelectError::NotFound) =
+            self.anim.select(if self.pressed { "down" } else { "base" })
+        {
+            panic!();
+        };
+    }
+
+    fn signal(&mut self, _clock: &Clock, occupied: bool) -> bool {
+        self.pressed = occupied;
+        occupied
+    }
+
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.channel)
+    }
+}
+
+impl PressurePlate {
+    pub fn new(pos: TilePos) -> PressurePlate {
+        PressurePlate::on_channel(pos, String::new())
+    }
+    pub fn on_channel(pos: TilePos, channel: String) -> PressurePlate {
+        PressurePlate {
+            pos,
+            anim: sprite::tile("PressurePlate", Direction::Center),
+            channel,
+            pressed: false,
         }
     }
 }
@@ -475,32 +715,32 @@ impl Moon {
 // OneWay
 // #[derive(Debug, Serialize, Deserialize)]
 // pub struct OneWay {
-//     pos: GamePos,
+//     pos: TilePos,
 //     anim: Animation,
 //     direction: TileVariant,
 // }
 // #[typetag::serde]
 // impl Tile for OneWay {
-//     fn get_pos(&self) -> GamePos {
+//     fn get_pos(&self) -> TilePos {
 //         self.pos
 //     }
 //     fn get_anim_mut(&mut self) -> &mut Animation {
 //         &mut self.anim
 //     }
 //     fn next(&self) -> Box<dyn Tile> {
-//         Some(Box::new(SmileyMan::new(GamePos::origin())))
+//         Some(Box::new(SmileyMan::new(TilePos::origin())))
 //     }
-//     fn create(&self, pos: GamePos, variant: TileVariant) -> Box<dyn Tile> {
+//     fn create(&self, pos: TilePos, variant: TileVariant) -> Box<dyn Tile> {
 //         Box::new(OneWay::new(pos, variant))
 //     }
 //     fn pick_tile(&self) -> Box<dyn Tile> {
 //         Box::new(Self {
-//             pos: GamePos::origin(),
+//             pos: TilePos::origin(),
 //             anim: get_default_anim((0, 0)),
 //             direction: TileVariant::Center,
 //         })
 //     }
-//     fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
+//     fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
 //         PostOperation::new_empty()
 //             .with_block_when(
 //                 move |p| {
@@ -513,7 +753,7 @@ impl Moon {
 //     }
 // }
 // impl OneWay {
-//     pub fn new(pos: GamePos, direction: TileVariant) -> OneWay {
+//     pub fn new(pos: TilePos, direction: TileVariant) -> OneWay {
 //         OneWay {
 //             pos,
 //             anim: get_default_anim(match_directions(direction, (10, 4))),
@@ -525,37 +765,37 @@ impl Moon {
 // SmileyMan
 // #[derive(Debug, Serialize, Deserialize)]
 // pub struct SmileyMan {
-//     pos: GamePos,
+//     pos: TilePos,
 //     anim: Animation,
 // }
 // #[typetag::serde]
 // impl Tile for SmileyMan {
-//     fn get_pos(&self) -> GamePos {
+//     fn get_pos(&self) -> TilePos {
 //         self.pos
 //     }
 //     fn get_anim_mut(&mut self) -> &mut Animation {
 //         &mut self.anim
 //     }
 //     fn next(&self) -> Box<dyn Tile> {
-//         Some(Box::new(Stair::new(GamePos::origin(), TileVariant::Center)))
+//         Some(Box::new(Stair::new(TilePos::origin(), TileVariant::Center)))
 //     }
-//     fn create(&self, pos: GamePos, _variant: TileVariant) -> Box<dyn Tile> {
+//     fn create(&self, pos: TilePos, _variant: TileVariant) -> Box<dyn Tile> {
 //         Box::new(SmileyMan::new(pos))
 //     }
 //     fn pick_tile(&self) -> Box<dyn Tile> {
 //         Box::new(Self {
-//             pos: GamePos::origin(),
+//             pos: TilePos::origin(),
 //             anim: get_default_anim((0, 0)),
 //         })
 //     }
-//     fn on_player_enter(&mut self, move_pos: GamePos) -> PostOperation {
+//     fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
 //         PostOperation::new_empty()
 //             .with_minigame(Box::new(SmileyWin::new()))
 //             .with_block_player(move_pos)
 //     }
 // }
 // impl SmileyMan {
-//     pub fn new(pos: GamePos) -> SmileyMan {
+//     pub fn new(pos: TilePos) -> SmileyMan {
 //         SmileyMan {
 //             pos,
 //             anim: get_default_anim((0, 7)),
@@ -567,13 +807,15 @@ impl Moon {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Stair {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for Stair {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -581,37 +823,21 @@ impl Tile for Stair {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Sun::new(GamePos::origin()))
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
         Box::new(Stair::new(pos, variant))
     }
-
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
 }
 
 impl Stair {
-    pub fn new(pos: GamePos, direction: Direction) -> Stair {
+    pub fn new(pos: TilePos, direction: Direction) -> Stair {
         Stair {
             pos,
-            anim: get_default_anim(match direction {
-                Direction::Left => (0, 1),
-                Direction::Right => (0, 1),
-                Direction::Top => (2, 1),
-                Direction::Bottom => (2, 1),
-                Direction::CornerBL => (0, 3),
-                Direction::CornerBR => (2, 3),
-                Direction::CornerTR => (2, 2),
-                Direction::CornerTL => (0, 2),
-                Direction::Center => (0, 0),
-            }),
+            anim: sprite::tile("Stair", direction),
+            direction,
         }
     }
 }
@@ -620,16 +846,23 @@ impl Stair {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Sun {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
     state: Obstruction,
+    /// Signals on this channel through the day
+    #[serde(default)]
+    channel: String,
 }
 
 #[typetag::serde]
 impl Tile for Sun {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["state"]
+    }
 
     fn get_anim_mut(&mut self) -> &mut Animation {
         &mut self.anim
@@ -639,6 +872,10 @@ impl Tile for Sun {
         self.state == Obstruction::Blocking
     }
 
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
     fn update_state(&mut self, clock: &Clock) {
         self.state = if clock.is_day() {
             Obstruction::Blocking
@@ -647,86 +884,120 @@ impl Tile for Sun {
         };
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Boulder::new(GamePos::origin(), Direction::Center))
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Sun {
+            channel: self.channel.clone(),
+            ..Sun::new(pos)
+        })
     }
 
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
-        Box::new(Sun::new(pos))
+    fn signal(&mut self, clock: &Clock, _occupied: bool) -> bool {
+        clock.is_day()
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-            state: Obstruction::Free,
-        })
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.channel)
     }
 }
 
 impl Sun {
-    pub fn new(pos: GamePos) -> Sun {
+    pub fn new(pos: TilePos) -> Sun {
         Sun {
             pos,
-            anim: get_default_anim((8, 0)),
+            anim: sprite::tile("Sun", Direction::Center),
             state: Obstruction::Blocking,
+            channel: String::new(),
         }
     }
 }
 
 // Warp
-// #[derive(Debug, Serialize, Deserialize)]
-// pub struct Warp {
-//     pos: GamePos,
-//     anim: Animation,
-//     load_name: String,
-// }
-// #[typetag::serde]
-// impl Tile for Warp {
-//     fn get_pos(&self) -> GamePos {
-//         self.pos
-//     }
-//     fn get_anim_mut(&mut self) -> &mut Animation {
-//         &mut self.anim
-//     }
-//     fn next(&self) -> Box<dyn Tile> {
-//         Some(Box::new(super::mountain::Boulder::new(GamePos::origin(), TileVariant::Center)))
-//     }
-//     fn create(&self, pos: GamePos, _variant: TileVariant) -> Box<dyn Tile> {
-//         Box::new(Warp::new(pos))
-//     }
-//     fn on_player_enter(&mut self, _move_pos: GamePos) -> PostOperation {
-//         PostOperation::new_empty()
-//             .with_custom(Box::new(|w, p| {
-//                 w.load_region(p.text.as_ref().unwrap())
-//                     .expect("load region wrong");
-//             }))
-//             .params(Params::new_empty().with_text(self.load_name.clone()))
-//     }
-//     fn pick_tile(&self) -> Box<dyn Tile> {
-//         Box::new(Self {
-//             pos: GamePos::origin(),
-//             anim: get_default_anim((0, 0)),
-//             load_name: String::from("a"),
-//         })
-//     }
-// }
-// impl Warp {
-//     pub fn new(pos: GamePos) -> Warp {
-//         let mut name = String::new();
-//         println!("Enter the name of the zone for this tile to load: ");
-//         std::io::stdin().read_line(&mut name).unwrap();
-//         Warp {
-//             load_name: name.trim().to_string(),
-//             pos,
-//             anim: get_default_anim((2, 4)),
-//         }
-//     }
-//     pub fn default() -> Warp {
-//         Warp {
-//             pos: GamePos::origin(),
-//             anim: get_default_anim((2, 4)),
-//             load_name: String::from(""),
-//         }
-//     }
-// }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Warp {
+    pos: TilePos,
+    #[serde(skip)]
+    anim: Animation,
+    target_region: String,
+    target_pos: TilePos,
+    /// Only warps while powered, for warps wired up to something
+    #[serde(default)]
+    wiring: Wiring,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[typetag::serde]
+impl Tile for Warp {
+    fn get_pos(&self) -> TilePos {
+        self.pos
+    }
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &["disabled"]
+    }
+
+    fn get_anim_mut(&mut self) -> &mut Animation {
+        &mut self.anim
+    }
+
+    fn draw_color(&self) -> Color {
+        if self.disabled {
+            Color::from_hex_argb(0xFF808080)
+        } else {
+            Color::WHITE
+        }
+    }
+
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
+        Box::new(Warp {
+            wiring: self.wiring.copy_links(),
+            ..Warp::new(pos, self.target_region.clone(), self.target_pos)
+        })
+    }
+
+    fn on_player_enter(&mut self, move_pos: TilePos) -> PostOperation {
+        // Only warp on the step onto the tile, not every turn the player stands here
+        if self.target_region.is_empty() || self.disabled || move_pos == TilePos::origin() {
+            return PostOperation::new_empty();
+        }
+        PostOperation::new_empty().with_warp(self.target_region.clone(), self.target_pos)
+    }
+
+    fn warp_target(&self) -> Option<(&String, TilePos)> {
+        Some((&self.target_region, self.target_pos))
+    }
+
+    fn set_warp_target(&mut self, region: String, pos: TilePos) {
+        self.target_region = region;
+        self.target_pos = pos;
+    }
+
+    fn has_turn_state(&self) -> bool {
+        true
+    }
+
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        Some(&mut self.wiring)
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        self.disabled = !powered;
+    }
+}
+
+impl Warp {
+    pub fn new(pos: TilePos, target_region: String, target_pos: TilePos) -> Warp {
+        Warp {
+            pos,
+            anim: sprite::tile("Warp", Direction::Center),
+            target_region,
+            target_pos,
+            wiring: Wiring::default(),
+            disabled: false,
+        }
+    }
+    /// A warp at `pos` that doesn't lead anywhere yet
+    pub fn unlinked(pos: TilePos) -> Warp {
+        Warp::new(pos, String::new(), TilePos::origin())
+    }
+}
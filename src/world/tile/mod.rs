@@ -1,19 +1,20 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
-use speedy2d::{color::Color, Graphics2D};
+use speedy2d::color::Color;
 
 use crate::{
-    draw::{
-        animation::Animation,
-        ui::img::{Img, ImgManager},
-    },
+    draw::{animation::Animation, render::Renderer, sprite},
     screen::camera::Camera,
 };
 
-
 use super::{
-    space::{GamePos, SPRITE_EXTENSION_HEIGHT, Direction},
+    command::Command,
+    entity::item::Inventory,
+    operation::PostOperation,
+    signal::{Signals, Wiring},
+    space::{Direction, TilePos, SPRITE_EXTENSION_HEIGHT},
+    story::StoryState,
     time::Clock,
     VIEW_DIST,
 };
@@ -38,38 +39,98 @@ impl Obstruction {
 
 #[typetag::serde(tag = "type")]
 pub trait Tile: Debug {
-    fn get_pos(&self) -> GamePos;
+    fn get_pos(&self) -> TilePos;
     fn get_anim_mut(&mut self) -> &mut Animation;
 
+    /// Which sprite of the tile type to draw. Only tiles that come in several directions have one
+    fn variant(&self) -> Direction {
+        Direction::Center
+    }
+    /// Whether the editor's auto-tile mode picks the variant from which neighbours are the same type of tile.
+    /// Only tiles drawn as a nine-piece sheet do
+    fn auto_tiles(&self) -> bool {
+        false
+    }
+    /// Whether signals, the clock or the story can change what's saved of the tile during a turn.
+    /// These are kept before every turn so it can be undone
+    fn has_turn_state(&self) -> bool {
+        false
+    }
+    /// Looks the animation back up in the sprite table. Animations aren't saved so this runs on load
+    fn reset_anim(&mut self) {
+        *self.get_anim_mut() = sprite::tile(self.typetag_name(), self.variant());
+    }
+
     fn block_movement(&self) -> bool {
         false
     }
+    /// Whether the acid hat eats through it
+    fn dissolves_in_acid(&self) -> bool {
+        false
+    }
     /// To trigger some update of the tile's state
     fn change_self(&mut self) {}
+    /// What happens when the player walks into this tile but it blocks them
+    fn on_player_bump(&self) -> Vec<Command> {
+        Vec::new()
+    }
 
     /// For updating the tile's state given the clock
     fn update_state(&mut self, _clock: &Clock) {}
+    /// For updating the tile's state given how far the player is through the story
+    fn update_story(&mut self, _story: &StoryState, _inventory: &Inventory) {}
+    /// Whether this signal source is putting out a signal this turn.
+    /// `occupied` is whether the player or an entity is standing on it
+    fn signal(&mut self, _clock: &Clock, _occupied: bool) -> bool {
+        false
+    }
+    /// Channel a signal source puts its signal out on. None if it isn't a source
+    fn channel_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+    /// Channels a signal sink listens to. None if it isn't a sink
+    fn wiring_mut(&mut self) -> Option<&mut Wiring> {
+        None
+    }
+    /// Saved fields that only change during play, like whether a plate is pressed.
+    /// The editor's inspector leaves them out
+    fn runtime_fields(&self) -> &'static [&'static str] {
+        &[]
+    }
+    /// Called every turn with the channels that are on
+    fn receive(&mut self, signals: &Signals) {
+        if let Some(powered) = self.wiring_mut().and_then(|wiring| wiring.update(signals)) {
+            self.set_powered(powered);
+        }
+    }
+    /// What a signal sink does when its wiring powers it or stops powering it
+    fn set_powered(&mut self, _powered: bool) {}
+
+    /// Called when the player steps onto this tile. `move_pos` is the step they took to get here
+    fn on_player_enter(&mut self, _move_pos: TilePos) -> PostOperation {
+        PostOperation::new_empty()
+    }
+
+    /// Region and position this tile sends the player to, if it's a warp
+    fn warp_target(&self) -> Option<(&String, TilePos)> {
+        None
+    }
+    /// Points a warp somewhere else. Does nothing for other tiles
+    fn set_warp_target(&mut self, _region: String, _pos: TilePos) {}
 
     /// For selecting different animations based on the current state
     fn update_anim(&mut self) {
         self.get_anim_mut().select("base").unwrap();
     }
 
-    fn draw(
-        &mut self,
-        graphics: &mut Graphics2D,
-        manager: &mut ImgManager,
-        clock: &Clock,
-        camera: &Camera,
-    ) {
+    fn draw(&mut self, renderer: &mut dyn Renderer, clock: &Clock, camera: &Camera) {
         let color = self.draw_color();
         let pos = self.get_pos();
         self.get_anim_mut().draw_overworld(
-            graphics,
-            manager,
+            renderer,
             clock,
             camera.rect_from_offset(
-                pos,
+                pos.into(),
                 (1.0, SPRITE_EXTENSION_HEIGHT).into(),
                 (0.0, 1.0 - SPRITE_EXTENSION_HEIGHT).into(),
             ),
@@ -80,31 +141,9 @@ pub trait Tile: Debug {
         Color::WHITE
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile>;
-    fn pick(&self) -> Box<dyn Tile>;
-
-    fn next(&self) -> Box<dyn Tile>;
-    fn cycle(&self) -> Box<dyn Tile> {
-        let next_tile = self.next();
-        println!("{}", format!("{:?}", next_tile).split_once(' ').unwrap().0);
-        return next_tile;
-    }
-}
-
-fn get_default_anim(frame: (u16, u16)) -> Animation {
-    let mut frames: HashMap<String, (bool, Vec<(u16, u16)>)> = HashMap::new();
-
-    frames.insert(String::from("base"), (true, vec![frame]));
-
-    anim_with_frames(frames)
-}
-
-fn anim_with_frames(frames: HashMap<String, (bool, Vec<(u16, u16)>)>) -> Animation {
-    Animation::new(
-        Img::new(String::from("assets/img/tiles.png")),
-        (7, 10),
-        frames,
-        (5, 0),
-        100,
-    )
+    /// A tile of the same type at `pos`, with the same properties. Used to draw with in the editor.
+    /// New types go in [`registry::TILES`] so the editor lists them
+    ///
+    /// [`registry::TILES`]: super::registry::TILES
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile>;
 }
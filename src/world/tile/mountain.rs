@@ -1,22 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{world::{
-    GamePos, Tile, Direction,
-}, draw::animation::{Animation, self}};
-
-use super::get_default_anim;
+use crate::{
+    draw::{animation::Animation, sprite},
+    world::{Direction, Tile, TilePos},
+};
 
 // Boulder
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Boulder {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
+    direction: Direction,
 }
 
 #[typetag::serde]
 impl Tile for Boulder {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -24,31 +25,29 @@ impl Tile for Boulder {
         &mut self.anim
     }
 
-    fn block_movement(&self) -> bool {
-        true
+    fn variant(&self) -> Direction {
+        self.direction
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(CliffFace::new(GamePos::origin()))
+    fn auto_tiles(&self) -> bool {
+        true
     }
 
-    fn create(&self, pos: GamePos, variant: Direction) -> Box<dyn Tile> {
-        Box::new(Boulder::new(pos, variant))
+    fn block_movement(&self) -> bool {
+        true
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
+    fn create(&self, pos: TilePos, variant: Direction) -> Box<dyn Tile> {
+        Box::new(Boulder::new(pos, variant))
     }
 }
 
 impl Boulder {
-    pub fn new(pos: GamePos, direction: Direction) -> Boulder {
+    pub fn new(pos: TilePos, direction: Direction) -> Boulder {
         Boulder {
             pos,
-            anim: get_default_anim(animation::match_directions(direction, (4, 10))),
+            anim: sprite::tile("Boulder", direction),
+            direction,
         }
     }
 }
@@ -57,13 +56,14 @@ impl Boulder {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CliffFace {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
 #[typetag::serde]
 impl Tile for CliffFace {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -71,31 +71,20 @@ impl Tile for CliffFace {
         &mut self.anim
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(Rock::new(GamePos::origin()))
-    }
-
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
         Box::new(CliffFace::new(pos))
     }
 
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
-
     fn block_movement(&self) -> bool {
         true
     }
 }
 
 impl CliffFace {
-    pub fn new(pos: GamePos) -> CliffFace {
+    pub fn new(pos: TilePos) -> CliffFace {
         CliffFace {
             pos,
-            anim: get_default_anim((0, 6)),
+            anim: sprite::tile("CliffFace", Direction::Center),
         }
     }
 }
@@ -104,13 +93,14 @@ impl CliffFace {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Rock {
-    pos: GamePos,
+    pos: TilePos,
+    #[serde(skip)]
     anim: Animation,
 }
 
 #[typetag::serde]
 impl Tile for Rock {
-    fn get_pos(&self) -> GamePos {
+    fn get_pos(&self) -> TilePos {
         self.pos
     }
 
@@ -122,27 +112,20 @@ impl Tile for Rock {
         true
     }
 
-    fn next(&self) -> Box<dyn Tile> {
-        Box::new(super::beehive::HoneyComb::new(GamePos::origin(), Direction::Center))
+    fn dissolves_in_acid(&self) -> bool {
+        true
     }
 
-    fn create(&self, pos: GamePos, _variant: Direction) -> Box<dyn Tile> {
+    fn create(&self, pos: TilePos, _variant: Direction) -> Box<dyn Tile> {
         Box::new(Rock::new(pos))
     }
-
-    fn pick(&self) -> Box<dyn Tile> {
-        Box::new(Self {
-            pos: GamePos::origin(),
-            anim: get_default_anim((0, 0)),
-        })
-    }
 }
 
 impl Rock {
-    pub fn new(pos: GamePos) -> Rock {
+    pub fn new(pos: TilePos) -> Rock {
         Rock {
             pos,
-            anim: get_default_anim((0, 4)),
+            anim: sprite::tile("Rock", Direction::Center),
         }
     }
 }
@@ -27,6 +27,12 @@ impl Clock {
         }
     }
 
+    /// A clock stopped at a given time, e.g. to start a test at night
+    pub fn at(hour: u8, day: u16, year: u16) -> Clock {
+        assert!(hour < HOURS_PER_DAY && day < DAYS_PER_YEAR);
+        Clock { hour, day, year }
+    }
+
     pub fn tick(&mut self) {
         self.hour += 1;
         self.format();
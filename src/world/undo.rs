@@ -0,0 +1,165 @@
+//! Turns taken in the current region, so a puzzle can be taken back a step at a time instead of
+//! reloading the region. Only lasts for the session, and starts over whenever the player changes region
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::{
+    data::DataManager, entity::player::Player, space::TilePos, story::StoryState, time::Clock,
+};
+
+/// Most turns that can be taken back
+const UNDO_LIMIT: usize = 100;
+
+/// Tiles as they were before they were changed, saved. None where there wasn't a tile
+pub type TileChanges = HashMap<TilePos, Option<Value>>;
+
+/// Entities as they were before they were changed. Entities are kept in a list whose order is
+/// their turn order, so they're known by where they were in it when the turn started
+pub struct EntityChanges {
+    /// Number of entities when the turn started
+    pub count: usize,
+    /// The entities that changed or were removed, saved
+    pub saved: HashMap<usize, Value>,
+    /// Where each entity in the list now was when the turn started. None for ones added since
+    pub origins: Vec<Option<usize>>,
+}
+
+impl EntityChanges {
+    /// Nothing changed yet, with `count` entities in the region
+    pub fn new(count: usize) -> EntityChanges {
+        EntityChanges {
+            count,
+            saved: HashMap::new(),
+            origins: (0..count).map(Some).collect(),
+        }
+    }
+}
+
+/// Everything in the region a turn changed, as it was before
+pub struct Journal {
+    pub tiles: TileChanges,
+    pub entities: EntityChanges,
+}
+
+/// What a turn changed, as it was before the turn. Only the tiles and entities the turn changed are kept
+pub struct Turn {
+    region: Option<Journal>,
+    player: Value,
+    clock: Value,
+    story: StoryState,
+}
+
+impl Turn {
+    /// Everything but the region, which is added with [`UndoStack::amend`] once the turn is over
+    pub fn capture(
+        player: &Player,
+        clock: &Clock,
+        story: &StoryState,
+    ) -> Result<Turn, serde_json::Error> {
+        Ok(Turn {
+            region: None,
+            player: serde_json::to_value(player)?,
+            clock: serde_json::to_value(clock)?,
+            story: story.clone(),
+        })
+    }
+
+    /// Puts the region back how it was and gives back everything else
+    pub fn restore(
+        self,
+        region: &mut DataManager,
+    ) -> Result<(Player, Clock, StoryState), serde_json::Error> {
+        let player = serde_json::from_value(self.player)?;
+        let clock = serde_json::from_value(self.clock)?;
+        if let Some(journal) = self.region {
+            region.restore_tiles(journal.tiles)?;
+            region.restore_entities(journal.entities)?;
+        }
+        Ok((player, clock, self.story))
+    }
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+    /// Region the turns were taken in
+    region: String,
+    turns: Vec<Turn>,
+}
+
+impl UndoStack {
+    /// Remembers a turn taken in `region`, forgetting the turns of any other region
+    pub fn push(&mut self, region: &str, turn: Turn) {
+        if self.region != region {
+            self.clear();
+            self.region = region.to_string();
+        }
+        if self.turns.len() == UNDO_LIMIT {
+            self.turns.remove(0);
+        }
+        self.turns.push(turn);
+    }
+
+    /// Adds what changed in `region` to the last turn, once it's over
+    pub fn amend(&mut self, region: &str, journal: Journal) {
+        if self.region != region {
+            return;
+        }
+        if let Some(turn) = self.turns.last_mut() {
+            turn.region.get_or_insert(journal);
+        }
+    }
+
+    /// The last turn taken in `region`, if that's where the turns were taken
+    pub fn pop(&mut self, region: &str) -> Option<Turn> {
+        if self.region != region {
+            return None;
+        }
+        self.turns.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    /// Keeps the turns taken in region `old` once it's been renamed to `new`
+    pub fn rename_region(&mut self, old: &str, new: &str) {
+        if self.region == old {
+            self.region = new.to_string();
+        }
+    }
+
+    /// Forgets the turns taken in a region that's been deleted
+    pub fn forget_region(&mut self, name: &str) {
+        if self.region == name {
+            self.clear();
+            self.region.clear();
+        }
+    }
+
+    /// Number of turns that can be taken back
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Number of tiles kept across every turn
+    pub fn saved_tiles(&self) -> usize {
+        self.journals().map(|journal| journal.tiles.len()).sum()
+    }
+
+    /// Number of entities kept across every turn
+    pub fn saved_entities(&self) -> usize {
+        self.journals()
+            .map(|journal| journal.entities.saved.len())
+            .sum()
+    }
+
+    fn journals(&self) -> impl Iterator<Item = &Journal> {
+        self.turns.iter().filter_map(|turn| turn.region.as_ref())
+    }
+}
@@ -0,0 +1,267 @@
+//! Renders small regions offscreen and compares them against the images in `tests/golden`.
+//! Run with `UPDATE_GOLDEN=1` to write new golden images after an intended change to how things look
+
+use std::{env, fs, path::PathBuf};
+
+use image::RgbaImage;
+use speedy2d::window::VirtualKeyCode;
+use wonderfill::world::{
+    command::Command,
+    data::DataManager,
+    dialogue::Dialogue,
+    entity::{
+        enemy::{AttackPattern, Enemy},
+        item::{Item, ItemKind},
+        utility::{Button, Lever, OneWay, PushBoulder},
+    },
+    signal::{Logic, Wiring},
+    sim::{Input, Simulation},
+    space::{Direction, TilePos},
+    tile::core::{BaseGround, BasePillar, Bridge, Moon, PressurePlate, Sun},
+    time::{Clock, HOURS_PER_DAY},
+};
+
+const WIDTH: u32 = 300;
+const HEIGHT: u32 = 300;
+
+/// How far apart a channel of two pixels can be before they count as different
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Fraction of the pixels that are allowed to differ
+const PIXEL_TOLERANCE: f32 = 0.001;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name))
+}
+
+fn assert_matches_golden(name: &str, actual: &RgbaImage) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        actual.save(&path).unwrap();
+        return;
+    }
+
+    let expected = match image::open(&path) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => panic!(
+            "Couldn't open golden image {}: {}. Run with UPDATE_GOLDEN=1 to create it",
+            path.display(),
+            e
+        ),
+    };
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{} is a different size to its golden image",
+        name
+    );
+
+    let different = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(e, a)| {
+            e.0.iter()
+                .zip(a.0.iter())
+                .any(|(e, a)| (*e as i16 - *a as i16).abs() > CHANNEL_TOLERANCE as i16)
+        })
+        .count();
+    let allowed = (expected.len() / 4) as f32 * PIXEL_TOLERANCE;
+    if different as f32 > allowed {
+        let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{} differs from its golden image in {} pixels. Got {}",
+            name,
+            different,
+            actual_path.display()
+        );
+    }
+}
+
+/// Renders the scene once in the day and once at night, which draws every sprite with its night frame
+fn assert_day_and_night_match_golden(name: &str, scene: fn() -> Simulation) {
+    let mut day = scene();
+    assert_matches_golden(&format!("{}_day", name), &day.render(WIDTH, HEIGHT));
+
+    let mut night = scene().with_clock(Clock::at(HOURS_PER_DAY - 1, 0, 0));
+    assert_matches_golden(&format!("{}_night", name), &night.render(WIDTH, HEIGHT));
+}
+
+/// Ground covering every tile within `radius` of the origin
+fn field(radius: i32) -> DataManager {
+    let mut mgr = DataManager::new(String::from("test"));
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            mgr.push_tile(Box::new(BaseGround::default(TilePos(x, y))));
+        }
+    }
+    mgr
+}
+
+#[test]
+fn player_on_ground() {
+    assert_day_and_night_match_golden("player_on_ground", || Simulation::new(field(3)));
+}
+
+#[test]
+fn sun_and_moon() {
+    assert_day_and_night_match_golden("sun_and_moon", || {
+        let mut region = field(3);
+        region.push_tile(Box::new(Sun::new(TilePos(-1, 1))));
+        region.push_tile(Box::new(Moon::new(TilePos(1, 1))));
+        Simulation::new(region)
+    });
+}
+
+#[test]
+fn pillar_in_front_covers_player() {
+    assert_day_and_night_match_golden("pillar_in_front_covers_player", || {
+        let mut region = field(3);
+        region.push_tile(Box::new(BasePillar::new(TilePos(0, 1))));
+        Simulation::new(region)
+    });
+}
+
+#[test]
+fn player_in_front_covers_pillar() {
+    assert_day_and_night_match_golden("player_in_front_covers_pillar", || {
+        let mut region = field(3);
+        region.push_tile(Box::new(BasePillar::new(TilePos(0, -1))));
+        Simulation::new(region)
+    });
+}
+
+#[test]
+fn entities_draw_over_tiles() {
+    assert_day_and_night_match_golden("entities_draw_over_tiles", || {
+        let mut region = field(3);
+        region.push_entity(Box::new(Button::with_effect(
+            TilePos(-1, 0),
+            TilePos(-1, 0),
+        )));
+        region.push_entity(Box::new(OneWay::new(TilePos(1, 0), Direction::Right)));
+        region.push_tile(Box::new(BasePillar::new(TilePos(1, 1))));
+        Simulation::new(region)
+    });
+}
+
+#[test]
+fn wired_switches_and_bridges() {
+    assert_day_and_night_match_golden("wired_switches_and_bridges", || {
+        let mut region = field(3);
+        region.push_entity(Box::new(Lever::on_channel(
+            TilePos(0, 1),
+            String::from("lever"),
+        )));
+        region.push_entity(Box::new(Lever::new(TilePos(-1, 1))));
+        region.push_tile(Box::new(PressurePlate::on_channel(
+            TilePos(1, 0),
+            String::from("plate"),
+        )));
+        region.push_tile(Box::new(PressurePlate::new(TilePos(1, 1))));
+        region.push_tile(Box::new(Bridge::new(TilePos(-1, -2))));
+        region.push_tile(Box::new(Bridge::wired(
+            TilePos(1, -2),
+            Wiring::new(vec![String::from("lever")], Logic::Any),
+        )));
+        let mut sim = Simulation::new(region);
+        // Pull the lever, then stand on a plate
+        sim.run([
+            Input::Move(Direction::Bottom),
+            Input::Move(Direction::Right),
+        ]);
+        sim
+    });
+}
+
+#[test]
+fn boulder_on_pressure_plate() {
+    assert_day_and_night_match_golden("boulder_on_pressure_plate", || {
+        let mut region = field(3);
+        region.push_tile(Box::new(PressurePlate::new(TilePos(2, 0))));
+        region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+        region.push_entity(Box::new(PushBoulder::new(TilePos(-1, 1))));
+        let mut sim = Simulation::new(region);
+        // Push one boulder onto the plate, keeping the camera where it was
+        sim.step(Input::Move(Direction::Right));
+        sim.with_camera_at(TilePos::origin().into())
+    });
+}
+
+#[test]
+fn telegraphed_attack() {
+    assert_day_and_night_match_golden("telegraphed_attack", || {
+        let mut region = field(3);
+        region.push_entity(Box::new(Enemy::new(TilePos(1, -1), AttackPattern::Sweep)));
+        let mut sim = Simulation::new(region);
+        sim.step(Input::Wait);
+        sim
+    });
+}
+
+#[test]
+fn items_and_inventory() {
+    assert_day_and_night_match_golden("items_and_inventory", || {
+        let mut region = field(3);
+        region.push_entity(Box::new(Item::new(TilePos(-1, 0), ItemKind::Key, 1)));
+        region.push_entity(Box::new(Item::new(TilePos(1, 0), ItemKind::Mast, 1)));
+        let mut sim = Simulation::new(region);
+        sim.world.player.inventory_mut().add(ItemKind::Key, 2);
+        sim.world
+            .player
+            .inventory_mut()
+            .add(ItemKind::FlightComputer, 1);
+        sim
+    });
+}
+
+#[test]
+fn camera_follows_position() {
+    let mut sim = Simulation::new(field(5))
+        .with_player_at(TilePos(2, 2))
+        .with_camera_at(TilePos(2, 2).into());
+    assert_matches_golden("camera_follows_position", &sim.render(WIDTH, HEIGHT));
+}
+
+#[test]
+fn message_box() {
+    let mut sim = Simulation::new(field(3));
+    Command::ShowText {
+        text: String::from("A message long enough to wrap onto a second line"),
+    }
+    .execute(&mut sim.world);
+    assert_matches_golden("message_box", &sim.render(WIDTH, HEIGHT));
+}
+
+#[test]
+fn dialogue_box() {
+    let mut sim = Simulation::new(field(3));
+    let dialogue = Dialogue::parse(
+        r#"{
+            "start": "hi",
+            "nodes": {
+                "hi": {
+                    "speaker": "Scientist",
+                    "text": "Have you seen my wrench?",
+                    "choices": [{ "text": "No" }, { "text": "Which one?" }]
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+    sim.world.open_dialogue(dialogue);
+    sim.world.move_dialogue_selection(1);
+    assert_matches_golden("dialogue_box", &sim.render(WIDTH, HEIGHT));
+}
+
+#[test]
+fn editor_overlay() {
+    let mut sim = Simulation::new(field(4));
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    sim.world.editor.direction = Direction::Right;
+    sim.world.editor.set_status(String::from("Channels: red"));
+    assert_matches_golden("editor_overlay", &sim.render(480, 360));
+}
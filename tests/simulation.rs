@@ -0,0 +1,1660 @@
+use std::path::PathBuf;
+
+use speedy2d::window::VirtualKeyCode;
+use wonderfill::world::{
+    command::Command,
+    data::DataManager,
+    dialogue::{Dialogue, DialogueError},
+    editor::{DrawItem, PromptKind, Tool},
+    entity::{
+        enemy::{AttackPattern, Enemy},
+        friendly::Npc,
+        item::{Item, ItemKind},
+        player::{Player, PlayerHat, MAX_HEALTH},
+        utility::{Button, Lever, OneWay, PushBoulder, Warp},
+    },
+    manifest,
+    prefab::Prefab,
+    registry::{self, Category},
+    save,
+    signal::{Logic, Wiring},
+    sim::{Input, Simulation},
+    space::{Direction, TilePos},
+    story::Condition,
+    tile::{
+        core::{BaseGround, Bridge, Checkpoint, Door, Grass, Moon, PressurePlate, Sun},
+        mountain::Rock,
+        Tile,
+    },
+    time::{Clock, HOURS_PER_DAY},
+    RespawnPoint, World,
+};
+
+use Direction::{Bottom as Down, Left, Right};
+
+/// Ground under each of `cells`, for entities placed off the corridor
+fn ground(region: &mut DataManager, cells: &[TilePos]) {
+    for &pos in cells {
+        region.push_tile(Box::new(BaseGround::default(pos)));
+    }
+}
+
+fn wiring(channels: &[&str], logic: Logic) -> Wiring {
+    Wiring::new(channels.iter().map(|c| c.to_string()).collect(), logic)
+}
+
+/// A row of ground from (0, 0) to (`len` - 1, 0) with `tile` on the end
+fn corridor(len: i32, tile: Box<dyn Tile>) -> DataManager {
+    let mut mgr = DataManager::new(String::from("test"));
+    for x in 0..len - 1 {
+        mgr.push_tile(Box::new(BaseGround::default(TilePos(x, 0))));
+    }
+    mgr.push_tile(tile);
+    mgr
+}
+
+fn night() -> Clock {
+    Clock::at(HOURS_PER_DAY - 1, 0, 0)
+}
+
+#[test]
+fn door_blocks_until_toggled() {
+    let mut sim = Simulation::new(corridor(3, Box::new(Door::new(TilePos(2, 0)))));
+
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+
+    Command::ToggleTile { pos: TilePos(2, 0) }.execute(&mut sim.world);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn button_opens_door() {
+    let mut region = corridor(4, Box::new(Door::new(TilePos(3, 0))));
+    region.push_tile(Box::new(BaseGround::default(TilePos(0, 1))));
+    region.push_entity(Box::new(Button::with_effect(TilePos(0, 1), TilePos(3, 0))));
+    let mut sim = Simulation::new(region);
+
+    // Pressing the button doesn't move the player onto it
+    sim.step(Input::Move(Down));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(sim.entity_at(TilePos(0, 1)).is_some());
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    sim.run([Input::Move(Right); 3]);
+    assert_eq!(sim.player_pos(), TilePos(3, 0));
+
+    // Pressing it again closes the door behind the player
+    sim.run([
+        Input::Move(Left),
+        Input::Move(Left),
+        Input::Move(Left),
+        Input::Move(Down),
+    ]);
+    sim.run([Input::Move(Right); 3]);
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn one_way_only_lets_player_through_one_way() {
+    let mut region = corridor(3, Box::new(BaseGround::default(TilePos(2, 0))));
+    region.push_entity(Box::new(OneWay::new(TilePos(1, 0), Direction::Right)));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+
+    sim.step(Input::Move(Left));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn sun_blocks_during_the_day() {
+    let mut sim = Simulation::new(corridor(2, Box::new(Sun::new(TilePos(1, 0)))));
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+
+    let mut sim =
+        Simulation::new(corridor(2, Box::new(Sun::new(TilePos(1, 0))))).with_clock(night());
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn moon_blocks_at_night() {
+    let mut sim = Simulation::new(corridor(2, Box::new(Moon::new(TilePos(1, 0)))));
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+
+    let mut sim =
+        Simulation::new(corridor(2, Box::new(Moon::new(TilePos(1, 0))))).with_clock(night());
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+}
+
+#[test]
+fn sun_opens_once_night_falls() {
+    let mut sim = Simulation::new(corridor(2, Box::new(Sun::new(TilePos(1, 0)))));
+
+    // Bumping into the sun still passes time
+    while sim.world.clock.is_day() {
+        sim.step(Input::Move(Right));
+        assert_eq!(sim.player_pos(), TilePos(0, 0));
+    }
+    assert_eq!(sim.turns(), HOURS_PER_DAY as usize / 2);
+
+    // Tiles catch up with the clock at the end of a turn
+    sim.step(Input::Wait);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+/// A corridor with a jabbing enemy two steps to the right of the player
+fn enemy_ahead() -> Simulation {
+    let mut region = corridor(4, Box::new(BaseGround::default(TilePos(3, 0))));
+    region.push_tile(Box::new(BaseGround::default(TilePos(0, 1))));
+    region.push_entity(Box::new(Enemy::new(TilePos(2, 0), AttackPattern::Jab)));
+    Simulation::new(region)
+}
+
+#[test]
+fn enemy_telegraphs_before_hitting() {
+    let mut sim = enemy_ahead();
+
+    // Walks up, then aims at the player
+    sim.step(Input::Wait);
+    assert!(sim.entity_at(TilePos(1, 0)).is_some());
+    sim.step(Input::Wait);
+    let attack = sim.entity_at(TilePos(1, 0)).unwrap().attack().unwrap();
+    assert_eq!(attack.cells, vec![TilePos(0, 0)]);
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+
+    sim.step(Input::Wait);
+    assert_eq!(sim.world.player.health(), MAX_HEALTH - 1);
+    assert!(sim.entity_at(TilePos(1, 0)).unwrap().attack().is_none());
+}
+
+#[test]
+fn stepping_out_of_a_telegraph_dodges_it() {
+    let mut sim = enemy_ahead();
+    sim.run([Input::Wait, Input::Wait]);
+
+    sim.step(Input::Move(Down));
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+}
+
+#[test]
+fn walking_into_an_enemy_defeats_it() {
+    let mut sim = enemy_ahead();
+    sim.run([Input::Wait, Input::Wait]);
+
+    // Beats it before the telegraphed attack lands
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(sim.entity_at(TilePos(1, 0)).is_none());
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+}
+
+#[test]
+fn running_out_of_health_sends_the_player_back_to_spawn() {
+    let mut region = corridor(3, Box::new(BaseGround::default(TilePos(2, 0))));
+    region.push_tile(Box::new(BaseGround::default(TilePos(2, -1))));
+    region.push_entity(Box::new(Enemy::new(TilePos(2, -1), AttackPattern::Burst)));
+    let mut sim = Simulation::new(region).with_player_at(TilePos(2, 0));
+
+    // Aims, hits, rests, and again
+    for _ in 0..MAX_HEALTH {
+        sim.run([Input::Wait, Input::Wait, Input::Wait]);
+    }
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+}
+
+/// Keeps regions saved by checkpoints out of the real saves folder
+fn use_temp_saves() {
+    manifest::use_saves_dir(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("saves"));
+}
+
+#[test]
+fn saving_a_region_that_cant_be_written_says_why() {
+    use_temp_saves();
+    // A folder where the region file should be stops it being written
+    std::fs::create_dir_all(manifest::region_path("save_blocked")).unwrap();
+    let mut region = corridor(1, Box::new(BaseGround::default(TilePos(0, 0))));
+    region.set_name(String::from("save_blocked"));
+    let mut sim = Simulation::new(region);
+
+    assert!(sim.world.mgr.save_region().is_err());
+    sim.world.save_region();
+    assert!(sim
+        .world
+        .editor
+        .status()
+        .unwrap()
+        .starts_with("Couldn't save save_blocked"));
+}
+
+#[test]
+fn stepping_on_a_checkpoint_records_it() {
+    use_temp_saves();
+    let mut region = corridor(3, Box::new(Checkpoint::new(TilePos(2, 0))));
+    region.set_name(String::from("checkpoint_records"));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    let expected = RespawnPoint {
+        region: String::from("checkpoint_records"),
+        pos: TilePos(2, 0),
+    };
+    assert_eq!(sim.world.checkpoint, Some(expected));
+    assert!(manifest::region_path("checkpoint_records").exists());
+}
+
+#[test]
+fn defeat_respawns_at_checkpoint_with_region_reloaded() {
+    use_temp_saves();
+    let mut region = corridor(4, Box::new(Door::new(TilePos(3, 0))));
+    region.set_name(String::from("checkpoint_respawn"));
+    region.push_tile(Box::new(Checkpoint::new(TilePos(1, 0))));
+    region.push_tile(Box::new(BaseGround::default(TilePos(1, 1))));
+    let mut sim = Simulation::new(region);
+    sim.step(Input::Move(Right));
+
+    // Things change after the checkpoint saved the region
+    Command::ToggleTile { pos: TilePos(3, 0) }.execute(&mut sim.world);
+    sim.world
+        .mgr
+        .push_entity(Box::new(Enemy::new(TilePos(1, 1), AttackPattern::Jab)));
+    sim.world.player.hurt(MAX_HEALTH - 1);
+    sim.run([Input::Wait, Input::Wait]);
+
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+    assert!(sim.entity_at(TilePos(1, 1)).is_none());
+    assert!(sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn respawn_falls_back_to_spawn_when_the_checkpoint_region_is_gone() {
+    use_temp_saves();
+    let mut region = corridor(4, Box::new(BaseGround::default(TilePos(3, 0))));
+    region.set_name(String::from("checkpoint_fallback"));
+    let mut sim = Simulation::new(region).with_player_at(TilePos(3, 0));
+    sim.world
+        .manifest
+        .register(String::from("checkpoint_fallback"), TilePos(1, 0));
+    sim.world.checkpoint = Some(RespawnPoint {
+        region: String::from("checkpoint_never_saved"),
+        pos: TilePos(50, 50),
+    });
+
+    Command::Respawn.execute(&mut sim.world);
+    assert_eq!(sim.world.mgr.get_name(), "checkpoint_fallback");
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn renaming_the_checkpoint_region_keeps_the_checkpoint() {
+    use_temp_saves();
+    // Left over from the last run
+    let _ = std::fs::remove_file(manifest::region_path("checkpoint_new_name"));
+    let mut region = corridor(4, Box::new(Checkpoint::new(TilePos(2, 0))));
+    region.set_name(String::from("checkpoint_old_name"));
+    let mut sim = Simulation::new(region);
+    sim.world
+        .manifest
+        .register(String::from("checkpoint_old_name"), TilePos(0, 0));
+    sim.run([Input::Move(Right), Input::Move(Right), Input::Move(Right)]);
+
+    sim.world
+        .rename_region("checkpoint_old_name", "checkpoint_new_name")
+        .unwrap();
+    let expected = RespawnPoint {
+        region: String::from("checkpoint_new_name"),
+        pos: TilePos(2, 0),
+    };
+    assert_eq!(sim.world.checkpoint, Some(expected));
+    assert!(sim.world.undo());
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+
+    Command::Respawn.execute(&mut sim.world);
+    assert_eq!(sim.world.mgr.get_name(), "checkpoint_new_name");
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn warps_only_go_to_valid_region_names() {
+    use_temp_saves();
+    let mut outside = corridor(2, Box::new(BaseGround::default(TilePos(1, 0))));
+    outside.set_name(String::from("outside_saves"));
+    let path = manifest::region_path("../outside_saves");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    save::write(&path, &outside).unwrap();
+
+    let mut region = corridor(2, Box::new(BaseGround::default(TilePos(1, 0))));
+    region.set_name(String::from("warp_name_check"));
+    let mut sim = Simulation::new(region);
+    Command::Warp {
+        region: String::from("../outside_saves"),
+        pos: TilePos(1, 0),
+    }
+    .execute(&mut sim.world);
+    assert_eq!(sim.world.mgr.get_name(), "warp_name_check");
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+}
+
+/// Unlocks `hat` and puts it on
+fn wearing(mut sim: Simulation, hat: PlayerHat) -> Simulation {
+    Command::UnlockHat { hat }.execute(&mut sim.world);
+    sim
+}
+
+#[test]
+fn hats_only_cycle_through_unlocked_ones() {
+    let mut sim = Simulation::new(corridor(1, Box::new(BaseGround::default(TilePos(0, 0)))));
+    sim.world.switch_hat();
+    assert_eq!(sim.world.player.hat(), PlayerHat::None);
+
+    sim.world.player.unlock_hat(PlayerHat::Teardrop);
+    sim.world.player.unlock_hat(PlayerHat::Helmet);
+    sim.world.switch_hat();
+    assert_eq!(sim.world.player.hat(), PlayerHat::Helmet);
+    sim.world.switch_hat();
+    assert_eq!(sim.world.player.hat(), PlayerHat::Teardrop);
+    sim.world.switch_hat();
+    assert_eq!(sim.world.player.hat(), PlayerHat::None);
+    assert_eq!(sim.turns(), 0);
+}
+
+#[test]
+fn teardrop_hops_over_what_blocks_the_way() {
+    let mut region = corridor(2, Box::new(Door::new(TilePos(1, 0))));
+    region.push_tile(Box::new(BaseGround::default(TilePos(2, 0))));
+    let mut sim = Simulation::new(region);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+
+    let mut sim = wearing(sim, PlayerHat::Teardrop);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+
+    // Nowhere to land
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn acid_dissolves_rock() {
+    let sim = Simulation::new(corridor(2, Box::new(Rock::new(TilePos(1, 0)))));
+    let mut sim = wearing(sim, PlayerHat::Acid);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(!sim.tile_at(TilePos(1, 0)).unwrap().block_movement());
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn acid_dissolves_boulders_that_are_stuck() {
+    let mut region = corridor(3, Box::new(Rock::new(TilePos(2, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    let mut sim = wearing(Simulation::new(region), PlayerHat::Acid);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(sim.entity_at(TilePos(1, 0)).is_none());
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn helmet_softens_hits() {
+    let mut sim = wearing(enemy_ahead(), PlayerHat::Helmet);
+    sim.run([Input::Wait, Input::Wait, Input::Wait]);
+    assert_eq!(sim.world.player.health(), MAX_HEALTH);
+}
+
+#[test]
+fn acid_splashes_creatures_beside_the_one_attacked() {
+    let mut region = corridor(2, Box::new(BaseGround::default(TilePos(1, 0))));
+    for y in [-1, 1] {
+        region.push_tile(Box::new(BaseGround::default(TilePos(1, y))));
+    }
+    for y in [-1, 0, 1] {
+        region.push_entity(Box::new(Enemy::new(TilePos(1, y), AttackPattern::Jab)));
+    }
+    let mut sim = wearing(Simulation::new(region), PlayerHat::Acid);
+
+    sim.step(Input::Move(Right));
+    for y in [-1, 0, 1] {
+        assert!(sim.entity_at(TilePos(1, y)).is_none());
+    }
+}
+
+#[test]
+fn stepping_on_an_item_picks_it_up() {
+    let mut region = corridor(3, Box::new(BaseGround::default(TilePos(2, 0))));
+    region.push_entity(Box::new(Item::new(TilePos(1, 0), ItemKind::Key, 2)));
+    region.push_entity(Box::new(Item::new(TilePos(2, 0), ItemKind::Key, 1)));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(sim.entity_at(TilePos(1, 0)).is_none());
+    assert_eq!(sim.world.player.inventory().count(ItemKind::Key), 2);
+
+    // Keys stack
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.world.player.inventory().count(ItemKind::Key), 3);
+}
+
+#[test]
+fn unique_items_are_only_held_once() {
+    let mut sim = Simulation::new(corridor(1, Box::new(BaseGround::default(TilePos(0, 0)))));
+    let give = Command::GiveItem {
+        item: ItemKind::Mast,
+        count: 2,
+    };
+    give.execute(&mut sim.world);
+    give.execute(&mut sim.world);
+    assert_eq!(sim.world.player.inventory().count(ItemKind::Mast), 1);
+
+    Command::TakeItem {
+        item: ItemKind::Mast,
+        count: 2,
+    }
+    .execute(&mut sim.world);
+    assert!(sim.world.player.inventory().has(ItemKind::Mast));
+}
+
+#[test]
+fn locked_door_uses_up_a_key() {
+    let mut sim = Simulation::new(corridor(
+        3,
+        Box::new(Door::locked(TilePos(2, 0), ItemKind::Key)),
+    ));
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert!(sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+
+    sim.world.player.inventory_mut().add(ItemKind::Key, 1);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(sim.world.player.inventory().is_empty());
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn door_needing_a_part_keeps_it() {
+    let mut sim = Simulation::new(corridor(
+        2,
+        Box::new(Door::locked(TilePos(1, 0), ItemKind::Engine)),
+    ));
+    sim.world.player.inventory_mut().add(ItemKind::Engine, 1);
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(sim.world.player.inventory().has(ItemKind::Engine));
+}
+
+#[test]
+fn inventory_is_saved_with_the_player() {
+    let mut player = Player::new();
+    player.inventory_mut().add(ItemKind::FlightComputer, 1);
+    player.inventory_mut().add(ItemKind::Key, 4);
+
+    let saved = serde_json::to_string(&player).unwrap();
+    let loaded: Player = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded.inventory(), player.inventory());
+}
+
+/// The scientist standing to the right of the player
+fn scientist() -> Simulation {
+    let mut region = corridor(2, Box::new(BaseGround::default(TilePos(1, 0))));
+    region.push_entity(Box::new(Npc::new(TilePos(1, 0), String::from("scientist"))));
+    Simulation::new(region)
+}
+
+#[test]
+fn talking_pauses_the_overworld() {
+    let mut sim = scientist();
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(sim.world.dialogue.is_some());
+
+    sim.run([Input::Wait, Input::Move(Left)]);
+    assert_eq!(sim.turns(), 1);
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+}
+
+#[test]
+fn choices_lead_through_the_conversation() {
+    let mut sim = scientist();
+    sim.step(Input::Move(Right));
+
+    // What are you building?
+    sim.step(Input::Choose(0));
+    assert!(sim.world.story.flag("heard_about_vessel"));
+    assert_eq!(sim.world.story.quest_stage("vessel"), 1);
+    sim.step(Input::Choose(0));
+    // Sounds dangerous
+    sim.step(Input::Choose(1));
+    assert!(sim.world.player.has_hat(PlayerHat::Helmet));
+    sim.step(Input::Choose(0));
+    assert!(sim.world.dialogue.is_none());
+
+    // Doesn't explain twice
+    sim.step(Input::Move(Right));
+    let conversation = sim.world.dialogue.as_ref().unwrap();
+    assert_eq!(conversation.choices(&sim.world).len(), 1);
+}
+
+#[test]
+fn choices_can_need_an_item() {
+    let mut sim = scientist();
+    sim.world.player.inventory_mut().add(ItemKind::Engine, 1);
+    sim.step(Input::Move(Right));
+
+    sim.step(Input::Choose(1));
+    assert!(sim.world.story.flag("gave_engine"));
+    assert!(!sim.world.player.inventory().has(ItemKind::Engine));
+
+    // Picking a choice that isn't there does nothing
+    sim.step(Input::Choose(5));
+    assert!(sim.world.dialogue.is_some());
+}
+
+#[test]
+fn dialogue_leading_nowhere_is_rejected() {
+    let json = r#"{ "start": "hi", "nodes": { "hi": { "text": "Hi", "next": "bye" } } }"#;
+    match Dialogue::parse(json) {
+        Err(DialogueError::MissingNode(node)) => assert_eq!(node, "bye"),
+        other => panic!("Expected a missing node, got {:?}", other),
+    }
+}
+
+#[test]
+fn dialogue_names_stay_in_the_dialogue_folder() {
+    for name in ["../../Cargo", "/etc/passwd", ""] {
+        match Dialogue::from_file(name) {
+            Err(DialogueError::InvalidName(invalid)) => assert_eq!(invalid, name),
+            other => panic!("Expected {:?} to be rejected, got {:?}", name, other),
+        }
+    }
+}
+
+#[test]
+fn every_dialogue_file_loads() {
+    for entry in std::fs::read_dir("assets/dialogue").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        if let Err(e) = Dialogue::from_file(name) {
+            panic!("Couldn't load dialogue {}: {:?}", name, e);
+        }
+    }
+}
+
+#[test]
+fn door_follows_a_story_flag() {
+    let condition = Condition::Flag {
+        flag: String::from("gate_open"),
+        value: true,
+    };
+    let mut sim = Simulation::new(corridor(
+        2,
+        Box::new(Door::open_when(TilePos(1, 0), condition)),
+    ));
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+
+    Command::SetFlag {
+        flag: String::from("gate_open"),
+        value: true,
+    }
+    .execute(&mut sim.world);
+    sim.step(Input::Wait);
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn commands_track_counters_and_quests() {
+    let mut sim = Simulation::new(corridor(1, Box::new(BaseGround::default(TilePos(0, 0)))));
+    let fail = Command::AddToCounter {
+        counter: String::from("puzzle_failures"),
+        by: 1,
+    };
+    fail.execute(&mut sim.world);
+    fail.execute(&mut sim.world);
+    assert_eq!(sim.world.story.counter("puzzle_failures"), 2);
+
+    let hint = Command::If {
+        condition: Condition::Counter {
+            counter: String::from("puzzle_failures"),
+            min: Some(2),
+            max: None,
+        },
+        then: vec![Command::SetQuestStage {
+            quest: String::from("puzzle"),
+            stage: 1,
+        }],
+        otherwise: Vec::new(),
+    };
+    hint.execute(&mut sim.world);
+    assert_eq!(sim.world.story.quest_stage("puzzle"), 1);
+    assert!(sim.world.check(&Condition::Quest {
+        quest: String::from("puzzle"),
+        min: Some(1),
+        max: Some(1),
+    }));
+}
+
+#[test]
+fn button_toggles_a_wired_door() {
+    let mut region = corridor(
+        4,
+        Box::new(Door::wired(TilePos(3, 0), wiring(&["gate"], Logic::Toggle))),
+    );
+    ground(&mut region, &[TilePos(0, 1)]);
+    region.push_entity(Box::new(Button::on_channel(
+        TilePos(0, 1),
+        String::from("gate"),
+    )));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Down));
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    // Stays open after the button comes back up, until it's pressed again
+    sim.step(Input::Wait);
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+    sim.step(Input::Move(Down));
+    assert!(sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn pressure_plate_holds_a_door_open() {
+    let mut region = corridor(
+        4,
+        Box::new(Door::wired(TilePos(3, 0), wiring(&["plate"], Logic::Any))),
+    );
+    region.push_tile(Box::new(PressurePlate::on_channel(
+        TilePos(1, 0),
+        String::from("plate"),
+    )));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Right));
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    // Stepping off lets the door shut in the player's face
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+}
+
+#[test]
+fn door_needs_all_of_its_levers() {
+    let mut region = corridor(
+        4,
+        Box::new(Door::wired(TilePos(3, 0), wiring(&["a", "b"], Logic::All))),
+    );
+    ground(&mut region, &[TilePos(0, 1), TilePos(1, 1)]);
+    region.push_entity(Box::new(Lever::on_channel(
+        TilePos(0, 1),
+        String::from("a"),
+    )));
+    region.push_entity(Box::new(Lever::on_channel(
+        TilePos(1, 1),
+        String::from("b"),
+    )));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Down));
+    assert!(sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    sim.run([Input::Move(Right), Input::Move(Down)]);
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    // Pulling one back closes it again
+    sim.step(Input::Move(Down));
+    assert!(sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn timed_door_closes_after_a_while() {
+    let mut region = corridor(
+        4,
+        Box::new(Door::wired(
+            TilePos(3, 0),
+            wiring(&["gate"], Logic::Timed { turns: 2 }),
+        )),
+    );
+    ground(&mut region, &[TilePos(0, 1)]);
+    region.push_entity(Box::new(Button::on_channel(
+        TilePos(0, 1),
+        String::from("gate"),
+    )));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Down), Input::Wait, Input::Wait]);
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+
+    sim.step(Input::Wait);
+    assert!(sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn bridge_is_laid_while_the_sun_is_up() {
+    let region = || {
+        let mut region = corridor(4, Box::new(BaseGround::default(TilePos(3, 0))));
+        region.push_tile(Box::new(Bridge::wired(
+            TilePos(2, 0),
+            wiring(&["day"], Logic::Any),
+        )));
+        let mut sun = Sun::new(TilePos(0, 1));
+        *sun.channel_mut().unwrap() = String::from("day");
+        region.push_tile(Box::new(sun));
+        region
+    };
+
+    let mut sim = Simulation::new(region());
+    sim.run([Input::Move(Right); 3]);
+    assert_eq!(sim.player_pos(), TilePos(3, 0));
+
+    // Dusk rather than night(), so the sun doesn't come back up partway through
+    let mut sim = Simulation::new(region()).with_clock(Clock::at(HOURS_PER_DAY / 2, 0, 0));
+    sim.run([Input::Move(Right); 3]);
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+}
+
+#[test]
+fn wired_warp_only_works_while_powered() {
+    let mut region = corridor(3, Box::new(BaseGround::default(TilePos(2, 0))));
+    region.push_entity(Box::new(Warp::wired(
+        TilePos(2, 0),
+        String::from("test"),
+        TilePos(0, 3),
+        wiring(&["warp"], Logic::Any),
+    )));
+    ground(&mut region, &[TilePos(1, 1)]);
+    region.push_entity(Box::new(Lever::on_channel(
+        TilePos(1, 1),
+        String::from("warp"),
+    )));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Wait, Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+
+    sim.run([Input::Move(Left), Input::Move(Down), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(0, 3));
+}
+
+#[test]
+fn links_are_saved_with_the_region() {
+    let mut region = corridor(
+        4,
+        Box::new(Door::wired(TilePos(3, 0), wiring(&["gate"], Logic::Toggle))),
+    );
+    ground(&mut region, &[TilePos(0, 1)]);
+    region.push_entity(Box::new(Button::on_channel(
+        TilePos(0, 1),
+        String::from("gate"),
+    )));
+    let saved = serde_json::to_string(&region).unwrap();
+    let loaded: DataManager = serde_json::from_str(&saved).unwrap();
+    let mut sim = Simulation::new(loaded);
+
+    sim.step(Input::Move(Down));
+    assert!(!sim.tile_at(TilePos(3, 0)).unwrap().block_movement());
+}
+
+/// Whether there's a pushable entity at each of `cells`
+fn boulders_at(sim: &Simulation, cells: &[TilePos]) -> bool {
+    cells
+        .iter()
+        .all(|&pos| matches!(sim.entity_at(pos), Some(entity) if entity.is_pushable()))
+}
+
+#[test]
+fn walking_into_a_boulder_pushes_it() {
+    let mut region = corridor(4, Box::new(BaseGround::default(TilePos(3, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(boulders_at(&sim, &[TilePos(2, 0)]));
+    assert!(sim.entity_at(TilePos(1, 0)).is_none());
+}
+
+#[test]
+fn boulder_stops_against_a_wall() {
+    let mut region = corridor(3, Box::new(Rock::new(TilePos(2, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(boulders_at(&sim, &[TilePos(1, 0)]));
+}
+
+#[test]
+fn row_of_boulders_moves_together() {
+    let mut region = corridor(5, Box::new(BaseGround::default(TilePos(4, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(2, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+    assert!(boulders_at(&sim, &[TilePos(3, 0), TilePos(4, 0)]));
+
+    // The end of the corridor stops the whole row
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(2, 0));
+    assert!(boulders_at(&sim, &[TilePos(3, 0), TilePos(4, 0)]));
+}
+
+#[test]
+fn boulder_wont_push_other_entities() {
+    let mut region = corridor(4, Box::new(BaseGround::default(TilePos(3, 0))));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    region.push_entity(Box::new(Lever::new(TilePos(2, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(boulders_at(&sim, &[TilePos(1, 0)]));
+}
+
+#[test]
+fn boulder_holds_a_pressure_plate_down() {
+    let mut region = corridor(
+        5,
+        Box::new(Door::wired(TilePos(4, 0), wiring(&["plate"], Logic::Any))),
+    );
+    region.push_tile(Box::new(PressurePlate::on_channel(
+        TilePos(2, 0),
+        String::from("plate"),
+    )));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Right), Input::Move(Left)]);
+    assert!(!sim.tile_at(TilePos(4, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn undo_takes_back_turns() {
+    let mut region = corridor(
+        5,
+        Box::new(Door::wired(TilePos(4, 0), wiring(&["plate"], Logic::Any))),
+    );
+    region.push_tile(Box::new(PressurePlate::on_channel(
+        TilePos(3, 0),
+        String::from("plate"),
+    )));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    let mut sim = Simulation::new(region);
+
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert!(boulders_at(&sim, &[TilePos(3, 0)]));
+    assert!(!sim.tile_at(TilePos(4, 0)).unwrap().block_movement());
+
+    sim.step(Input::Undo);
+    assert_eq!(sim.player_pos(), TilePos(1, 0));
+    assert!(boulders_at(&sim, &[TilePos(2, 0)]));
+    assert!(sim.tile_at(TilePos(4, 0)).unwrap().block_movement());
+
+    sim.step(Input::Undo);
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert!(boulders_at(&sim, &[TilePos(1, 0)]));
+
+    // Nothing left to take back
+    assert!(!sim.world.undo());
+}
+
+#[test]
+fn undo_on_a_large_region_only_keeps_what_changed() {
+    let mut region = DataManager::new(String::from("test"));
+    for x in 0..320 {
+        for y in 0..320 {
+            region.push_tile(Box::new(BaseGround::default(TilePos(x, y))));
+        }
+    }
+    region.push_tile(Box::new(PressurePlate::on_channel(
+        TilePos(3, 0),
+        String::from("plate"),
+    )));
+    region.push_tile(Box::new(Door::wired(
+        TilePos(6, 0),
+        wiring(&["plate"], Logic::Any),
+    )));
+    region.push_entity(Box::new(PushBoulder::new(TilePos(1, 0))));
+    region.push_entity(Box::new(Item::new(TilePos(2, 1), ItemKind::Key, 1)));
+    for x in 0..20 {
+        for y in 20..40 {
+            region.push_entity(Box::new(PushBoulder::new(TilePos(x, y))));
+        }
+    }
+    let mut sim = Simulation::new(region);
+    let before = serde_json::to_value(&sim.world.mgr).unwrap();
+
+    let mut inputs = vec![Input::Move(Right), Input::Move(Right)];
+    for _ in 0..19 {
+        inputs.extend([Input::Move(Down), Input::Move(Direction::Top)]);
+    }
+    sim.run(inputs.iter().copied());
+    assert!(!sim.tile_at(TilePos(6, 0)).unwrap().block_movement());
+    assert_eq!(sim.world.undo_len(), 40);
+    // The door and the plate, which change by themselves, and the tile stepped onto
+    assert!(sim.world.undo_saved_tiles() <= 3 * 40);
+    // The boulder pushed twice and the key picked up
+    assert_eq!(sim.world.undo_saved_entities(), 3);
+    assert_eq!(sim.world.player.inventory().count(ItemKind::Key), 1);
+
+    for _ in 0..40 {
+        assert!(sim.world.undo());
+    }
+    assert!(!sim.world.undo());
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert_eq!(serde_json::to_value(&sim.world.mgr).unwrap(), before);
+}
+
+#[test]
+fn undo_takes_back_tiles_changed_by_commands() {
+    let mut sim = Simulation::new(corridor(3, Box::new(Door::new(TilePos(2, 0)))));
+    sim.step(Input::Wait);
+    Command::ToggleTile { pos: TilePos(2, 0) }.execute(&mut sim.world);
+    sim.step(Input::Wait);
+    assert!(!sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+
+    sim.step(Input::Undo);
+    assert!(!sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+    sim.step(Input::Undo);
+    assert!(sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn undo_forgets_turns_after_a_respawn() {
+    let mut sim = Simulation::new(corridor(3, Box::new(BaseGround::default(TilePos(2, 0)))));
+    sim.run([Input::Move(Right), Input::Move(Right)]);
+    assert_eq!(sim.world.undo_len(), 2);
+
+    Command::Respawn.execute(&mut sim.world);
+    assert_eq!(sim.world.undo_len(), 0);
+}
+
+/// Names of everything in the editor's palette
+fn palette_names(world: &World) -> Vec<&'static str> {
+    world.editor.palette().map(DrawItem::name).collect()
+}
+
+#[test]
+fn editor_palette_lists_every_type() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    assert!(sim.world.editor.open);
+    let tiles = palette_names(&sim.world);
+    for name in ["BaseGround", "Bridge", "Door", "PressurePlate", "Rock"] {
+        assert!(tiles.contains(&name), "{} isn't in {:?}", name, tiles);
+    }
+
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    let entities = palette_names(&sim.world);
+    for name in ["Button", "Lever", "PushBoulder", "Warp"] {
+        assert!(entities.contains(&name), "{} isn't in {:?}", name, entities);
+    }
+}
+
+#[test]
+fn registry_kinds_make_their_own_type() {
+    for kind in registry::TILES {
+        let saved = serde_json::to_value(kind.make()).unwrap();
+        assert_eq!(saved["type"], kind.name());
+        let loaded: Box<dyn Tile> = serde_json::from_value(saved).unwrap();
+        assert_eq!(loaded.typetag_name(), kind.name());
+        assert_eq!(
+            registry::tile_kind(kind.name()).unwrap().display_name,
+            kind.display_name
+        );
+    }
+    for kind in registry::ENTITIES {
+        assert_eq!(
+            serde_json::to_value(kind.make()).unwrap()["type"],
+            kind.name()
+        );
+        assert_eq!(
+            registry::entity_kind(kind.name()).unwrap().display_name,
+            kind.display_name
+        );
+    }
+    assert!(registry::entity_kind("Player").is_none());
+
+    // Each type is only listed once
+    let mut names: Vec<_> = registry::TILES.iter().map(|kind| kind.name()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), registry::TILES.len());
+    let mut names: Vec<_> = registry::ENTITIES.iter().map(|kind| kind.name()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), registry::ENTITIES.len());
+
+    let mut shown: Vec<_> = registry::TILES
+        .iter()
+        .map(|kind| kind.display_name)
+        .chain(registry::ENTITIES.iter().map(|kind| kind.display_name))
+        .collect();
+    shown.sort_unstable();
+    shown.dedup();
+    assert_eq!(
+        shown.len(),
+        registry::TILES.len() + registry::ENTITIES.len()
+    );
+}
+
+#[test]
+fn editor_cycles_through_the_registry() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    let names: Vec<_> = registry::TILES.iter().map(|kind| kind.name()).collect();
+    assert_eq!(palette_names(&sim.world), names);
+
+    for name in names.iter().skip(1).chain(names.first()) {
+        sim.world.send_input_down(&VirtualKeyCode::T);
+        assert_eq!(sim.world.editor.draw_item.name(), *name);
+    }
+    assert_eq!(sim.world.editor.draw_item.display_name(), "Ground");
+    assert_eq!(
+        sim.world.editor.draw_item.category(),
+        Some(Category::Terrain)
+    );
+
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    let names: Vec<_> = registry::ENTITIES.iter().map(|kind| kind.name()).collect();
+    assert_eq!(palette_names(&sim.world), names);
+    sim.world.send_input_down(&VirtualKeyCode::T);
+    assert_eq!(sim.world.editor.draw_item.name(), names[1]);
+}
+
+#[test]
+fn editor_keys_only_work_while_it_is_open() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    assert_eq!(sim.world.editor.draw_item.name(), "BaseGround");
+
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    assert_eq!(sim.world.editor.draw_item.name(), "Button");
+}
+
+#[test]
+fn clicking_the_editor_picks_items_and_directions() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.render(400, 400);
+    sim.world.send_input_down(&VirtualKeyCode::E);
+
+    // Second palette row
+    assert!(sim.world.click_editor((300.0, 150.0)));
+    assert_eq!(
+        sim.world.editor.draw_item.name(),
+        palette_names(&sim.world)[1]
+    );
+
+    // Middle of the bottom row of the direction picker
+    assert!(sim.world.click_editor((234.0, 78.0)));
+    assert_eq!(sim.world.editor.direction, Direction::Bottom);
+
+    // Tool row
+    assert!(sim.world.click_editor((300.0, 100.0)));
+    assert_eq!(sim.world.editor.tool, Tool::Line);
+
+    // Entities tab
+    assert!(sim.world.click_editor((350.0, 10.0)));
+    assert_eq!(sim.world.editor.draw_item.name(), "Button");
+
+    assert!(!sim.world.click_editor((100.0, 100.0)));
+}
+
+#[test]
+fn prompt_takes_typed_channels() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    sim.world.send_input_down(&VirtualKeyCode::C);
+    // The C that opened the prompt isn't typed into it
+    for c in "cred, blue".chars() {
+        sim.world.send_char(c);
+    }
+    sim.world.send_input_down(&VirtualKeyCode::Backspace);
+    // Other keys go to the prompt rather than the editor
+    sim.world.send_input_down(&VirtualKeyCode::Z);
+    assert_eq!(sim.world.editor.prompt.as_ref().unwrap().text, "red, blu");
+
+    sim.world.send_input_down(&VirtualKeyCode::Return);
+    assert!(sim.world.editor.prompt.is_none());
+    assert_eq!(
+        sim.world.editor.channels,
+        Some(vec![String::from("red"), String::from("blu")])
+    );
+    assert_eq!(sim.world.editor.draw_item.name(), "BaseGround");
+
+    sim.world.send_input_down(&VirtualKeyCode::N);
+    assert_eq!(
+        sim.world.editor.prompt.as_ref().unwrap().kind,
+        PromptKind::NewRegion
+    );
+    assert!(sim.world.cancel_prompt());
+    assert!(!sim.world.cancel_prompt());
+}
+
+#[test]
+fn inspector_sets_properties() {
+    let mut sim = Simulation::new(corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))));
+    sim.world.editor.draw_item = DrawItem::Entity(Box::new(Button::default()));
+    sim.world.editor.set_property("channel", "red");
+    sim.world.editor.set_property("effect_pos", "[3, 4]");
+
+    let properties = sim.world.editor.draw_item.properties();
+    let property = |key: &str| properties.iter().find(|(k, _)| k == key).unwrap().1.clone();
+    assert_eq!(property("channel"), "red");
+    assert_eq!(property("effect_pos"), serde_json::json!([3, 4]));
+
+    // Values of the wrong type are turned down
+    sim.world.editor.set_property("pressed", "maybe");
+    assert!(sim
+        .world
+        .editor
+        .status()
+        .unwrap()
+        .starts_with("Couldn't set pressed"));
+}
+
+#[test]
+fn inspector_only_shows_what_is_set_while_making_a_region() {
+    let keys = |item: DrawItem| -> Vec<String> {
+        item.properties().into_iter().map(|(key, _)| key).collect()
+    };
+    let enemy = DrawItem::Entity(Box::new(Enemy::new(TilePos(0, 0), AttackPattern::Jab)));
+    assert_eq!(keys(enemy), ["damage", "pattern", "rest_turns"]);
+    let plate = DrawItem::Tile(Box::new(PressurePlate::new(TilePos(0, 0))));
+    assert_eq!(keys(plate), ["channel"]);
+
+    let door = DrawItem::Tile(Box::new(Door::wired(
+        TilePos(0, 0),
+        wiring(&["gate"], Logic::Timed { turns: 2 }),
+    )));
+    let properties = door.properties();
+    let (_, wiring) = properties.iter().find(|(key, _)| key == "wiring").unwrap();
+    let mut fields: Vec<_> = wiring.as_object().unwrap().keys().cloned().collect();
+    fields.sort_unstable();
+    assert_eq!(fields, ["channels", "inverted", "logic"]);
+}
+
+/// Presses Ctrl and `key` together
+fn ctrl(world: &mut World, key: VirtualKeyCode) {
+    world.send_input_down(&VirtualKeyCode::LControl);
+    world.send_input_down(&key);
+    world.send_input_up(&key);
+    world.send_input_up(&VirtualKeyCode::LControl);
+}
+
+/// Opens the editor with `item` picked
+fn editing(region: DataManager, item: DrawItem) -> Simulation {
+    let mut sim = Simulation::new(region);
+    sim.world.send_input_down(&VirtualKeyCode::E);
+    sim.world.editor.draw_item = item;
+    sim
+}
+
+fn is_rock(sim: &Simulation, pos: TilePos) -> bool {
+    sim.tile_at(pos).map(|tile| tile.typetag_name()) == Some("Rock")
+}
+
+#[test]
+fn a_stroke_is_undone_and_redone_at_once() {
+    let mut sim = editing(
+        corridor(3, Box::new(BaseGround::default(TilePos(2, 0)))),
+        DrawItem::Tile(Box::new(Rock::new(TilePos(0, 0)))),
+    );
+    sim.world.paint(TilePos(1, 0));
+    sim.world.paint(TilePos(2, 0));
+    sim.world.paint(TilePos(1, 0));
+    sim.world.editor.history.end_stroke();
+    sim.world.paint(TilePos(2, 1));
+    sim.world.editor.history.end_stroke();
+    assert_eq!(sim.world.editor.history.len(), 2);
+
+    ctrl(&mut sim.world, VirtualKeyCode::Z);
+    assert!(sim.tile_at(TilePos(2, 1)).is_none());
+    assert!(is_rock(&sim, TilePos(1, 0)));
+
+    ctrl(&mut sim.world, VirtualKeyCode::Z);
+    assert_eq!(
+        sim.tile_at(TilePos(1, 0)).unwrap().typetag_name(),
+        "BaseGround"
+    );
+    assert_eq!(
+        sim.tile_at(TilePos(2, 0)).unwrap().typetag_name(),
+        "BaseGround"
+    );
+    assert!(!sim.world.undo_edit());
+
+    ctrl(&mut sim.world, VirtualKeyCode::Y);
+    assert!(is_rock(&sim, TilePos(1, 0)) && is_rock(&sim, TilePos(2, 0)));
+    assert!(sim.tile_at(TilePos(2, 1)).is_none());
+
+    // A new edit can't be followed by redoing older ones
+    sim.world.erase(TilePos(0, 0));
+    assert!(!sim.world.redo_edit());
+    assert!(sim.world.undo_edit());
+    assert!(sim.tile_at(TilePos(0, 0)).is_some());
+}
+
+#[test]
+fn undoing_an_erase_puts_back_the_same_entity() {
+    let mut region = corridor(3, Box::new(Door::new(TilePos(2, 0))));
+    region.push_entity(Box::new(Button::with_effect(TilePos(1, 0), TilePos(2, 0))));
+    let mut sim = editing(
+        region,
+        DrawItem::Entity(Box::new(PushBoulder::new(TilePos(0, 0)))),
+    );
+    sim.world.erase(TilePos(1, 0));
+    sim.world.editor.history.end_stroke();
+    assert!(sim.entity_at(TilePos(1, 0)).is_none());
+
+    assert!(sim.world.undo_edit());
+    // Still opens the door it was wired to
+    sim.step(Input::Move(Right));
+    assert_eq!(sim.player_pos(), TilePos(0, 0));
+    assert_eq!(
+        sim.entity_at(TilePos(1, 0)).unwrap().typetag_name(),
+        "Button"
+    );
+    assert!(!sim.tile_at(TilePos(2, 0)).unwrap().block_movement());
+}
+
+#[test]
+fn property_edits_can_be_undone() {
+    let mut sim = editing(
+        corridor(2, Box::new(BaseGround::default(TilePos(1, 0)))),
+        DrawItem::Entity(Box::new(Button::default())),
+    );
+    sim.world.editor.set_property("channel", "red");
+    sim.world.editor.set_property("channel", "blue");
+    let channel = |sim: &Simulation| {
+        let properties = sim.world.editor.draw_item.properties();
+        properties
+            .into_iter()
+            .find(|(k, _)| k == "channel")
+            .unwrap()
+            .1
+    };
+
+    assert!(sim.world.undo_edit());
+    assert_eq!(channel(&sim), "red");
+    assert!(sim.world.undo_edit());
+    assert_eq!(channel(&sim), "");
+    assert!(sim.world.redo_edit());
+    assert_eq!(channel(&sim), "red");
+
+    // Only the kind of item it was set on can take it back
+    let boulder = DrawItem::Entity(Box::new(PushBoulder::new(TilePos(0, 0))));
+    let button = std::mem::replace(&mut sim.world.editor.draw_item, boulder);
+    assert!(!sim.world.undo_edit());
+    sim.world.editor.draw_item = button;
+    assert!(sim.world.undo_edit());
+    assert_eq!(channel(&sim), "");
+}
+
+#[test]
+fn edit_history_survives_switching_regions() {
+    use_temp_saves();
+    let mut region = corridor(2, Box::new(BaseGround::default(TilePos(1, 0))));
+    region.set_name(String::from("history_first"));
+    let mut sim = editing(region, DrawItem::Tile(Box::new(Rock::new(TilePos(0, 0)))));
+    sim.world.paint(TilePos(1, 0));
+    sim.world.editor.history.end_stroke();
+
+    let mut other = DataManager::new(String::from("history_second"));
+    ground(&mut other, &[TilePos(0, 0)]);
+    other.save_region().unwrap();
+    sim.world
+        .warp(&String::from("history_second"), TilePos(0, 0));
+    assert_eq!(sim.world.mgr.get_name(), "history_second");
+
+    // The edit is taken back in the other region's file, without leaving this one
+    assert!(sim.world.undo_edit());
+    assert_eq!(sim.world.mgr.get_name(), "history_second");
+    let mut first = DataManager::new(String::new());
+    first.load_region("history_first").unwrap();
+    assert_eq!(
+        first.get_tile(TilePos(1, 0)).unwrap().typetag_name(),
+        "BaseGround"
+    );
+
+    assert!(sim.world.redo_edit());
+    sim.world
+        .warp(&String::from("history_first"), TilePos(0, 0));
+    assert!(is_rock(&sim, TilePos(1, 0)));
+}
+
+/// Ground from (0, 0) to (`width` - 1, `height` - 1)
+fn floor(width: i32, height: i32) -> DataManager {
+    let mut mgr = DataManager::new(String::from("test"));
+    for x in 0..width {
+        for y in 0..height {
+            mgr.push_tile(Box::new(BaseGround::default(TilePos(x, y))));
+        }
+    }
+    mgr
+}
+
+fn rocks(sim: &Simulation, width: i32, height: i32) -> Vec<TilePos> {
+    let mut cells = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if is_rock(sim, TilePos(x, y)) {
+                cells.push(TilePos(x, y));
+            }
+        }
+    }
+    cells
+}
+
+#[test]
+fn lines_have_no_gaps() {
+    for &end in &[
+        TilePos(5, 2),
+        TilePos(-3, 7),
+        TilePos(-6, -6),
+        TilePos(0, -4),
+        TilePos(0, 0),
+    ] {
+        let line = TilePos(0, 0).line_to(end);
+        assert_eq!(line.first(), Some(&TilePos(0, 0)));
+        assert_eq!(line.last(), Some(&end));
+        assert_eq!(line.len() as i32, TilePos(0, 0).chebyshev_distance(end) + 1);
+        assert!(line
+            .windows(2)
+            .all(|pair| pair[0].chebyshev_distance(pair[1]) == 1));
+    }
+}
+
+#[test]
+fn brush_joins_up_fast_drags() {
+    let mut sim = editing(
+        floor(6, 4),
+        DrawItem::Tile(Box::new(Rock::new(TilePos(0, 0)))),
+    );
+    sim.world.brush_to(TilePos(0, 0), false);
+    sim.world.brush_to(TilePos(4, 2), false);
+    sim.world.editor.history.end_stroke();
+    assert_eq!(rocks(&sim, 6, 4), TilePos(0, 0).line_to(TilePos(4, 2)));
+
+    assert!(sim.world.undo_edit());
+    assert!(rocks(&sim, 6, 4).is_empty());
+}
+
+#[test]
+fn shape_tools_cover_the_drag() {
+    let mut sim = editing(
+        floor(5, 5),
+        DrawItem::Tile(Box::new(Rock::new(TilePos(0, 0)))),
+    );
+    sim.world.send_input_down(&VirtualKeyCode::B);
+    assert_eq!(sim.world.editor.tool, Tool::Line);
+    sim.world.use_tool(TilePos(0, 0), TilePos(4, 4), false);
+    assert_eq!(rocks(&sim, 5, 5).len(), 5);
+    assert!(sim.world.undo_edit());
+
+    sim.world.editor.tool = Tool::HollowRectangle;
+    sim.world.use_tool(TilePos(3, 3), TilePos(1, 1), false);
+    let hollow = rocks(&sim, 5, 5);
+    assert_eq!(hollow.len(), 8);
+    assert!(!hollow.contains(&TilePos(2, 2)));
+    assert!(sim.world.undo_edit());
+
+    sim.world.editor.tool = Tool::Rectangle;
+    sim.world.use_tool(TilePos(1, 1), TilePos(3, 3), false);
+    assert_eq!(rocks(&sim, 5, 5).len(), 9);
+    // Erasing a rectangle with the shape tool
+    sim.world.use_tool(TilePos(0, 0), TilePos(4, 0), true);
+    assert!((0..5).all(|x| sim.tile_at(TilePos(x, 0)).is_none()));
+    assert_eq!(sim.world.editor.history.len(), 2);
+}
+
+#[test]
+fn fill_stops_at_other_tiles() {
+    let mut region = floor(5, 3);
+    // A wall of rock down the middle
+    for y in 0..3 {
+        region.push_tile(Box::new(Rock::new(TilePos(2, y))));
+    }
+    let mut sim = editing(
+        region,
+        DrawItem::Tile(Box::new(Checkpoint::new(TilePos(0, 0)))),
+    );
+    sim.world.editor.tool = Tool::Fill;
+    sim.world.use_tool(TilePos(0, 1), TilePos(0, 1), false);
+
+    let is_checkpoint =
+        |sim: &Simulation, pos| sim.tile_at(pos).unwrap().typetag_name() == "Checkpoint";
+    assert!(
+        (0..3).all(|y| is_checkpoint(&sim, TilePos(0, y)) && is_checkpoint(&sim, TilePos(1, y)))
+    );
+    assert!((0..3).all(|y| is_rock(&sim, TilePos(2, y)) && !is_checkpoint(&sim, TilePos(3, y))));
+
+    // The empty space around the region goes on forever
+    sim.world.use_tool(TilePos(-1, 0), TilePos(-1, 0), false);
+    assert!(sim.tile_at(TilePos(-1, 0)).is_none());
+    assert_eq!(sim.world.editor.history.len(), 1);
+}
+
+#[test]
+fn big_fills_are_one_edit() {
+    let mut sim = editing(
+        floor(60, 60),
+        DrawItem::Tile(Box::new(Rock::new(TilePos(0, 0)))),
+    );
+    sim.world.editor.tool = Tool::Fill;
+    sim.world.use_tool(TilePos(0, 0), TilePos(0, 0), false);
+    assert!(is_rock(&sim, TilePos(59, 59)));
+    assert_eq!(sim.world.editor.history.len(), 1);
+    assert_eq!(
+        sim.world.editor.history.last_done().unwrap().describe(),
+        "3600 cells"
+    );
+
+    assert!(sim.world.undo_edit());
+    assert!((0..60).all(|i| !is_rock(&sim, TilePos(i, i))));
+}
+
+fn variant_at(sim: &Simulation, pos: TilePos) -> Direction {
+    sim.tile_at(pos).unwrap().variant()
+}
+
+#[test]
+fn auto_tiling_fits_a_block_together() {
+    let mut sim = editing(
+        floor(5, 5),
+        DrawItem::Tile(Box::new(Grass::new(TilePos(0, 0), Direction::Center))),
+    );
+    sim.world.send_input_down(&VirtualKeyCode::G);
+    assert!(sim.world.editor.auto_tile);
+    sim.world.editor.tool = Tool::Rectangle;
+    sim.world.use_tool(TilePos(1, 1), TilePos(3, 3), false);
+
+    let expected = [
+        [Direction::CornerTL, Direction::Top, Direction::CornerTR],
+        [Direction::Left, Direction::Center, Direction::Right],
+        [Direction::CornerBL, Direction::Bottom, Direction::CornerBR],
+    ];
+    for (y, row) in expected.iter().enumerate() {
+        for (x, &direction) in row.iter().enumerate() {
+            assert_eq!(
+                variant_at(&sim, TilePos(x as i32 + 1, y as i32 + 1)),
+                direction
+            );
+        }
+    }
+
+    // Taking out the top middle opens up the top of the center
+    sim.world.editor.tool = Tool::Brush;
+    sim.world.erase(TilePos(2, 1));
+    sim.world.editor.history.end_stroke();
+    assert_eq!(variant_at(&sim, TilePos(2, 2)), Direction::Top);
+
+    // Neighbours that were re-picked are undone along with the erase
+    assert!(sim.world.undo_edit());
+    assert_eq!(variant_at(&sim, TilePos(2, 1)), Direction::Top);
+    assert_eq!(variant_at(&sim, TilePos(2, 2)), Direction::Center);
+}
+
+#[test]
+fn without_auto_tiling_tiles_face_the_picked_direction() {
+    let mut sim = editing(
+        floor(3, 3),
+        DrawItem::Tile(Box::new(Grass::new(TilePos(0, 0), Direction::Center))),
+    );
+    sim.world.editor.direction = Direction::Left;
+    sim.world.paint(TilePos(1, 1));
+    sim.world.paint(TilePos(2, 1));
+    assert_eq!(variant_at(&sim, TilePos(1, 1)), Direction::Left);
+    assert_eq!(variant_at(&sim, TilePos(2, 1)), Direction::Left);
+}
+
+/// A saved field of the entity at `pos`
+fn entity_field(sim: &Simulation, pos: TilePos, field: &str) -> serde_json::Value {
+    serde_json::to_value(sim.entity_at(pos).unwrap()).unwrap()[field].clone()
+}
+
+/// Selects the rectangle from `corner` to `opposite` with the select tool
+fn select(sim: &mut Simulation, corner: TilePos, opposite: TilePos) {
+    sim.world.editor.tool = Tool::Select;
+    sim.world.use_tool(corner, opposite, false);
+}
+
+#[test]
+fn pasted_buttons_point_at_the_pasted_door() {
+    let mut region = floor(8, 4);
+    region.push_tile(Box::new(Door::new(TilePos(2, 1))));
+    region.push_entity(Box::new(Button::with_effect(TilePos(1, 1), TilePos(2, 1))));
+    let mut sim = editing(region, DrawItem::default_tile());
+    select(&mut sim, TilePos(2, 1), TilePos(1, 1));
+    ctrl(&mut sim.world, VirtualKeyCode::C);
+    assert_eq!(
+        sim.world.editor.clipboard.as_ref().unwrap().size,
+        TilePos(2, 1)
+    );
+
+    assert!(sim.world.paste(TilePos(4, 2)));
+    assert_eq!(sim.tile_at(TilePos(5, 2)).unwrap().typetag_name(), "Door");
+    assert_eq!(
+        entity_field(&sim, TilePos(4, 2), "effect_pos"),
+        serde_json::json!([5, 2])
+    );
+    // The original is left alone
+    assert_eq!(
+        entity_field(&sim, TilePos(1, 1), "effect_pos"),
+        serde_json::json!([2, 1])
+    );
+
+    assert!(sim.world.undo_edit());
+    assert!(sim.entity_at(TilePos(4, 2)).is_none());
+    assert_eq!(
+        sim.tile_at(TilePos(5, 2)).unwrap().typetag_name(),
+        "BaseGround"
+    );
+}
+
+#[test]
+fn clipboard_turns_and_flips() {
+    let mut region = floor(8, 4);
+    region.push_entity(Box::new(OneWay::new(TilePos(1, 1), Direction::Right)));
+    region.push_tile(Box::new(Grass::new(TilePos(2, 1), Direction::CornerTR)));
+    let mut sim = editing(region, DrawItem::default_tile());
+    select(&mut sim, TilePos(1, 1), TilePos(2, 1));
+    assert!(sim.world.copy_selection());
+
+    ctrl(&mut sim.world, VirtualKeyCode::R);
+    assert_eq!(
+        sim.world.editor.clipboard.as_ref().unwrap().size,
+        TilePos(1, 2)
+    );
+    sim.world.paste(TilePos(4, 0));
+    assert_eq!(entity_field(&sim, TilePos(4, 0), "direction"), "Bottom");
+    assert_eq!(variant_at(&sim, TilePos(4, 1)), Direction::CornerBR);
+
+    // Back to the way it was copied, then flipped
+    for _ in 0..3 {
+        ctrl(&mut sim.world, VirtualKeyCode::R);
+    }
+    ctrl(&mut sim.world, VirtualKeyCode::M);
+    sim.world.paste(TilePos(5, 3));
+    assert_eq!(variant_at(&sim, TilePos(5, 3)), Direction::CornerTL);
+    assert_eq!(entity_field(&sim, TilePos(6, 3), "direction"), "Left");
+}
+
+#[test]
+fn cutting_clears_the_selection_in_one_edit() {
+    let mut region = floor(4, 4);
+    region.push_entity(Box::new(PushBoulder::new(TilePos(2, 2))));
+    let mut sim = editing(region, DrawItem::default_tile());
+    select(&mut sim, TilePos(1, 1), TilePos(2, 2));
+    ctrl(&mut sim.world, VirtualKeyCode::X);
+    assert!(sim.entity_at(TilePos(2, 2)).is_none());
+    assert!(sim.tile_at(TilePos(1, 1)).is_none() && sim.tile_at(TilePos(3, 3)).is_some());
+    assert_eq!(sim.world.editor.history.len(), 1);
+
+    assert!(sim.world.paste(TilePos(0, 0)));
+    assert!(sim.entity_at(TilePos(1, 1)).is_some());
+    ctrl(&mut sim.world, VirtualKeyCode::Z);
+    ctrl(&mut sim.world, VirtualKeyCode::Z);
+    assert!(sim.entity_at(TilePos(2, 2)).is_some());
+    assert!(sim.tile_at(TilePos(1, 1)).is_some());
+}
+
+#[test]
+fn prefabs_are_saved_and_stamped_into_other_regions() {
+    use_temp_saves();
+    let mut region = floor(3, 3);
+    region.push_tile(Box::new(Rock::new(TilePos(1, 1))));
+    let mut sim = editing(region, DrawItem::default_tile());
+    select(&mut sim, TilePos(0, 0), TilePos(1, 1));
+    sim.world.copy_selection();
+    sim.world.send_input_down(&VirtualKeyCode::LControl);
+    sim.world.send_input_down(&VirtualKeyCode::S);
+    sim.world.send_input_up(&VirtualKeyCode::LControl);
+    for c in "shouse".chars() {
+        sim.world.send_char(c);
+    }
+    sim.world.submit_prompt();
+    assert_eq!(
+        Prefab::load("house").unwrap(),
+        *sim.world.editor.clipboard.as_ref().unwrap()
+    );
+
+    let mut other = editing(
+        DataManager::new(String::from("elsewhere")),
+        DrawItem::default_tile(),
+    );
+    other.world.send_input_down(&VirtualKeyCode::LControl);
+    other.world.send_input_down(&VirtualKeyCode::O);
+    for c in "ohouse".chars() {
+        other.world.send_char(c);
+    }
+    other.world.send_input_down(&VirtualKeyCode::Return);
+    assert!(other.world.paste(TilePos(10, 10)));
+    assert!(is_rock(&other, TilePos(11, 11)));
+    assert_eq!(
+        other.tile_at(TilePos(10, 10)).unwrap().typetag_name(),
+        "BaseGround"
+    );
+
+    assert!(Prefab::load("no_such_prefab").is_err());
+    assert!(Prefab::load("../house").is_err());
+}